@@ -0,0 +1,2 @@
+// 测试都在 tests/ 目录下，每个文件编译为独立的测试程序。
+// easy-fs 的块缓存是全局的且只按块号索引，同一进程中只能使用一个块设备。
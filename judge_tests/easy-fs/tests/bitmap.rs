@@ -0,0 +1,49 @@
+mod common;
+
+use common::{device, INODE_BITMAP_BLOCKS, TOTAL_BLOCKS};
+use easy_fs::EasyFileSystem;
+use std::collections::HashSet;
+
+const IMAGE: &str = "bitmap";
+
+#[test]
+fn alloc_inode_unique() {
+    let efs = EasyFileSystem::create(device(IMAGE), TOTAL_BLOCKS, INODE_BITMAP_BLOCKS);
+    let mut efs = efs.lock();
+    let mut seen = HashSet::new();
+    for _ in 0..200 {
+        assert!(seen.insert(efs.alloc_inode()), "分配到了重复的inode");
+    }
+}
+
+#[test]
+fn alloc_data_unique() {
+    let efs = EasyFileSystem::create(device(IMAGE), TOTAL_BLOCKS, INODE_BITMAP_BLOCKS);
+    let mut efs = efs.lock();
+    let mut seen = HashSet::new();
+    for _ in 0..1000 {
+        assert!(seen.insert(efs.alloc_data()), "分配到了重复的数据块");
+    }
+}
+
+#[test]
+fn dealloc_then_realloc_reuses() {
+    let efs = EasyFileSystem::create(device(IMAGE), TOTAL_BLOCKS, INODE_BITMAP_BLOCKS);
+    let mut efs = efs.lock();
+    let blocks: Vec<u32> = (0..10).map(|_| efs.alloc_data()).collect();
+    efs.dealloc_data(blocks[3]);
+    assert_eq!(efs.alloc_data(), blocks[3], "释放的数据块应当被优先重新分配");
+}
+
+#[test]
+fn file_blocks_are_released_by_clear() {
+    let efs = EasyFileSystem::create(device(IMAGE), TOTAL_BLOCKS, INODE_BITMAP_BLOCKS);
+    let root = EasyFileSystem::root_inode(&efs);
+    let file = root.create("data").unwrap();
+    let first = efs.lock().alloc_data();
+    efs.lock().dealloc_data(first);
+    file.write_at(0, &vec![1u8; 40 * 512]);
+    file.clear();
+    // clear 释放的块可以再次分配，第一个可用块应当回到写入前的位置
+    assert_eq!(efs.lock().alloc_data(), first, "clear 没有释放文件占用的数据块");
+}
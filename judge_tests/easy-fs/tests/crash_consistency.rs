@@ -0,0 +1,42 @@
+mod common;
+
+use common::{device, fresh_fs, image_path, pattern, read_all};
+use easy_fs::EasyFileSystem;
+use std::process::Command;
+
+const IMAGE: &str = "crash_consistency";
+const FILES: [(&str, usize, u8); 3] = [("small", 100, 1), ("medium", 5000, 2), ("large", 80 * 512, 3)];
+
+// 写入后不做任何额外的同步就“断电”，在新进程中重新打开镜像，
+// 已返回的写操作必须已经落盘
+#[test]
+fn synced_data_survives_crash() {
+    let root = fresh_fs(IMAGE);
+    for (name, len, seed) in FILES {
+        let file = root.create(name).unwrap();
+        file.write_at(0, &pattern(len, seed));
+    }
+
+    // 新进程中没有块缓存，只能看到块设备上的内容
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["verify_after_crash", "--exact", "--ignored", "--nocapture"])
+        .env("EASYFS_JUDGE_VERIFY", image_path(IMAGE))
+        .status()
+        .expect("无法启动校验进程");
+    assert!(status.success(), "重新打开镜像后文件内容不正确");
+}
+
+// 由 synced_data_survives_crash 在子进程中运行
+#[test]
+#[ignore]
+fn verify_after_crash() {
+    if std::env::var("EASYFS_JUDGE_VERIFY").is_err() {
+        return;
+    }
+    let efs = EasyFileSystem::open(device(IMAGE));
+    let root = EasyFileSystem::root_inode(&efs);
+    for (name, len, seed) in FILES {
+        let file = root.find(name).unwrap_or_else(|| panic!("重新打开后找不到文件 {}", name));
+        assert_eq!(read_all(&file), pattern(len, seed), "重新打开后文件 {} 的内容不正确", name);
+    }
+}
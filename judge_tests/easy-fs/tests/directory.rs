@@ -0,0 +1,45 @@
+mod common;
+
+use common::fresh_fs;
+
+const IMAGE: &str = "directory";
+
+#[test]
+fn ls_lists_created_files() {
+    let root = fresh_fs(IMAGE);
+    let names = ["a", "bb", "ccc", "filea", "fileb"];
+    for name in names {
+        root.create(name).unwrap();
+    }
+    let mut listed = root.ls();
+    listed.sort();
+    let mut expected: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+    expected.sort();
+    assert_eq!(listed, expected, "ls 的结果与创建的文件不一致");
+}
+
+#[test]
+fn many_entries() {
+    let root = fresh_fs(IMAGE);
+    // 目录项超过一个块
+    for i in 0..100 {
+        root.create(&format!("file{}", i)).unwrap();
+    }
+    assert_eq!(root.ls().len(), 100, "目录项数量不正确");
+    assert!(root.find("file99").is_some(), "找不到最后创建的文件");
+}
+
+#[test]
+fn duplicate_create_fails() {
+    let root = fresh_fs(IMAGE);
+    assert!(root.create("same").is_some());
+    assert!(root.create("same").is_none(), "重复创建同名文件应当失败");
+    assert_eq!(root.ls().len(), 1, "重复创建后目录项数量不正确");
+}
+
+#[test]
+fn find_missing_returns_none() {
+    let root = fresh_fs(IMAGE);
+    root.create("exists").unwrap();
+    assert!(root.find("missing").is_none(), "不存在的文件应当找不到");
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{fresh_fs, pattern, read_all};
+
+const IMAGE: &str = "file_ops";
+
+#[test]
+fn create_and_find() {
+    let root = fresh_fs(IMAGE);
+    assert!(root.create("hello").is_some(), "创建文件失败");
+    assert!(root.find("hello").is_some(), "找不到刚创建的文件");
+}
+
+#[test]
+fn write_then_read() {
+    let root = fresh_fs(IMAGE);
+    let file = root.create("data").unwrap();
+    let data = pattern(1000, 7);
+    assert_eq!(file.write_at(0, &data), data.len(), "写入长度不正确");
+    assert_eq!(read_all(&file), data, "读出的内容与写入的不一致");
+}
+
+#[test]
+fn overwrite_in_middle() {
+    let root = fresh_fs(IMAGE);
+    let file = root.create("data").unwrap();
+    let mut expected = pattern(2048, 1);
+    file.write_at(0, &expected);
+    let patch = pattern(300, 99);
+    file.write_at(700, &patch);
+    expected[700..1000].copy_from_slice(&patch);
+    assert_eq!(read_all(&file), expected, "覆盖写入后内容不正确");
+}
+
+#[test]
+fn large_file_uses_indirect_blocks() {
+    let root = fresh_fs(IMAGE);
+    let file = root.create("large").unwrap();
+    // 超过直接块和一级间接块能表示的范围
+    let data = pattern(200 * 512 + 123, 3);
+    assert_eq!(file.write_at(0, &data), data.len(), "写入长度不正确");
+    assert_eq!(read_all(&file), data, "大文件读出的内容与写入的不一致");
+}
+
+#[test]
+fn clear_file() {
+    let root = fresh_fs(IMAGE);
+    let file = root.create("data").unwrap();
+    file.write_at(0, &pattern(5000, 5));
+    file.clear();
+    assert!(read_all(&file).is_empty(), "clear 后文件仍有内容");
+
+    // 清空后可以重新写入
+    let data = pattern(600, 8);
+    file.write_at(0, &data);
+    assert_eq!(read_all(&file), data, "clear 后重新写入的内容不正确");
+}
@@ -0,0 +1,3 @@
+INSERT INTO blobs (hash, size, ref_count)
+VALUES (?, ?, 1)
+ON DUPLICATE KEY UPDATE ref_count = ref_count + 1
\ No newline at end of file
@@ -0,0 +1 @@
+DELETE FROM blobs WHERE hash = ?
\ No newline at end of file
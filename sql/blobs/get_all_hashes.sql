@@ -0,0 +1 @@
+SELECT hash FROM blobs
\ No newline at end of file
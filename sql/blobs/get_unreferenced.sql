@@ -0,0 +1 @@
+SELECT hash, size FROM blobs WHERE ref_count = 0
\ No newline at end of file
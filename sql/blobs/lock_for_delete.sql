@@ -0,0 +1 @@
+SELECT ref_count FROM blobs WHERE hash = ? FOR UPDATE
\ No newline at end of file
@@ -0,0 +1,2 @@
+UPDATE blobs b
+SET b.ref_count = (SELECT COUNT(*) FROM upload_files f WHERE f.blob_hash = b.hash)
\ No newline at end of file
@@ -0,0 +1,4 @@
+UPDATE blobs b
+JOIN (SELECT blob_hash, COUNT(*) AS n FROM upload_files WHERE upload_id = ? GROUP BY blob_hash) f
+  ON b.hash = f.blob_hash
+SET b.ref_count = GREATEST(b.ref_count - f.n, 0)
\ No newline at end of file
@@ -0,0 +1,5 @@
+INSERT INTO test_cases (test_id, name, verdict, exit_code, duration_ms)
+SELECT ?, name, verdict, exit_code, duration_ms
+FROM test_cases
+WHERE test_id = ?
+ORDER BY id
\ No newline at end of file
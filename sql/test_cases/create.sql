@@ -0,0 +1,2 @@
+INSERT INTO test_cases (test_id, name, verdict, exit_code, duration_ms)
+VALUES (?, ?, ?, ?, ?)
\ No newline at end of file
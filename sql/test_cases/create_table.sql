@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS test_cases (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    test_id INT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    verdict VARCHAR(20) NOT NULL,
+    exit_code INT,
+    duration_ms BIGINT,
+    FOREIGN KEY (test_id) REFERENCES test_results(id) ON DELETE CASCADE
+)
\ No newline at end of file
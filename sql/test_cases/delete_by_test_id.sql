@@ -0,0 +1,2 @@
+DELETE FROM test_cases
+WHERE test_id = ?
\ No newline at end of file
@@ -0,0 +1,4 @@
+SELECT name, verdict, exit_code, duration_ms
+FROM test_cases
+WHERE test_id = ?
+ORDER BY id
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE test_results
+ADD COLUMN cached_from INT
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE test_results
+ADD COLUMN env_fingerprint TEXT
\ No newline at end of file
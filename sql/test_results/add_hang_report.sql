@@ -0,0 +1,2 @@
+ALTER TABLE test_results
+ADD COLUMN hang_report TEXT
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE test_results
+ADD COLUMN submission_hash VARCHAR(64)
\ No newline at end of file
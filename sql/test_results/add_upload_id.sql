@@ -0,0 +1,2 @@
+ALTER TABLE test_results
+ADD COLUMN upload_id INT
\ No newline at end of file
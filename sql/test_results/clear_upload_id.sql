@@ -0,0 +1,3 @@
+UPDATE test_results
+SET upload_id = NULL
+WHERE upload_id = ?
\ No newline at end of file
@@ -0,0 +1,6 @@
+UPDATE test_results dst
+JOIN test_results src ON src.id = ?
+SET dst.status = src.status, dst.output = src.output, dst.error = src.error,
+    dst.hang_report = src.hang_report, dst.env_fingerprint = src.env_fingerprint,
+    dst.cached_from = COALESCE(src.cached_from, src.id)
+WHERE dst.id = ?
\ No newline at end of file
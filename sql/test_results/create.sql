@@ -1,2 +1,2 @@
-INSERT INTO test_results (user_id, status)
-VALUES (?, 'Pending')
\ No newline at end of file
+INSERT INTO test_results (user_id, status, profile, upload_id)
+VALUES (?, 'Pending', ?, ?)
\ No newline at end of file
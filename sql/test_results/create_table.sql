@@ -2,8 +2,15 @@ CREATE TABLE IF NOT EXISTS test_results (
     id INT AUTO_INCREMENT PRIMARY KEY,
     user_id INT NOT NULL,
     status VARCHAR(20) NOT NULL,
+    profile VARCHAR(64),
     output TEXT,
     error TEXT,
+    hang_report TEXT,
+    env_fingerprint TEXT,
+    submission_hash VARCHAR(64),
+    bundle_hash VARCHAR(64),
+    cached_from INT,
+    upload_id INT,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
     FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
@@ -0,0 +1,6 @@
+SELECT id
+FROM test_results
+WHERE submission_hash = ? AND bundle_hash = ? AND profile = ? AND env_fingerprint = ?
+  AND status IN ('Passed', 'Failed') AND error IS NULL AND id <> ?
+ORDER BY created_at DESC
+LIMIT 1
\ No newline at end of file
@@ -0,0 +1,4 @@
+SELECT tr.id, tr.user_id, tr.profile, tr.status, tr.upload_id,
+       CAST((SELECT COUNT(*) FROM test_cases tc WHERE tc.test_id = tr.id AND tc.verdict = 'Passed') AS SIGNED) AS passed_cases
+FROM test_results tr
+WHERE tr.status IN ('Passed', 'Failed', 'Error')
\ No newline at end of file
@@ -0,0 +1,3 @@
+UPDATE test_results
+SET env_fingerprint = ?
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1,3 @@
+UPDATE test_results
+SET hang_report = ?, updated_at = CURRENT_TIMESTAMP
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1,3 @@
+UPDATE test_results
+SET submission_hash = ?, bundle_hash = ?
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1 @@
+INSERT INTO upload_files (upload_id, path, blob_hash, size) VALUES (?, ?, ?, ?)
\ No newline at end of file
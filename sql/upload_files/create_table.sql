@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS upload_files (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    upload_id INT NOT NULL,
+    path VARCHAR(1024) NOT NULL,
+    blob_hash CHAR(64) NOT NULL,
+    size BIGINT NOT NULL,
+    INDEX idx_upload_files_upload (upload_id),
+    INDEX idx_upload_files_blob (blob_hash),
+    FOREIGN KEY (upload_id) REFERENCES uploads(id) ON DELETE CASCADE
+)
\ No newline at end of file
@@ -0,0 +1 @@
+DELETE FROM upload_files WHERE upload_id = ? AND LEFT(path, CHAR_LENGTH(?)) = ?
\ No newline at end of file
@@ -0,0 +1 @@
+DELETE FROM upload_files WHERE upload_id = ?
\ No newline at end of file
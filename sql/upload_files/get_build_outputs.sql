@@ -0,0 +1,8 @@
+SELECT f.upload_id, us.username, u.project_dir, f.path, f.size
+FROM upload_files f
+JOIN uploads u ON f.upload_id = u.id
+JOIN users us ON u.user_id = us.id
+WHERE u.project_dir IS NOT NULL
+  AND (f.path LIKE '%/os/target/%' OR f.path LIKE '%/user/target/%')
+  AND NOT EXISTS (SELECT 1 FROM test_results tr WHERE tr.upload_id = u.id AND tr.status IN ('Pending', 'Running'))
+ORDER BY f.upload_id, f.path
\ No newline at end of file
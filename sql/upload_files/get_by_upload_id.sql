@@ -0,0 +1 @@
+SELECT path, blob_hash, size FROM upload_files WHERE upload_id = ? ORDER BY path
\ No newline at end of file
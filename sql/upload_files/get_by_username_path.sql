@@ -0,0 +1,6 @@
+SELECT f.path, f.blob_hash, f.size
+FROM upload_files f
+JOIN uploads u ON f.upload_id = u.id
+JOIN users us ON u.user_id = us.id
+WHERE us.username = ? AND (f.path = ? OR LEFT(f.path, CHAR_LENGTH(?)) = ?)
+ORDER BY f.path
\ No newline at end of file
@@ -0,0 +1 @@
+UPDATE upload_files SET path = ? WHERE upload_id = ? AND path = ?
\ No newline at end of file
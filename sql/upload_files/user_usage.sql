@@ -0,0 +1,4 @@
+SELECT CAST(COALESCE(SUM(f.size), 0) AS SIGNED) AS used
+FROM upload_files f
+JOIN uploads u ON f.upload_id = u.id
+WHERE u.user_id = ?
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE uploads
+ADD COLUMN project_dir VARCHAR(512)
\ No newline at end of file
@@ -4,6 +4,7 @@ CREATE TABLE IF NOT EXISTS uploads (
     filename VARCHAR(255) NOT NULL,
     file_path VARCHAR(255) NOT NULL,
     file_size BIGINT NOT NULL,
+    project_dir VARCHAR(512),
     uploaded_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
 )
\ No newline at end of file
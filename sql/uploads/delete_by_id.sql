@@ -0,0 +1,2 @@
+DELETE FROM uploads
+WHERE id = ?
\ No newline at end of file
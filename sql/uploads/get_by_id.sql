@@ -0,0 +1,6 @@
+SELECT u.id, u.filename, u.file_path, u.file_size, u.uploaded_at, u.project_dir, us.username,
+       tr.id AS test_id, tr.status AS test_status, tr.profile AS test_profile
+FROM uploads u
+JOIN users us ON u.user_id = us.id
+LEFT JOIN test_results tr ON tr.id = (SELECT MAX(id) FROM test_results WHERE upload_id = u.id)
+WHERE u.id = ?
\ No newline at end of file
@@ -0,0 +1,7 @@
+SELECT u.id, u.filename, u.file_path, u.file_size, u.uploaded_at, u.project_dir, us.username,
+       tr.id AS test_id, tr.status AS test_status, tr.profile AS test_profile
+FROM uploads u
+JOIN users us ON u.user_id = us.id
+JOIN test_results tr ON tr.id = (SELECT MAX(id) FROM test_results WHERE upload_id = u.id)
+WHERE tr.profile = ? AND u.project_dir IS NOT NULL
+ORDER BY u.id DESC
@@ -0,0 +1,5 @@
+SELECT u.id, u.file_path, us.username
+FROM uploads u
+JOIN users us ON u.user_id = us.id
+WHERE NOT EXISTS (SELECT 1 FROM upload_files f WHERE f.upload_id = u.id)
+ORDER BY u.id
\ No newline at end of file
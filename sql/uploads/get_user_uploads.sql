@@ -1,4 +1,7 @@
-SELECT id, filename, file_path, file_size, uploaded_at
-FROM uploads
-WHERE user_id = ?
-ORDER BY uploaded_at DESC
\ No newline at end of file
+SELECT u.id, u.filename, u.file_path, u.file_size, u.uploaded_at, u.project_dir, us.username,
+       tr.id AS test_id, tr.status AS test_status, tr.profile AS test_profile
+FROM uploads u
+JOIN users us ON u.user_id = us.id
+LEFT JOIN test_results tr ON tr.id = (SELECT MAX(id) FROM test_results WHERE upload_id = u.id)
+WHERE u.user_id = ?
+ORDER BY u.uploaded_at DESC
\ No newline at end of file
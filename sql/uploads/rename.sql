@@ -0,0 +1,3 @@
+UPDATE uploads
+SET filename = ?, file_path = ?
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1,3 @@
+UPDATE uploads
+SET project_dir = ?
+WHERE id = ?
\ No newline at end of file
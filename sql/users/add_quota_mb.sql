@@ -0,0 +1,2 @@
+ALTER TABLE users
+ADD COLUMN quota_mb INT
\ No newline at end of file
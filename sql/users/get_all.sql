@@ -1 +1 @@
-SELECT id, username, password, role FROM users
\ No newline at end of file
+SELECT id, username, password, role, quota_mb FROM users
\ No newline at end of file
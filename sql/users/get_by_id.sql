@@ -1,2 +1,2 @@
-SELECT id, username, password, role FROM users
+SELECT id, username, password, role, quota_mb FROM users
 WHERE id = ?
\ No newline at end of file
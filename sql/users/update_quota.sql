@@ -0,0 +1,3 @@
+UPDATE users
+SET quota_mb = ?
+WHERE id = ?
\ No newline at end of file
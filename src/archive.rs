@@ -0,0 +1,294 @@
+use crc32fast::Hasher;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// 不使用 ZIP64 扩展，条目数和压缩包大小受 32 位字段限制
+const MAX_ENTRIES: usize = 0xFFFF;
+const MAX_ARCHIVE_SIZE: u64 = 0xFFFF_FFFF;
+
+// 读取文件时每次处理的字节数
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+// 通用标志位：bit 3 表示大小和 CRC 写在数据之后的描述符中，bit 11 表示文件名为 UTF-8
+const FLAGS: u16 = 0x0808;
+const VERSION_NEEDED: u16 = 20;
+const VERSION_MADE_BY_UNIX: u16 = (3 << 8) | 20;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+// 压缩包中的一个条目
+pub struct ArchiveEntry {
+    pub name: String, // 压缩包内以 / 分隔的路径，目录以 / 结尾
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+// 列出目录下要打包的条目，所有条目放在 prefix 目录下。
+// 不跟随符号链接，避免打包到目录之外的文件
+pub fn collect_entries(root: &Path, prefix: &str) -> io::Result<Vec<ArchiveEntry>> {
+    let mut entries = vec![ArchiveEntry {
+        name: format!("{}/", prefix),
+        path: root.to_path_buf(),
+        is_dir: true,
+        size: 0,
+        modified: std::fs::metadata(root)?.modified().ok(),
+    }];
+    collect_dir(root, prefix, &mut entries)?;
+
+    let total: u64 = entries.iter().map(|entry| entry.size).sum();
+    if entries.len() > MAX_ENTRIES {
+        return Err(io::Error::new(io::ErrorKind::Other, format!("文件数超过 {} 个，无法打包", MAX_ENTRIES)));
+    }
+    if total > MAX_ARCHIVE_SIZE / 2 {
+        return Err(io::Error::new(io::ErrorKind::Other, "目录过大，无法打包下载"));
+    }
+    Ok(entries)
+}
+
+fn collect_dir(dir: &Path, prefix: &str, entries: &mut Vec<ArchiveEntry>) -> io::Result<()> {
+    let mut children: Vec<std::fs::DirEntry> = std::fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    children.sort_by_key(|entry| entry.file_name());
+
+    for child in children {
+        let file_type = child.file_type()?;
+        let name = format!("{}/{}", prefix, child.file_name().to_string_lossy());
+        let metadata = child.metadata()?;
+        if file_type.is_dir() {
+            entries.push(ArchiveEntry {
+                name: format!("{}/", name),
+                path: child.path(),
+                is_dir: true,
+                size: 0,
+                modified: metadata.modified().ok(),
+            });
+            collect_dir(&child.path(), &name, entries)?;
+        } else if file_type.is_file() {
+            entries.push(ArchiveEntry {
+                name,
+                path: child.path(),
+                is_dir: false,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// 中央目录中记录的条目信息
+struct CentralRecord {
+    name: String,
+    method: u16,
+    time: u16,
+    date: u16,
+    crc: u32,
+    compressed: u32,
+    uncompressed: u32,
+    external_attr: u32,
+    offset: u32,
+}
+
+// 记录已写出字节数的输出
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// 依次写出 zip 压缩包。输出不需要支持 Seek：
+// 每个文件的大小和 CRC 在数据之后的描述符中给出，因此可以边读文件边发送给客户端
+pub fn write_zip<W: Write>(entries: &[ArchiveEntry], out: W) -> io::Result<W> {
+    let mut out = CountingWriter { inner: out, written: 0 };
+    let mut records = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        let offset = to_u32(out.written)?;
+        let name_len = u16::try_from(entry.name.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, format!("文件名过长，无法打包: {}", entry.name)))?;
+        let (time, date) = dos_time(entry.modified);
+        let method = if entry.is_dir { METHOD_STORED } else { METHOD_DEFLATED };
+
+        // 本地文件头，CRC 和大小留空
+        out.write_all(&0x04034b50u32.to_le_bytes())?;
+        out.write_all(&VERSION_NEEDED.to_le_bytes())?;
+        out.write_all(&FLAGS.to_le_bytes())?;
+        out.write_all(&method.to_le_bytes())?;
+        out.write_all(&time.to_le_bytes())?;
+        out.write_all(&date.to_le_bytes())?;
+        out.write_all(&[0u8; 12])?;
+        out.write_all(&name_len.to_le_bytes())?;
+        out.write_all(&0u16.to_le_bytes())?;
+        out.write_all(entry.name.as_bytes())?;
+
+        let (crc, compressed, uncompressed) = if entry.is_dir {
+            (0, 0, 0)
+        } else {
+            write_file_data(&entry.path, &mut out)?
+        };
+        let (compressed, uncompressed) = (to_u32(compressed)?, to_u32(uncompressed)?);
+
+        // 数据描述符
+        out.write_all(&0x08074b50u32.to_le_bytes())?;
+        out.write_all(&crc.to_le_bytes())?;
+        out.write_all(&compressed.to_le_bytes())?;
+        out.write_all(&uncompressed.to_le_bytes())?;
+
+        let external_attr = if entry.is_dir { (0o40755 << 16) | 0x10 } else { 0o100644 << 16 };
+        records.push(CentralRecord {
+            name: entry.name.clone(),
+            method,
+            time,
+            date,
+            crc,
+            compressed,
+            uncompressed,
+            external_attr,
+            offset,
+        });
+    }
+
+    let central_offset = to_u32(out.written)?;
+    for record in &records {
+        out.write_all(&0x02014b50u32.to_le_bytes())?;
+        out.write_all(&VERSION_MADE_BY_UNIX.to_le_bytes())?;
+        out.write_all(&VERSION_NEEDED.to_le_bytes())?;
+        out.write_all(&FLAGS.to_le_bytes())?;
+        out.write_all(&record.method.to_le_bytes())?;
+        out.write_all(&record.time.to_le_bytes())?;
+        out.write_all(&record.date.to_le_bytes())?;
+        out.write_all(&record.crc.to_le_bytes())?;
+        out.write_all(&record.compressed.to_le_bytes())?;
+        out.write_all(&record.uncompressed.to_le_bytes())?;
+        out.write_all(&(record.name.len() as u16).to_le_bytes())?;
+        out.write_all(&[0u8; 8])?; // 扩展字段长度、注释长度、磁盘号、内部属性
+        out.write_all(&record.external_attr.to_le_bytes())?;
+        out.write_all(&record.offset.to_le_bytes())?;
+        out.write_all(record.name.as_bytes())?;
+    }
+    let central_size = to_u32(out.written)? - central_offset;
+
+    // 中央目录结束记录
+    out.write_all(&0x06054b50u32.to_le_bytes())?;
+    out.write_all(&[0u8; 4])?;
+    out.write_all(&(records.len() as u16).to_le_bytes())?;
+    out.write_all(&(records.len() as u16).to_le_bytes())?;
+    out.write_all(&central_size.to_le_bytes())?;
+    out.write_all(&central_offset.to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())?;
+    out.flush()?;
+    Ok(out.inner)
+}
+
+// 压缩写出一个文件的内容，返回 (CRC, 压缩后大小, 原始大小)
+fn write_file_data<W: Write>(path: &Path, out: &mut CountingWriter<W>) -> io::Result<(u32, u64, u64)> {
+    let mut file = std::fs::File::open(path)?;
+    let start = out.written;
+    let mut hasher = Hasher::new();
+    let mut uncompressed = 0u64;
+    let mut buf = vec![0u8; READ_CHUNK_SIZE];
+
+    let mut encoder = DeflateEncoder::new(&mut *out, Compression::fast());
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        encoder.write_all(&buf[..n])?;
+        uncompressed += n as u64;
+    }
+    encoder.finish()?;
+
+    Ok((hasher.finalize(), out.written - start, uncompressed))
+}
+
+fn to_u32(value: u64) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| io::Error::new(io::ErrorKind::Other, "压缩包超过 4GB，无法打包"))
+}
+
+// 转换为 zip 使用的 DOS 日期时间（本地时间，精度 2 秒，最早 1980 年）
+fn dos_time(modified: Option<SystemTime>) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let time: chrono::DateTime<chrono::Local> = modified.unwrap_or_else(SystemTime::now).into();
+    if time.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let dos_time = ((time.hour() << 11) | (time.minute() << 5) | (time.second() / 2)) as u16;
+    let dos_date = (((time.year() - 1980) as u32) << 9 | (time.month() << 5) | time.day()) as u16;
+    (dos_time, dos_date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn archive_round_trips_through_zip_reader() {
+        let root = std::env::temp_dir().join(format!("archive_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("os/src")).unwrap();
+        std::fs::create_dir_all(root.join("空目录")).unwrap();
+        std::fs::create_dir_all(root.join("文档")).unwrap();
+        let source = "fn main() {}\n".repeat(1000);
+        std::fs::write(root.join("os/src/main.rs"), &source).unwrap();
+        std::fs::write(root.join("文档/说明.txt"), "实验报告").unwrap();
+        std::fs::write(root.join("os/empty.txt"), "").unwrap();
+
+        let entries = collect_entries(&root, "项目").unwrap();
+        let bytes = write_zip(&entries, Vec::new()).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "项目/",
+                "项目/os/",
+                "项目/os/empty.txt",
+                "项目/os/src/",
+                "项目/os/src/main.rs",
+                "项目/文档/",
+                "项目/文档/说明.txt",
+                "项目/空目录/",
+            ]
+        );
+
+        // 读到末尾时 zip 会校验 CRC
+        let read = |archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>, name: &str| {
+            let mut content = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+            content
+        };
+        assert_eq!(read(&mut archive, "项目/os/src/main.rs"), source);
+        assert_eq!(read(&mut archive, "项目/文档/说明.txt"), "实验报告");
+        assert_eq!(read(&mut archive, "项目/os/empty.txt"), "");
+
+        let dir = archive.by_name("项目/空目录/").unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(dir.unix_mode().map(|mode| mode & 0o170000), Some(0o040000));
+    }
+
+    #[test]
+    fn dos_time_clamps_to_1980() {
+        assert_eq!(dos_time(Some(SystemTime::UNIX_EPOCH)), (0, (1 << 5) | 1));
+    }
+}
@@ -0,0 +1,240 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// 评测产物的种类
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArtifactKind {
+    Kernel,     // 未裁剪的内核ELF
+    FsImage,    // 文件系统镜像
+    Transcript, // 完整的运行输出
+    BuildLog,   // 编译日志
+    Repro,      // 复现包
+}
+
+impl ArtifactKind {
+    pub const ALL: [ArtifactKind; 5] = [
+        ArtifactKind::Kernel,
+        ArtifactKind::FsImage,
+        ArtifactKind::Transcript,
+        ArtifactKind::BuildLog,
+        ArtifactKind::Repro,
+    ];
+
+    // 配置中使用的名称
+    fn key(&self) -> &'static str {
+        match self {
+            ArtifactKind::Kernel => "kernel",
+            ArtifactKind::FsImage => "fs_img",
+            ArtifactKind::Transcript => "transcript",
+            ArtifactKind::BuildLog => "build_log",
+            ArtifactKind::Repro => "repro",
+        }
+    }
+
+    // 保存时使用的文件名
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ArtifactKind::Kernel => "os.elf",
+            ArtifactKind::FsImage => "fs.img",
+            ArtifactKind::Transcript => "transcript.txt",
+            ArtifactKind::BuildLog => "build.log",
+            ArtifactKind::Repro => "repro.zip",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ArtifactKind::Kernel => "内核ELF",
+            ArtifactKind::FsImage => "文件系统镜像",
+            ArtifactKind::Transcript => "运行输出",
+            ArtifactKind::BuildLog => "编译日志",
+            ArtifactKind::Repro => "复现包",
+        }
+    }
+
+    fn from_file_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.file_name() == name)
+    }
+}
+
+// 已保存的产物
+#[derive(Debug, Clone)]
+pub struct ArtifactInfo {
+    pub kind: ArtifactKind,
+    pub size: u64,
+}
+
+// 按评测ID保存产物的存储
+pub struct ArtifactStore {
+    root: PathBuf,
+    kinds: Vec<ArtifactKind>,
+    retention_days: u64,
+}
+
+impl ArtifactStore {
+    // 从环境变量读取配置：
+    // ARTIFACTS_DIR            产物根目录，默认 artifacts
+    // JUDGE_ARTIFACTS          需要保存的产物，逗号分隔，默认全部
+    // ARTIFACT_RETENTION_DAYS  保留天数，默认 14，0 表示永久保留
+    pub fn from_env() -> Self {
+        let root = std::env::var("ARTIFACTS_DIR").unwrap_or_else(|_| "artifacts".to_string());
+        let kinds = match std::env::var("JUDGE_ARTIFACTS") {
+            Ok(list) => ArtifactKind::ALL
+                .into_iter()
+                .filter(|kind| list.split(',').any(|item| item.trim() == kind.key()))
+                .collect(),
+            Err(_) => ArtifactKind::ALL.to_vec(),
+        };
+        let retention_days = std::env::var("ARTIFACT_RETENTION_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(14);
+
+        Self {
+            root: PathBuf::from(root),
+            kinds,
+            retention_days,
+        }
+    }
+
+    pub fn retention_days(&self) -> u64 {
+        self.retention_days
+    }
+
+    pub fn is_enabled(&self, kind: ArtifactKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    // 某次评测的产物目录
+    pub fn run_dir(&self, test_id: i32) -> PathBuf {
+        self.root.join(test_id.to_string())
+    }
+
+    // 复制一个文件作为产物，未启用的种类直接忽略
+    pub async fn save_file(&self, test_id: i32, kind: ArtifactKind, src: &Path) -> std::io::Result<()> {
+        if !self.is_enabled(kind) {
+            return Ok(());
+        }
+        let dir = self.run_dir(test_id);
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::copy(src, dir.join(kind.file_name())).await?;
+        Ok(())
+    }
+
+    // 直接写入内容作为产物
+    pub async fn save_bytes(&self, test_id: i32, kind: ArtifactKind, data: &[u8]) -> std::io::Result<()> {
+        if !self.is_enabled(kind) {
+            return Ok(());
+        }
+        let dir = self.run_dir(test_id);
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(dir.join(kind.file_name()), data).await?;
+        Ok(())
+    }
+
+    // 复用其他评测的结果时，把源评测的产物复制给本次评测，源评测的产物过期删除后仍然可以下载。
+    // 尽量建立硬链接，不重复占用空间
+    pub async fn copy_run(&self, source_id: i32, test_id: i32) -> std::io::Result<usize> {
+        let dir = self.run_dir(test_id);
+        let mut copied = 0;
+        for artifact in self.list(source_id).await {
+            let src = self.run_dir(source_id).join(artifact.kind.file_name());
+            let dst = dir.join(artifact.kind.file_name());
+            tokio::fs::create_dir_all(&dir).await?;
+            if tokio::fs::hard_link(&src, &dst).await.is_err() {
+                tokio::fs::copy(&src, &dst).await?;
+            }
+            copied += 1;
+        }
+        Ok(copied)
+    }
+
+    // 列出某次评测保存的所有产物
+    pub async fn list(&self, test_id: i32) -> Vec<ArtifactInfo> {
+        let mut artifacts = Vec::new();
+        for kind in ArtifactKind::ALL {
+            if let Ok(metadata) = tokio::fs::metadata(self.run_dir(test_id).join(kind.file_name())).await {
+                artifacts.push(ArtifactInfo {
+                    kind,
+                    size: metadata.len(),
+                });
+            }
+        }
+        artifacts
+    }
+
+    // 根据文件名获取产物路径，只接受已知的产物文件名
+    pub fn path_of(&self, test_id: i32, file_name: &str) -> Option<PathBuf> {
+        ArtifactKind::from_file_name(file_name).map(|kind| self.run_dir(test_id).join(kind.file_name()))
+    }
+
+    // 超过保留期限的产物目录：(评测ID, 占用的字节数)。保留天数为 0 时永久保留
+    pub async fn expired_runs(&self) -> std::io::Result<Vec<(i32, u64)>> {
+        if self.retention_days == 0 {
+            return Ok(Vec::new());
+        }
+        let max_age = Duration::from_secs(self.retention_days * 24 * 60 * 60);
+        let mut expired_runs = Vec::new();
+
+        let mut entries = match tokio::fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(expired_runs),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let test_id = match entry.file_name().to_str().and_then(|name| name.parse::<i32>().ok()) {
+                Some(test_id) => test_id,
+                None => continue,
+            };
+            let metadata = entry.metadata().await?;
+            let expired = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .map_or(false, |age| age > max_age);
+            if metadata.is_dir() && expired {
+                let size = self.list(test_id).await.iter().map(|artifact| artifact.size).sum();
+                expired_runs.push((test_id, size));
+            }
+        }
+
+        expired_runs.sort();
+        Ok(expired_runs)
+    }
+
+    // 删除某次评测的所有产物
+    pub async fn remove_run(&self, test_id: i32) -> std::io::Result<()> {
+        match tokio::fs::remove_dir_all(self.run_dir(test_id)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cached_run_gets_its_own_copy_of_artifacts() {
+        let root = std::env::temp_dir().join(format!("artifacts_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = ArtifactStore {
+            root: root.clone(),
+            kinds: ArtifactKind::ALL.to_vec(),
+            retention_days: 14,
+        };
+        store.save_bytes(1, ArtifactKind::Transcript, b"[ok] ch3_sleep").await.unwrap();
+        store.save_bytes(1, ArtifactKind::BuildLog, b"Finished").await.unwrap();
+
+        assert_eq!(store.copy_run(1, 2).await.unwrap(), 2);
+        store.remove_run(1).await.unwrap();
+
+        let kinds: Vec<ArtifactKind> = store.list(2).await.into_iter().map(|artifact| artifact.kind).collect();
+        assert_eq!(kinds, [ArtifactKind::Transcript, ArtifactKind::BuildLog]);
+        let transcript = store.path_of(2, "transcript.txt").unwrap();
+        assert_eq!(std::fs::read(transcript).unwrap(), b"[ok] ch3_sleep");
+        assert_eq!(store.copy_run(3, 4).await.unwrap(), 0);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
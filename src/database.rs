@@ -44,6 +44,56 @@ async fn init_tables(pool: &DbPool) -> Result<(), DbError> {
         .execute(pool)
         .await?;
     
+    // 创建用例结果表
+    sqlx::query(include_str!("../sql/test_cases/create_table.sql"))
+        .execute(pool)
+        .await?;
+    
+    // 创建内容寻址存储的文件表和上传文件表
+    sqlx::query(include_str!("../sql/blobs/create_table.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::query(include_str!("../sql/upload_files/create_table.sql"))
+        .execute(pool)
+        .await?;
+    
+    // 为已存在的旧表补充新增的列
+    migrate_tables(pool).await?;
+    
+    Ok(())
+}
+
+// 补充旧版本数据库中缺少的列
+async fn migrate_tables(pool: &DbPool) -> Result<(), DbError> {
+    ensure_column(pool, "test_results", "hang_report", include_str!("../sql/test_results/add_hang_report.sql")).await?;
+    ensure_column(pool, "test_results", "profile", include_str!("../sql/test_results/add_profile.sql")).await?;
+    ensure_column(pool, "test_results", "env_fingerprint", include_str!("../sql/test_results/add_env_fingerprint.sql")).await?;
+    ensure_column(pool, "test_results", "submission_hash", include_str!("../sql/test_results/add_submission_hash.sql")).await?;
+    ensure_column(pool, "test_results", "bundle_hash", include_str!("../sql/test_results/add_bundle_hash.sql")).await?;
+    ensure_column(pool, "test_results", "cached_from", include_str!("../sql/test_results/add_cached_from.sql")).await?;
+    ensure_column(pool, "uploads", "project_dir", include_str!("../sql/uploads/add_project_dir.sql")).await?;
+    ensure_column(pool, "test_results", "upload_id", include_str!("../sql/test_results/add_upload_id.sql")).await?;
+    ensure_column(pool, "users", "quota_mb", include_str!("../sql/users/add_quota_mb.sql")).await?;
+    
+    Ok(())
+}
+
+// 如果指定列不存在，则执行对应的 ALTER TABLE 语句
+async fn ensure_column(pool: &DbPool, table: &str, column: &str, alter_sql: &str) -> Result<(), DbError> {
+    let exists = sqlx::query(include_str!("../sql/schema/column_exists.sql"))
+        .bind(table)
+        .bind(column)
+        .fetch_optional(pool)
+        .await?
+        .is_some();
+    
+    if !exists {
+        tracing::info!("为表 {} 添加列 {}", table, column);
+        sqlx::query(alter_sql)
+            .execute(pool)
+            .await?;
+    }
+    
     Ok(())
 }
 
@@ -101,6 +151,7 @@ impl UserRepo {
                 username: row.get("username"),
                 password: row.get("password"),
                 role,
+                quota_mb: row.get::<Option<i32>, _>("quota_mb").map(i64::from),
             }
         })
         .collect();
@@ -125,6 +176,7 @@ impl UserRepo {
                 username: row.get("username"),
                 password: row.get("password"),
                 role,
+                quota_mb: row.get::<Option<i32>, _>("quota_mb").map(i64::from),
             }
         });
         
@@ -148,6 +200,7 @@ impl UserRepo {
                 username: row.get("username"),
                 password: row.get("password"),
                 role,
+                quota_mb: row.get::<Option<i32>, _>("quota_mb").map(i64::from),
             }
         });
         
@@ -215,6 +268,17 @@ impl UserRepo {
         
         Ok(result.rows_affected() > 0)
     }
+    
+    // 设置用户的存储配额，None 表示使用角色的默认配额
+    pub async fn update_quota(pool: &DbPool, user_id: i32, quota_mb: Option<i32>) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/users/update_quota.sql"))
+        .bind(quota_mb)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
 }
 
 // 会话相关的数据库操作
@@ -287,23 +351,322 @@ impl UploadRepo {
         Ok(result.last_insert_id() as i32)
     }
     
+    // 上传文件写入各自的目录后更新记录的路径
+    pub async fn update_file_path(pool: &DbPool, id: i32, file_path: &str) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/uploads/update_file_path.sql"))
+        .bind(file_path)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    // 删除上传记录（文件写入失败时撤销）
+    pub async fn delete_upload(pool: &DbPool, id: i32) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/uploads/delete_by_id.sql"))
+        .bind(id)
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    // 重命名上传的文件，同时更新文件列表中的路径（相对用户目录）
+    pub async fn rename_upload(
+        pool: &DbPool,
+        id: i32,
+        filename: &str,
+        file_path: &str,
+        old_entry: &str,
+        new_entry: &str,
+    ) -> Result<(), DbError> {
+        let mut tx = pool.begin().await?;
+        sqlx::query(include_str!("../sql/uploads/rename.sql"))
+        .bind(filename)
+        .bind(file_path)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(include_str!("../sql/upload_files/rename.sql"))
+        .bind(new_entry)
+        .bind(id)
+        .bind(old_entry)
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        
+        Ok(())
+    }
+    
+    // 用户删除上传：评测结果保留，但不再关联到这次上传；
+    // 释放上传引用的文件，没有其他引用的文件之后由垃圾回收删除
+    pub async fn remove_upload(pool: &DbPool, id: i32) -> Result<(), DbError> {
+        let mut tx = pool.begin().await?;
+        sqlx::query(include_str!("../sql/test_results/clear_upload_id.sql"))
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(include_str!("../sql/blobs/release_upload.sql"))
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(include_str!("../sql/upload_files/delete_by_upload_id.sql"))
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(include_str!("../sql/uploads/delete_by_id.sql"))
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        
+        Ok(())
+    }
+    
+    // 记录通过检查的工程根目录，用于之后比较提交内容
+    pub async fn update_project_dir(pool: &DbPool, id: i32, project_dir: &str) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/uploads/update_project_dir.sql"))
+        .bind(project_dir)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
     // 获取用户的上传记录
-    pub async fn get_user_uploads(pool: &DbPool, user_id: i32) -> Result<Vec<sqlx::mysql::MySqlRow>, DbError> {
-        let uploads = sqlx::query(include_str!("../sql/uploads/get_user_uploads.sql"))
+    pub async fn get_user_uploads(pool: &DbPool, user_id: i32) -> Result<Vec<crate::models::UploadRecord>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/uploads/get_user_uploads.sql"))
         .bind(user_id)
         .fetch_all(pool)
         .await?;
         
-        Ok(uploads)
+        Ok(rows.iter().map(upload_from_row).collect())
     }
     
     // 获取所有上传记录（管理员使用）
-    pub async fn get_all_uploads(pool: &DbPool) -> Result<Vec<sqlx::mysql::MySqlRow>, DbError> {
-        let uploads = sqlx::query(include_str!("../sql/uploads/get_all_uploads.sql"))
+    pub async fn get_all_uploads(pool: &DbPool) -> Result<Vec<crate::models::UploadRecord>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/uploads/get_all_uploads.sql"))
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(upload_from_row).collect())
+    }
+    
+    // 获取单个上传记录
+    pub async fn get_upload_by_id(pool: &DbPool, id: i32) -> Result<Option<crate::models::UploadRecord>, DbError> {
+        let row = sqlx::query(include_str!("../sql/uploads/get_by_id.sql"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(row.as_ref().map(upload_from_row))
+    }
+    
+    // 获取评测过某题目的上传记录（按上传时间从新到旧），用于相似度分析
+    pub async fn get_uploads_by_profile(pool: &DbPool, profile: &str) -> Result<Vec<crate::models::UploadRecord>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/uploads/get_by_profile.sql"))
+        .bind(profile)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(upload_from_row).collect())
+    }
+    
+    // 获取还没有存入内容寻址存储的上传：(上传ID, 文件路径, 用户名)
+    pub async fn get_untracked_uploads(pool: &DbPool) -> Result<Vec<(i32, String, String)>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/uploads/get_untracked.sql"))
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| (row.get("id"), row.get("file_path"), row.get("username"))).collect())
+    }
+}
+
+// 将查询结果的一行转换为上传记录
+fn upload_from_row(row: &sqlx::mysql::MySqlRow) -> crate::models::UploadRecord {
+    crate::models::UploadRecord {
+        id: row.get("id"),
+        filename: row.get("filename"),
+        file_path: row.get("file_path"),
+        file_size: row.get("file_size"),
+        uploaded_at: row.get("uploaded_at"),
+        username: row.try_get("username").ok(),
+        project_dir: row.get("project_dir"),
+        test_id: row.get("test_id"),
+        test_status: row.get("test_status"),
+        test_profile: row.get("test_profile"),
+    }
+}
+
+// 内容寻址存储的文件记录
+pub struct BlobRepo;
+
+impl BlobRepo {
+    // 记录一次上传包含的文件，并增加各文件的引用计数
+    pub async fn add_upload_files(pool: &DbPool, upload_id: i32, files: &[crate::blobstore::StoredFile]) -> Result<(), DbError> {
+        let mut tx = pool.begin().await?;
+        for file in files {
+            sqlx::query(include_str!("../sql/blobs/add_ref.sql"))
+            .bind(&file.hash)
+            .bind(file.size as i64)
+            .execute(&mut tx)
+            .await?;
+            sqlx::query(include_str!("../sql/upload_files/create.sql"))
+            .bind(upload_id)
+            .bind(&file.path)
+            .bind(&file.hash)
+            .bind(file.size as i64)
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+        
+        Ok(())
+    }
+    
+    // 按上传文件表重新计算引用计数（删除用户时上传文件记录随上传一起被级联删除）
+    pub async fn recount_refs(pool: &DbPool) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/blobs/recount.sql"))
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    // 获取没有被引用的文件：(哈希, 大小)
+    pub async fn get_unreferenced(pool: &DbPool) -> Result<Vec<(String, i64)>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/blobs/get_unreferenced.sql"))
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| (row.get("hash"), row.get("size"))).collect())
+    }
+    
+    // 在事务中锁住文件记录，返回是否仍然没有被引用。锁住期间增加引用的写入会等待事务结束
+    pub async fn lock_unreferenced(tx: &mut sqlx::Transaction<'_, sqlx::MySql>, hash: &str) -> Result<bool, DbError> {
+        let row = sqlx::query(include_str!("../sql/blobs/lock_for_delete.sql"))
+        .bind(hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        Ok(row.map_or(false, |row| row.get::<i32, _>("ref_count") == 0))
+    }
+    
+    // 删除文件记录
+    pub async fn delete_blob(tx: &mut sqlx::Transaction<'_, sqlx::MySql>, hash: &str) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/blobs/delete_by_hash.sql"))
+        .bind(hash)
+        .execute(&mut *tx)
+        .await?;
+        
+        Ok(())
+    }
+    
+    // 所有有记录的文件哈希
+    pub async fn get_all_hashes(pool: &DbPool) -> Result<Vec<String>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/blobs/get_all_hashes.sql"))
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(|row| row.get("hash")).collect())
+    }
+    
+    // 评测已结束的上传中 os、user 目录下 target 里的文件：(上传ID, 用户名, 工程根目录, 路径, 大小)
+    pub async fn get_build_outputs(pool: &DbPool) -> Result<Vec<(i32, String, String, String, u64)>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/upload_files/get_build_outputs.sql"))
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get("upload_id"),
+                    row.get("username"),
+                    row.get("project_dir"),
+                    row.get("path"),
+                    row.get::<i64, _>("size").max(0) as u64,
+                )
+            })
+            .collect())
+    }
+    
+    // 删除一次上传中给定 target 目录下的文件记录，并减少对应文件的引用计数
+    pub async fn remove_build_outputs(pool: &DbPool, upload_id: i32, dirs: &[String]) -> Result<(), DbError> {
+        let mut tx = pool.begin().await?;
+        for dir in dirs {
+            let prefix = format!("{}/", dir);
+            sqlx::query(include_str!("../sql/blobs/release_build_outputs.sql"))
+            .bind(upload_id)
+            .bind(&prefix)
+            .bind(&prefix)
+            .execute(&mut tx)
+            .await?;
+            sqlx::query(include_str!("../sql/upload_files/delete_build_outputs.sql"))
+            .bind(upload_id)
+            .bind(&prefix)
+            .bind(&prefix)
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+        
+        Ok(())
+    }
+    
+    // 一次上传包含的文件
+    pub async fn get_upload_files(pool: &DbPool, upload_id: i32) -> Result<Vec<crate::blobstore::StoredFile>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/upload_files/get_by_upload_id.sql"))
+        .bind(upload_id)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(stored_file_from_row).collect())
+    }
+    
+    // 用户所有上传包含的文件
+    pub async fn get_user_files(pool: &DbPool, username: &str) -> Result<Vec<crate::blobstore::StoredFile>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/upload_files/get_by_username.sql"))
+        .bind(username)
         .fetch_all(pool)
         .await?;
         
-        Ok(uploads)
+        Ok(rows.iter().map(stored_file_from_row).collect())
+    }
+    
+    // 用户目录下某个文件或目录中的文件，path 相对 uploads/<用户名>
+    pub async fn get_user_files_under(pool: &DbPool, username: &str, path: &str) -> Result<Vec<crate::blobstore::StoredFile>, DbError> {
+        let dir = format!("{}/", path);
+        let rows = sqlx::query(include_str!("../sql/upload_files/get_by_username_path.sql"))
+        .bind(username)
+        .bind(path)
+        .bind(&dir)
+        .bind(&dir)
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(stored_file_from_row).collect())
+    }
+    
+    // 用户上传的文件总大小（字节），按用户看到的大小计算，不扣除重复的内容
+    pub async fn get_user_usage(pool: &DbPool, user_id: i32) -> Result<u64, DbError> {
+        let used: i64 = sqlx::query(include_str!("../sql/upload_files/user_usage.sql"))
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?
+        .get("used");
+        
+        Ok(used.max(0) as u64)
+    }
+}
+
+fn stored_file_from_row(row: &sqlx::mysql::MySqlRow) -> crate::blobstore::StoredFile {
+    crate::blobstore::StoredFile {
+        path: row.get("path"),
+        hash: row.get("blob_hash"),
+        size: row.get::<i64, _>("size").max(0) as u64,
     }
 }
 
@@ -311,10 +674,12 @@ impl UploadRepo {
 pub struct TestRepo;
 
 impl TestRepo {
-    // 创建新的测试记录
-    pub async fn create_test(pool: &DbPool, user_id: i32) -> Result<i32, DbError> {
+    // 创建新的测试记录，upload_id 为被评测的上传（金丝雀评测没有对应的上传）
+    pub async fn create_test(pool: &DbPool, user_id: i32, profile: &str, upload_id: Option<i32>) -> Result<i32, DbError> {
         let result = sqlx::query(include_str!("../sql/test_results/create.sql"))
         .bind(user_id)
+        .bind(profile)
+        .bind(upload_id)
         .execute(pool)
         .await?;
         
@@ -371,12 +736,88 @@ impl TestRepo {
         Ok(())
     }
     
+    // 保存卡死时采集到的CPU状态报告
+    pub async fn save_hang_report(pool: &DbPool, id: i32, report: &str) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/test_results/update_hang_report.sql"))
+        .bind(report)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    // 保存评测机环境指纹
+    pub async fn save_fingerprint(pool: &DbPool, id: i32, fingerprint: &str) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/test_results/update_fingerprint.sql"))
+        .bind(fingerprint)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    // 记录提交内容和测试包的哈希，用于复用相同提交的结果
+    pub async fn save_hashes(pool: &DbPool, id: i32, submission_hash: &str, bundle_hash: &str) -> Result<(), DbError> {
+        sqlx::query(include_str!("../sql/test_results/update_hashes.sql"))
+        .bind(submission_hash)
+        .bind(bundle_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    // 查找提交内容、测试包、评测配置和评测环境都相同的已完成结果。
+    // 超时、编译失败等带有错误信息的结果可能与评测机负载有关，不会被复用
+    pub async fn find_cached_result(
+        pool: &DbPool,
+        id: i32,
+        submission_hash: &str,
+        bundle_hash: &str,
+        profile: &str,
+        env_fingerprint: &str,
+    ) -> Result<Option<i32>, DbError> {
+        let row = sqlx::query(include_str!("../sql/test_results/find_cached.sql"))
+        .bind(submission_hash)
+        .bind(bundle_hash)
+        .bind(profile)
+        .bind(env_fingerprint)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(row.map(|row| row.get("id")))
+    }
+    
+    // 将已有结果复制到新的测试记录，并标记为复用
+    pub async fn reuse_result(pool: &DbPool, id: i32, source_id: i32) -> Result<(), DbError> {
+        let mut tx = pool.begin().await?;
+        
+        sqlx::query(include_str!("../sql/test_results/copy_result.sql"))
+        .bind(source_id)
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+        
+        sqlx::query(include_str!("../sql/test_cases/copy.sql"))
+        .bind(id)
+        .bind(source_id)
+        .execute(&mut tx)
+        .await?;
+        
+        tx.commit().await?;
+        Ok(())
+    }
+    
     // 获取用户的测试结果
     pub async fn get_user_tests(pool: &DbPool, user_id: i32) -> Result<Vec<crate::models::TestResult>, DbError> {
         let rows = sqlx::query(
             r#"
-            SELECT tr.id, tr.user_id, u.username, tr.status, tr.output, tr.error, 
-                   tr.created_at, tr.updated_at
+            SELECT tr.id, tr.user_id, u.username, tr.status, tr.profile, tr.output, tr.error, tr.hang_report,
+                   tr.env_fingerprint, tr.cached_from, tr.upload_id, tr.created_at, tr.updated_at
             FROM test_results tr
             JOIN users u ON tr.user_id = u.id
             WHERE tr.user_id = ?
@@ -387,78 +828,70 @@ impl TestRepo {
         .fetch_all(pool)
         .await?;
         
-        let mut results = Vec::with_capacity(rows.len());
-        
-        for row in rows {
-            let status = match row.get::<String, _>("status").as_str() {
-                "Pending" => crate::models::TestStatus::Pending,
-                "Running" => crate::models::TestStatus::Running,
-                "Passed" => crate::models::TestStatus::Passed,
-                "Failed" => crate::models::TestStatus::Failed,
-                _ => crate::models::TestStatus::Error,
-            };
-            
-            results.push(crate::models::TestResult {
-                id: row.get("id"),
-                user_id: row.get("user_id"),
-                username: row.get("username"),
-                status,
-                output: row.get("output"),
-                error: row.get("error"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            });
-        }
-        
-        Ok(results)
+        Ok(rows.iter().map(test_result_from_row).collect())
     }
     
     // 获取所有测试结果(管理员使用)
     pub async fn get_all_tests(pool: &DbPool) -> Result<Vec<crate::models::TestResult>, DbError> {
         let rows = sqlx::query(
             r#"
-            SELECT tr.id, tr.user_id, u.username, tr.status, tr.output, tr.error,
-                   tr.created_at, tr.updated_at
+            SELECT tr.id, tr.user_id, u.username, tr.status, tr.profile, tr.output, tr.error, tr.hang_report,
+                   tr.env_fingerprint, tr.cached_from, tr.upload_id, tr.created_at, tr.updated_at
+            FROM test_results tr
+            JOIN users u ON tr.user_id = u.id
+            ORDER BY tr.created_at DESC
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows.iter().map(test_result_from_row).collect())
+    }
+    
+    // 按评测环境指纹搜索测试结果(管理员使用)
+    pub async fn search_tests_by_fingerprint(pool: &DbPool, keyword: &str) -> Result<Vec<crate::models::TestResult>, DbError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT tr.id, tr.user_id, u.username, tr.status, tr.profile, tr.output, tr.error, tr.hang_report,
+                   tr.env_fingerprint, tr.cached_from, tr.upload_id, tr.created_at, tr.updated_at
             FROM test_results tr
             JOIN users u ON tr.user_id = u.id
+            WHERE tr.env_fingerprint LIKE ?
             ORDER BY tr.created_at DESC
             "#,
         )
+        .bind(format!("%{}%", keyword))
         .fetch_all(pool)
         .await?;
         
-        let mut results = Vec::with_capacity(rows.len());
+        Ok(rows.iter().map(test_result_from_row).collect())
+    }
+    
+    // 所有已结束评测的概要，用于在清理时找出每个学生在每道题上的最好成绩
+    pub async fn get_finished_summaries(pool: &DbPool) -> Result<Vec<crate::models::ResultSummary>, DbError> {
+        let rows = sqlx::query(include_str!("../sql/test_results/get_finished_summary.sql"))
+        .fetch_all(pool)
+        .await?;
         
-        for row in rows {
-            let status = match row.get::<String, _>("status").as_str() {
-                "Pending" => crate::models::TestStatus::Pending,
-                "Running" => crate::models::TestStatus::Running,
-                "Passed" => crate::models::TestStatus::Passed,
-                "Failed" => crate::models::TestStatus::Failed,
-                _ => crate::models::TestStatus::Error,
-            };
-            
-            results.push(crate::models::TestResult {
+        Ok(rows
+            .iter()
+            .map(|row| crate::models::ResultSummary {
                 id: row.get("id"),
                 user_id: row.get("user_id"),
-                username: row.get("username"),
-                status,
-                output: row.get("output"),
-                error: row.get("error"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            });
-        }
-        
-        Ok(results)
+                profile: row.get("profile"),
+                status: row.get("status"),
+                upload_id: row.get("upload_id"),
+                passed_cases: row.get::<i64, _>("passed_cases").max(0) as u64,
+            })
+            .collect())
     }
     
     // 获取单个测试结果详情
     pub async fn get_test_by_id(pool: &DbPool, id: i32) -> Result<Option<crate::models::TestResult>, DbError> {
         let row = sqlx::query(
             r#"
-            SELECT tr.id, tr.user_id, u.username, tr.status, tr.output, tr.error,
-                   tr.created_at, tr.updated_at
+            SELECT tr.id, tr.user_id, u.username, tr.status, tr.profile, tr.output, tr.error, tr.hang_report,
+                   tr.env_fingerprint, tr.cached_from, tr.upload_id, tr.created_at, tr.updated_at
             FROM test_results tr
             JOIN users u ON tr.user_id = u.id
             WHERE tr.id = ?
@@ -468,27 +901,97 @@ impl TestRepo {
         .fetch_optional(pool)
         .await?;
         
-        if let Some(row) = row {
-            let status = match row.get::<String, _>("status").as_str() {
-                "Pending" => crate::models::TestStatus::Pending,
-                "Running" => crate::models::TestStatus::Running,
-                "Passed" => crate::models::TestStatus::Passed,
-                "Failed" => crate::models::TestStatus::Failed,
-                _ => crate::models::TestStatus::Error,
+        Ok(row.as_ref().map(test_result_from_row))
+    }
+}
+
+// 将查询结果的一行转换为测试结果
+fn test_result_from_row(row: &sqlx::mysql::MySqlRow) -> crate::models::TestResult {
+    let status = match row.get::<String, _>("status").as_str() {
+        "Pending" => crate::models::TestStatus::Pending,
+        "Running" => crate::models::TestStatus::Running,
+        "Passed" => crate::models::TestStatus::Passed,
+        "Failed" => crate::models::TestStatus::Failed,
+        _ => crate::models::TestStatus::Error,
+    };
+    
+    crate::models::TestResult {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        username: row.get("username"),
+        status,
+        profile: row.get("profile"),
+        output: row.get("output"),
+        error: row.get("error"),
+        hang_report: row.get("hang_report"),
+        env_fingerprint: row.get("env_fingerprint"),
+        cached_from: row.get("cached_from"),
+        upload_id: row.get("upload_id"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+// 用例结果相关的数据库操作
+pub struct TestCaseRepo;
+
+impl TestCaseRepo {
+    // 保存一次评测的所有用例结果（覆盖旧结果）
+    pub async fn save_cases(pool: &DbPool, test_id: i32, cases: &[crate::models::TestCaseResult]) -> Result<(), DbError> {
+        let mut tx = pool.begin().await?;
+        
+        sqlx::query(include_str!("../sql/test_cases/delete_by_test_id.sql"))
+        .bind(test_id)
+        .execute(&mut tx)
+        .await?;
+        
+        for case in cases {
+            let verdict_str = match case.verdict {
+                crate::models::CaseVerdict::Passed => "Passed",
+                crate::models::CaseVerdict::Failed => "Failed",
+                crate::models::CaseVerdict::TimeLimitExceeded => "TLE",
+                crate::models::CaseVerdict::NotRun => "NotRun",
             };
             
-            Ok(Some(crate::models::TestResult {
-                id: row.get("id"),
-                user_id: row.get("user_id"),
-                username: row.get("username"),
-                status,
-                output: row.get("output"),
-                error: row.get("error"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            }))
-        } else {
-            Ok(None)
+            sqlx::query(include_str!("../sql/test_cases/create.sql"))
+            .bind(test_id)
+            .bind(&case.name)
+            .bind(verdict_str)
+            .bind(case.exit_code)
+            .bind(case.duration_ms)
+            .execute(&mut tx)
+            .await?;
         }
+        
+        tx.commit().await?;
+        
+        Ok(())
+    }
+    
+    // 获取一次评测的所有用例结果
+    pub async fn get_cases(pool: &DbPool, test_id: i32) -> Result<Vec<crate::models::TestCaseResult>, DbError> {
+        let cases = sqlx::query(include_str!("../sql/test_cases/get_by_test_id.sql"))
+        .bind(test_id)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let verdict = match row.get::<String, _>("verdict").as_str() {
+                "Passed" => crate::models::CaseVerdict::Passed,
+                "Failed" => crate::models::CaseVerdict::Failed,
+                "TLE" => crate::models::CaseVerdict::TimeLimitExceeded,
+                _ => crate::models::CaseVerdict::NotRun,
+            };
+            
+            crate::models::TestCaseResult {
+                name: row.get("name"),
+                verdict,
+                exit_code: row.get("exit_code"),
+                duration_ms: row.get("duration_ms"),
+            }
+        })
+        .collect();
+        
+        Ok(cases)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,345 @@
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// 每个差异块前后保留的上下文行数
+const CONTEXT_LINES: usize = 3;
+
+// 超过此大小的文件不做逐行比较
+const MAX_DIFF_FILE_SIZE: u64 = 1024 * 1024;
+
+// 比较时忽略的目录（编译产物、版本库和压缩工具附带的目录）
+const IGNORED_DIRS: [&str; 3] = ["target", ".git", "__MACOSX"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl FileChange {
+    pub fn description(&self) -> &'static str {
+        match self {
+            FileChange::Added => "新增",
+            FileChange::Removed => "删除",
+            FileChange::Modified => "修改",
+        }
+    }
+}
+
+// 文件级别的差异摘要
+#[derive(Debug, Clone)]
+pub struct FileSummary {
+    pub path: String,
+    pub change: FileChange,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+    pub too_large: bool, // 超过 MAX_DIFF_FILE_SIZE，只比较是否相同
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineTag {
+    Equal,
+    Delete,
+    Insert,
+}
+
+// 差异中的一行，行号从 1 开始
+#[derive(Debug, Clone)]
+pub struct DiffLine {
+    pub tag: LineTag,
+    pub old_no: Option<usize>,
+    pub new_no: Option<usize>,
+    pub text: String,
+}
+
+// 一个差异块，header 形如 @@ -1,4 +1,5 @@
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub header: String,
+    pub lines: Vec<DiffLine>,
+}
+
+// 单个文件的逐行差异
+pub enum FileDiff {
+    Text(Vec<Hunk>),
+    Binary,
+    TooLarge,
+}
+
+// 列出目录中参与比较的文件，键为以 / 分隔的相对路径
+pub fn list_files(root: &Path, skip: &[&str]) -> std::io::Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    collect_files(root, root, skip, &mut files)?;
+    Ok(files)
+}
+
+fn collect_files(root: &Path, dir: &Path, skip: &[&str], files: &mut BTreeMap<String, PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        let name = entry.file_name().to_string_lossy().to_string();
+        if IGNORED_DIRS.contains(&name.as_str()) || skip.contains(&relative.as_str()) {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(root, &path, skip, files)?;
+        } else if file_type.is_file() {
+            files.insert(relative, path);
+        }
+    }
+    Ok(())
+}
+
+// 比较两棵目录树，返回有变化的文件（按路径排序）
+pub fn diff_trees(old_root: &Path, new_root: &Path, skip: &[&str]) -> std::io::Result<Vec<FileSummary>> {
+    let old_files = list_files(old_root, skip)?;
+    let new_files = list_files(new_root, skip)?;
+    let mut paths: Vec<&String> = old_files.keys().chain(new_files.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut summaries = Vec::new();
+    for path in paths {
+        let (old_path, new_path) = (old_files.get(path), new_files.get(path));
+        let old = old_path.map(|p| read_file(p)).transpose()?;
+        let new = new_path.map(|p| read_file(p)).transpose()?;
+        let change = match (&old, &new, old_path, new_path) {
+            (None, Some(_), _, _) => FileChange::Added,
+            (Some(_), None, _, _) => FileChange::Removed,
+            (Some(Content::Loaded(old)), Some(Content::Loaded(new)), _, _) if old == new => continue,
+            // 大小相同的大文件按哈希比较
+            (Some(Content::TooLarge(old)), Some(Content::TooLarge(new)), Some(old_path), Some(new_path))
+                if old == new && crate::digest::hash_file(old_path)? == crate::digest::hash_file(new_path)? =>
+            {
+                continue
+            }
+            _ => FileChange::Modified,
+        };
+
+        let empty = Content::Loaded(Vec::new());
+        let (additions, deletions, binary, too_large) = match (old.as_ref().unwrap_or(&empty), new.as_ref().unwrap_or(&empty)) {
+            (Content::Loaded(old), Content::Loaded(new)) => match (as_text(old), as_text(new)) {
+                (Some(old), Some(new)) => {
+                    let diff = TextDiff::from_lines(old, new);
+                    let mut counts = (0, 0);
+                    for change in diff.iter_all_changes() {
+                        match change.tag() {
+                            ChangeTag::Insert => counts.0 += 1,
+                            ChangeTag::Delete => counts.1 += 1,
+                            ChangeTag::Equal => {}
+                        }
+                    }
+                    (counts.0, counts.1, false, false)
+                }
+                _ => (0, 0, true, false),
+            },
+            _ => (0, 0, false, true),
+        };
+        summaries.push(FileSummary {
+            path: path.clone(),
+            change,
+            additions,
+            deletions,
+            binary,
+            too_large,
+        });
+    }
+    Ok(summaries)
+}
+
+// 比较同一路径在两棵目录树中的内容，文件不存在时视为空文件
+pub fn diff_file(old_root: &Path, new_root: &Path, path: &str) -> std::io::Result<FileDiff> {
+    let mut contents = Vec::new();
+    for root in [old_root, new_root] {
+        let file = root.join(path);
+        if !file.is_file() {
+            contents.push(Vec::new());
+            continue;
+        }
+        if file.metadata()?.len() > MAX_DIFF_FILE_SIZE {
+            return Ok(FileDiff::TooLarge);
+        }
+        contents.push(std::fs::read(&file)?);
+    }
+
+    let (old, new) = match (as_text(&contents[0]), as_text(&contents[1])) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return Ok(FileDiff::Binary),
+    };
+    let diff = TextDiff::from_lines(old, new);
+    let mut hunks = Vec::new();
+    for group in diff.grouped_ops(CONTEXT_LINES) {
+        let (first, last) = match (group.first(), group.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+        let old_start = first.old_range().start;
+        let new_start = first.new_range().start;
+        let header = format!(
+            "@@ -{},{} +{},{} @@",
+            old_start + 1,
+            last.old_range().end - old_start,
+            new_start + 1,
+            last.new_range().end - new_start
+        );
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                lines.push(DiffLine {
+                    tag: match change.tag() {
+                        ChangeTag::Equal => LineTag::Equal,
+                        ChangeTag::Delete => LineTag::Delete,
+                        ChangeTag::Insert => LineTag::Insert,
+                    },
+                    old_no: change.old_index().map(|i| i + 1),
+                    new_no: change.new_index().map(|i| i + 1),
+                    text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                });
+            }
+        }
+        hunks.push(Hunk { header, lines });
+    }
+    Ok(FileDiff::Text(hunks))
+}
+
+// 参与比较的文件内容，超过 MAX_DIFF_FILE_SIZE 的文件不读入内存，只记录大小
+enum Content {
+    Loaded(Vec<u8>),
+    TooLarge(u64),
+}
+
+fn read_file(path: &Path) -> std::io::Result<Content> {
+    let size = path.metadata()?.len();
+    if size > MAX_DIFF_FILE_SIZE {
+        return Ok(Content::TooLarge(size));
+    }
+    std::fs::read(path).map(Content::Loaded)
+}
+
+// 含有 NUL 或不是 UTF-8 的文件视为二进制文件
+fn as_text(content: &[u8]) -> Option<&str> {
+    if content.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &[u8]) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn summarizes_changed_files_between_trees() {
+        let base = std::env::temp_dir().join(format!("diff_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let (old, new) = (base.join("old"), base.join("new"));
+        write(&old, "os/src/main.rs", b"fn main() {\n    a();\n}\n");
+        write(&new, "os/src/main.rs", b"fn main() {\n    b();\n    c();\n}\n");
+        write(&old, "os/src/same.rs", b"same\n");
+        write(&new, "os/src/same.rs", b"same\n");
+        write(&old, "os/src/old.rs", b"gone\n");
+        write(&new, "os/src/new.rs", b"added\n");
+        write(&new, "os/fs.img", b"\0\x01\x02");
+        write(&new, "os/target/debug/os", b"ignored");
+        write(&new, "user/src/bin/test.rs", b"skipped\n");
+
+        let summaries = diff_trees(&old, &new, &["user"]).unwrap();
+        std::fs::remove_dir_all(&base).unwrap();
+
+        let described: Vec<(&str, FileChange, usize, usize, bool)> = summaries
+            .iter()
+            .map(|s| (s.path.as_str(), s.change, s.additions, s.deletions, s.binary))
+            .collect();
+        assert_eq!(
+            described,
+            [
+                ("os/fs.img", FileChange::Added, 0, 0, true),
+                ("os/src/main.rs", FileChange::Modified, 2, 1, false),
+                ("os/src/new.rs", FileChange::Added, 1, 0, false),
+                ("os/src/old.rs", FileChange::Removed, 0, 1, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn large_files_are_compared_by_hash() {
+        let base = std::env::temp_dir().join(format!("diff_large_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let (old, new) = (base.join("old"), base.join("new"));
+        let large = vec![b'a'; MAX_DIFF_FILE_SIZE as usize + 1];
+        let mut changed = large.clone();
+        *changed.last_mut().unwrap() = b'b';
+        write(&old, "os/fs.img", &large);
+        write(&new, "os/fs.img", &large);
+        write(&old, "os/log.txt", &large);
+        write(&new, "os/log.txt", &changed);
+        write(&new, "os/big.rs", &large);
+
+        let summaries = diff_trees(&old, &new, &[]).unwrap();
+        let too_large = diff_file(&old, &new, "os/log.txt").unwrap();
+        std::fs::remove_dir_all(&base).unwrap();
+
+        let described: Vec<(&str, FileChange, bool, bool)> = summaries
+            .iter()
+            .map(|s| (s.path.as_str(), s.change, s.binary, s.too_large))
+            .collect();
+        assert_eq!(
+            described,
+            [
+                ("os/big.rs", FileChange::Added, false, true),
+                ("os/log.txt", FileChange::Modified, false, true),
+            ]
+        );
+        assert!(matches!(too_large, FileDiff::TooLarge));
+    }
+
+    #[test]
+    fn file_diff_numbers_lines_and_keeps_context() {
+        let base = std::env::temp_dir().join(format!("diff_file_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let (old, new) = (base.join("old"), base.join("new"));
+        let old_lines: Vec<String> = (1..=10).map(|i| format!("line {}", i)).collect();
+        let mut new_lines = old_lines.clone();
+        new_lines[4] = "changed".to_string();
+        write(&old, "a.rs", old_lines.join("\r\n").as_bytes());
+        write(&new, "a.rs", new_lines.join("\r\n").as_bytes());
+        write(&new, "b.bin", b"\0");
+
+        let diff = diff_file(&old, &new, "a.rs").unwrap();
+        let binary = diff_file(&old, &new, "b.bin").unwrap();
+        std::fs::remove_dir_all(&base).unwrap();
+
+        let hunks = match diff {
+            FileDiff::Text(hunks) => hunks,
+            _ => panic!("文本文件应当逐行比较"),
+        };
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].header, "@@ -2,7 +2,7 @@");
+        let changed: Vec<(LineTag, Option<usize>, Option<usize>, &str)> = hunks[0]
+            .lines
+            .iter()
+            .filter(|line| line.tag != LineTag::Equal)
+            .map(|line| (line.tag, line.old_no, line.new_no, line.text.as_str()))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                (LineTag::Delete, Some(5), None, "line 5"),
+                (LineTag::Insert, None, Some(5), "changed"),
+            ]
+        );
+        assert!(matches!(binary, FileDiff::Binary));
+    }
+}
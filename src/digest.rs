@@ -0,0 +1,51 @@
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+// 计算目录树的内容哈希：按相对路径排序后依次哈希路径和文件内容，
+// 结果与文件的修改时间、遍历顺序无关。skip 中的目录名会被跳过（如 target）
+pub fn hash_dir(dir: &Path, skip: &[&str]) -> std::io::Result<String> {
+    let mut files = Vec::new();
+    collect_files(dir, dir, skip, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for relative in files {
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(std::fs::read(dir.join(&relative))?);
+        hasher.update([0u8]);
+    }
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+// 计算单个文件的内容哈希，按块读取，适用于大文件
+pub fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn collect_files(root: &Path, dir: &Path, skip: &[&str], files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if skip.iter().any(|name| entry.file_name() == *name) {
+                continue;
+            }
+            collect_files(root, &path, skip, files)?;
+        } else if file_type.is_file() {
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
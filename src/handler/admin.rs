@@ -1,6 +1,6 @@
 use crate::database::UserRepo;
-use crate::models::{AppState, User, UserRole, UserCreateForm, UserUpdateForm};
-use crate::templates::{admin_panel_template, alert_redirect_template}; // Import alert_redirect_template
+use crate::models::{AppState, QuotaForm, User, UserRole, UserCreateForm, UserUpdateForm};
+use crate::templates::{admin_panel_template, alert_redirect_template, judge_health_template, vendor_crates_template}; // Import alert_redirect_template
 use axum::{
     extract::{Extension, Form, Path, State},
     response::{Html, IntoResponse},
@@ -206,4 +206,111 @@ pub async fn delete_user(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// 设置用户的存储配额，留空恢复为角色的默认配额
+pub async fn update_user_quota(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+    Form(form): Form<QuotaForm>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能设置存储配额", "/")).into_response();
+    }
+
+    let quota_mb = match form.quota_mb.trim() {
+        "" => None,
+        value => match value.parse::<i32>() {
+            Ok(mb) if mb >= 0 => Some(mb),
+            _ => return Html(alert_redirect_template("配额必须是非负整数（MB）", "/admin/users")).into_response(),
+        },
+    };
+
+    let result = match UserRepo::get_user_by_username(&state.db_pool, &username).await {
+        Ok(Some(target)) => UserRepo::update_quota(&state.db_pool, target.id, quota_mb).await.map(|_| true),
+        Ok(None) => Ok(false),
+        Err(e) => Err(e),
+    };
+    let (error, success) = match result {
+        Ok(true) => (None, Some(format!("用户 {} 的存储配额已更新", username))),
+        Ok(false) => (Some(format!("用户 {} 不存在", username)), None),
+        Err(e) => {
+            tracing::error!("Failed to update quota of {}: {}", username, e);
+            (Some(format!("设置存储配额失败: {}", e)), None)
+        }
+    };
+
+    match UserRepo::get_all_users(&state.db_pool).await {
+        Ok(users) => Html(admin_panel_template(&users, error.as_deref(), success.as_deref())).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to get users: {}", e);
+            Html(alert_redirect_template("获取用户列表失败！", "/admin/users")).into_response()
+        }
+    }
+}
+
+// 评测机状态页面
+pub async fn judge_health(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+
+    let health = state.test_queue.health_snapshot().await;
+    Html(judge_health_template(&health, None)).into_response()
+}
+
+// 重新运行环境检查
+pub async fn recheck_judge(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+
+    let message = if state.test_queue.run_preflight().await {
+        "环境检查完成，所有评测配置均可用"
+    } else {
+        "环境检查完成，部分评测配置缺少必需的工具"
+    };
+    let health = state.test_queue.health_snapshot().await;
+    Html(judge_health_template(&health, Some(message))).into_response()
+}
+
+// 手动提交金丝雀评测
+pub async fn submit_canary(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+
+    match state.test_queue.submit_canary().await {
+        Ok(test_id) => {
+            let health = state.test_queue.health_snapshot().await;
+            let message = format!("已提交金丝雀评测 #{}", test_id);
+            Html(judge_health_template(&health, Some(&message))).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to submit canary: {}", e);
+            Html(alert_redirect_template(&format!("提交金丝雀评测失败: {}", e), "/admin/health")).into_response()
+        }
+    }
+}
+
+// 依赖镜像中允许使用的crate
+pub async fn vendor_crates(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+
+    Html(vendor_crates_template(state.test_queue.vendor())).into_response()
+}
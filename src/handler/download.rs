@@ -0,0 +1,208 @@
+use crate::archive;
+use axum::body::{boxed, Body, Bytes};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Response;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::mpsc;
+
+// 发送给客户端的每块数据大小
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// 生成压缩包时最多缓存的数据块数，客户端读取较慢时打包线程会等待
+const ZIP_CHANNEL_CAPACITY: usize = 8;
+
+// 下载文件的 Content-Disposition。filename 是给旧客户端的 ASCII 文件名，
+// filename* 按 RFC 5987 给出 UTF-8 编码的原始文件名（如中文文件名）
+pub fn content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| if (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        urlencoding::encode(file_name)
+    )
+}
+
+// 请求的字节范围
+enum ByteRange {
+    Full,
+    Partial(u64, u64), // 闭区间
+    Unsatisfiable,
+}
+
+// 解析 Range 请求头，只支持单个范围：bytes=start-end、bytes=start-、bytes=-suffix。
+// 多个范围或无法解析时按完整文件返回
+fn parse_range(headers: &HeaderMap, len: u64) -> ByteRange {
+    let spec = match headers.get(header::RANGE).and_then(|value| value.to_str().ok()) {
+        Some(value) => match value.trim().strip_prefix("bytes=") {
+            Some(spec) if !spec.contains(',') => spec.trim(),
+            _ => return ByteRange::Full,
+        },
+        None => return ByteRange::Full,
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some(parts) => parts,
+        None => return ByteRange::Full,
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+    if len == 0 || range.0 >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(range.0, range.1)
+}
+
+// 以流的方式发送文件，支持 Range 请求断点续传
+pub async fn file_response(path: PathBuf, file_name: &str, headers: &HeaderMap) -> io::Result<Response> {
+    let mut file = tokio::fs::File::open(&path).await?;
+    let len = file.metadata().await?.len();
+
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_DISPOSITION, content_disposition(file_name))
+        .header(header::ACCEPT_RANGES, "bytes");
+    let response = match parse_range(headers, len) {
+        ByteRange::Full => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, len)
+            .body(boxed(stream_file(file, len))),
+        ByteRange::Partial(start, end) => {
+            file.seek(io::SeekFrom::Start(start)).await?;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len))
+                .header(header::CONTENT_LENGTH, end - start + 1)
+                .body(boxed(stream_file(file, end - start + 1)))
+        }
+        ByteRange::Unsatisfiable => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(boxed(Body::empty())),
+    };
+    response.map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+// 从文件当前位置读取 remaining 字节作为响应体
+fn stream_file(mut file: tokio::fs::File, mut remaining: u64) -> Body {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        while remaining > 0 {
+            let want = remaining.min(CHUNK_SIZE as u64) as usize;
+            match file.read(&mut buf[..want]).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    remaining -= n as u64;
+                    // 客户端断开连接
+                    if sender.send_data(Bytes::copy_from_slice(&buf[..n])).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("读取下载文件失败: {}", e);
+                    break;
+                }
+            }
+        }
+        // 文件在发送过程中被截断，长度与 Content-Length 不符，中断连接让客户端知道下载不完整
+        if remaining > 0 {
+            sender.abort();
+        }
+    });
+    body
+}
+
+// 把目录打包成 zip 边生成边发送，不在内存或磁盘上保存整个压缩包。
+// 文件数或大小超出 zip 格式限制时返回错误
+pub async fn zip_response(dir: PathBuf, archive_name: &str) -> io::Result<Response> {
+    let prefix = archive_name.trim_end_matches(".zip").to_string();
+    let entries = tokio::task::spawn_blocking(move || archive::collect_entries(&dir, &prefix))
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))??;
+
+    let (tx, mut rx) = mpsc::channel::<io::Result<Bytes>>(ZIP_CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        let writer = ChannelWriter {
+            tx: tx.clone(),
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        };
+        if let Err(e) = archive::write_zip(&entries, writer) {
+            // 客户端断开时发送也会失败，这里只是尽量通知
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        while let Some(chunk) = rx.recv().await {
+            match chunk {
+                Ok(chunk) => {
+                    if sender.send_data(chunk).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("生成压缩包失败: {}", e);
+                    sender.abort();
+                    return;
+                }
+            }
+        }
+    });
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_DISPOSITION, content_disposition(archive_name))
+        .body(boxed(body))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+// 把打包线程写出的数据按块送入通道
+struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+    buf: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn send_buffered(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE)));
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "客户端已断开连接"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send_buffered()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffered()
+    }
+}
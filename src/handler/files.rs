@@ -1,14 +1,18 @@
-use crate::database::UserRepo;
-use crate::models::{AppState, User, UserRole};
+use crate::database::{UploadRepo, UserRepo};
+use crate::diff;
+use crate::models::{AppState, DiffQuery, UploadRecord, User, UserRole};
+use super::download;
 // Import new template functions and alert_redirect_template
-use crate::templates::{alert_redirect_template, files_list_template, build_files_list_content_html, upload_page_template};
+use crate::templates::{alert_redirect_template, files_list_template, build_files_list_content_html, file_view_template, upload_page_template, submission_diff_template};
 use axum::{
-    extract::{Extension, Path, State},
-    response::{Html, IntoResponse, Redirect},
+    extract::{Extension, Path, Query, State},
+    http::HeaderMap,
+    response::{Html, IntoResponse},
 };
+use std::path::PathBuf;
 
 // 获取目录大小的辅助函数
-fn get_dir_size(dir_path: &std::path::Path) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<u64, std::io::Error>> + Send + '_>> {
+pub(super) fn get_dir_size(dir_path: &std::path::Path) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<u64, std::io::Error>> + Send + '_>> {
     Box::pin(async move {
         let mut total_size = 0;
         let mut read_dir = tokio::fs::read_dir(dir_path).await?;
@@ -26,6 +30,52 @@ fn get_dir_size(dir_path: &std::path::Path) -> std::pin::Pin<Box<dyn std::future
     })
 }
 
+// 在线查看的文本文件大小上限
+const MAX_VIEW_FILE_SIZE: u64 = 1024 * 1024;
+
+// 将用户目录下的相对路径解析为规范化的真实路径，返回 (用户目录, 目标路径)。
+// 规范化会展开 .. 和符号链接，结果不在用户目录内时拒绝访问
+async fn resolve_user_path(username: &str, relative: &str) -> Result<(PathBuf, PathBuf), &'static str> {
+    let base = tokio::fs::canonicalize(format!("uploads/{}", username))
+        .await
+        .map_err(|_| "无法访问用户目录")?;
+    let target = tokio::fs::canonicalize(base.join(relative.trim_start_matches('/')))
+        .await
+        .map_err(|_| "文件不存在")?;
+    if !target.starts_with(&base) {
+        return Err("不允许访问用户目录之外的文件");
+    }
+    Ok((base, target))
+}
+
+// 检查权限并确认目标用户存在，失败时返回提示页面。
+// path 是要访问的文件或目录（相对用户目录），其中在其他节点上传的文件先从存储中取到本地；
+// 为 None 时只列出用户目录，只建立各次上传的顶层目录，内容在浏览到时再恢复
+async fn check_file_access(user: &User, state: &AppState, target_username: &str, path: Option<&str>) -> Result<(), axum::response::Response> {
+    // 检查权限：只能查看自己的或者管理员可以查看所有人的
+    if user.username != target_username && !matches!(user.role, UserRole::Admin) {
+        return Err(Html(alert_redirect_template("您没有权限查看此用户的文件", "/")).into_response());
+    }
+
+    match UserRepo::get_user_by_username(&state.db_pool, target_username).await {
+        Ok(Some(_)) => {
+            let restored = match path {
+                Some(path) => state.blobs.restore_path(&state.db_pool, target_username, path).await,
+                None => state.blobs.restore_top_level(&state.db_pool, target_username).await,
+            };
+            if let Err(e) = restored {
+                tracing::warn!("Failed to restore {}/{}: {}", target_username, path.unwrap_or_default(), e);
+            }
+            Ok(())
+        }
+        Ok(None) => Err(Html(alert_redirect_template("用户不存在", "/")).into_response()),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            Err(Html(alert_redirect_template("数据库错误", "/")).into_response())
+        }
+    }
+}
+
 // 查看用户所有文件列表
 #[axum::debug_handler]
 pub async fn view_user_files(
@@ -33,36 +83,54 @@ pub async fn view_user_files(
     State(state): State<AppState>,
     Path(target_username): Path<String>,
 ) -> impl IntoResponse {
-    // 检查权限：只能查看自己的或者管理员可以查看所有人的 - 注意！修复条件语法
-    if user.username != target_username && !matches!(user.role, UserRole::Admin) {
-        return Html(alert_redirect_template("您没有权限查看此用户的文件", "/")).into_response();
+    if let Err(response) = check_file_access(&user, &state, &target_username, None).await {
+        return response;
     }
-    
-    // 获取目标用户信息，加上下划线前缀避免未使用警告
-    let _target_user = match UserRepo::get_user_by_username(&state.db_pool, &target_username).await {
-        Ok(Some(u)) => u,
-        Ok(None) => return Html(alert_redirect_template("用户不存在", "/")).into_response(),
-        Err(e) => {
-            tracing::error!("Database error: {}", e);
-            return Html(alert_redirect_template("数据库错误", "/")).into_response();
-        }
-    };
-    
-    // 读取用户目录内容
-    let user_dir = format!("uploads/{}", target_username);
-    let mut entries = Vec::new();
-    
+
     // 确保用户目录存在
-    match tokio::fs::create_dir_all(&user_dir).await {
-        Ok(_) => (),
+    if let Err(e) = tokio::fs::create_dir_all(format!("uploads/{}", target_username)).await {
+        tracing::error!("Failed to ensure user directory exists: {}", e);
+        return Html(alert_redirect_template("无法访问用户目录", "/")).into_response();
+    }
+
+    browse_path(&target_username, "").await
+}
+
+// 浏览用户目录下的任意一级子目录，或在线查看其中的文件
+pub async fn browse_user_files(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path((target_username, path)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if let Err(response) = check_file_access(&user, &state, &target_username, Some(&path)).await {
+        return response;
+    }
+
+    browse_path(&target_username, &path).await
+}
+
+async fn browse_path(target_username: &str, path: &str) -> axum::response::Response {
+    let back_url = format!("/files/{}", urlencoding::encode(target_username));
+    let (base, target) = match resolve_user_path(target_username, path).await {
+        Ok(resolved) => resolved,
+        Err(message) => return Html(alert_redirect_template(message, &back_url)).into_response(),
+    };
+    let relative = target.strip_prefix(&base).unwrap_or(&target).to_string_lossy().replace('\\', "/");
+
+    let metadata = match tokio::fs::metadata(&target).await {
+        Ok(metadata) => metadata,
         Err(e) => {
-            tracing::error!("Failed to ensure user directory exists: {}", e);
-            return Html(alert_redirect_template("无法访问用户目录", "/")).into_response();
+            tracing::error!("Failed to stat {}: {}", target.display(), e);
+            return Html(alert_redirect_template("文件不存在", &back_url)).into_response();
         }
+    };
+    if metadata.is_file() {
+        return view_file(target_username, &relative, &target, metadata.len()).await;
     }
-    
+
     // 读取目录内容
-    match tokio::fs::read_dir(&user_dir).await {
+    let mut entries = Vec::new();
+    match tokio::fs::read_dir(&target).await {
         Ok(mut dir) => {
             while let Ok(Some(entry)) = dir.next_entry().await {
                 if let Ok(metadata) = entry.metadata().await {
@@ -84,34 +152,111 @@ pub async fn view_user_files(
             }
         }
         Err(e) => {
-            tracing::error!("Failed to read user directory: {}", e);
-            return Html(alert_redirect_template("读取用户目录失败", "/")).into_response();
+            tracing::error!("Failed to read directory {}: {}", target.display(), e);
+            return Html(alert_redirect_template("读取用户目录失败", &back_url)).into_response();
         }
     }
     
-    // 按文件名排序
-    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    // 目录在前，同类按文件名排序
+    entries.sort_by(|a, b| b.3.cmp(&a.3).then_with(|| a.0.cmp(&b.0)));
 
     // 构建文件列表内容的 HTML - 使用模板函数
-    let files_content_html = build_files_list_content_html(&entries, &target_username);
+    let files_content_html = build_files_list_content_html(&entries, target_username, &relative);
 
     // 返回文件列表页面 - 使用模板函数
-    Html(files_list_template(&target_username, &files_content_html)).into_response()
+    Html(files_list_template(target_username, &relative, &files_content_html)).into_response()
+}
+
+// 在线查看文件：文本文件带行号显示（Rust 源码带语法高亮），其他文件只提供下载
+async fn view_file(target_username: &str, relative: &str, target: &std::path::Path, size: u64) -> axum::response::Response {
+    let content = if size > MAX_VIEW_FILE_SIZE {
+        None
+    } else {
+        match tokio::fs::read(target).await {
+            Ok(bytes) if !bytes.contains(&0) => String::from_utf8(bytes).ok(),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::error!("Failed to read file {}: {}", target.display(), e);
+                let back_url = format!("/files/{}", urlencoding::encode(target_username));
+                return Html(alert_redirect_template("读取文件失败", &back_url)).into_response();
+            }
+        }
+    };
+
+    Html(file_view_template(target_username, relative, size, content.as_deref())).into_response()
 }
 
-// 下载文件处理函数
+// 下载文件处理函数，路径可以是用户目录下任意一级的文件；目录打包成 zip 下载
 pub async fn download_file(
     Extension(user): Extension<User>,
     State(state): State<AppState>,
-    Path((username, filename)): Path<(String, String)>,
+    Path((username, path)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(response) = check_file_access(&user, &state, &username, Some(&path)).await {
+        return response;
+    }
+
+    let back_url = format!("/files/{}", urlencoding::encode(&username));
+    let (_, target) = match resolve_user_path(&username, &path).await {
+        Ok(resolved) => resolved,
+        Err(message) => return Html(alert_redirect_template(message, &back_url)).into_response(),
+    };
+    let filename = target.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+    let response = if target.is_dir() {
+        download::zip_response(target, &format!("{}.zip", filename)).await
+    } else {
+        download::file_response(target, &filename, &headers).await
+    };
+    response.unwrap_or_else(|e| {
+        tracing::error!("Failed to download {}/{}: {}", username, path, e);
+        Html(alert_redirect_template(&format!("下载失败: {}", e), &back_url)).into_response()
+    })
+}
+
+// 把用户的整个目录打包成 zip 下载
+pub async fn download_user_archive(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(username): Path<String>,
+) -> impl IntoResponse {
+    if let Err(response) = check_file_access(&user, &state, &username, Some("")).await {
+        return response;
+    }
+
+    let back_url = format!("/files/{}", urlencoding::encode(&username));
+    let (base, _) = match resolve_user_path(&username, "").await {
+        Ok(resolved) => resolved,
+        Err(message) => return Html(alert_redirect_template(message, &back_url)).into_response(),
+    };
+    download::zip_response(base, &format!("{}.zip", username))
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to archive files of {}: {}", username, e);
+            Html(alert_redirect_template(&format!("下载失败: {}", e), &back_url)).into_response()
+        })
+}
+
+// 上传文件页面处理函数
+pub async fn upload_page(
+    Extension(_user): Extension<User>,
+) -> impl IntoResponse {
+    // 使用模板函数
+    Html(upload_page_template()).into_response()
+}
+// 对比一次上传与课程初始代码或同一学生的另一次上传
+pub async fn diff_submission(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path((username, upload_id)): Path<(String, i32)>,
+    Query(query): Query<DiffQuery>,
 ) -> impl IntoResponse {
-    // 检查权限：只能下载自己的或者管理员可以下载所有人的
     if user.username != username && !matches!(user.role, UserRole::Admin) {
-        return Html(alert_redirect_template("您没有权限下载此文件", "/")).into_response();
+        return Html(alert_redirect_template("您没有权限查看此用户的提交", "/")).into_response();
     }
-    
-    // 获取目标用户信息
-    let _target_user = match UserRepo::get_user_by_username(&state.db_pool, &username).await {
+
+    let target_user = match UserRepo::get_user_by_username(&state.db_pool, &username).await {
         Ok(Some(u)) => u,
         Ok(None) => return Html(alert_redirect_template("用户不存在", "/")).into_response(),
         Err(e) => {
@@ -119,46 +264,90 @@ pub async fn download_file(
             return Html(alert_redirect_template("数据库错误", "/")).into_response();
         }
     };
-    
-    // 构建文件路径
-    let file_path = format!("uploads/{}/{}", username, filename);
-    
-    // 检查文件是否存在
-    match tokio::fs::metadata(&file_path).await {
-        Ok(metadata) => {
-            if metadata.is_dir() {
-                // 如果是目录，重定向到目录列表
-                Redirect::to(&format!("/files/{}/{}", username, filename)).into_response()
-            } else {
-                // 如果是文件，提供下载
-                match tokio::fs::read(&file_path).await {
-                    Ok(content) => {
-                        // 设置正确的Content-Type和Content-Disposition头
-                        let filename_encoded = urlencoding::encode(&filename);
-                        let headers = [
-                            (axum::http::header::CONTENT_TYPE, "application/octet-stream"),
-                            (axum::http::header::CONTENT_DISPOSITION, &format!("attachment; filename=\"{}\"", filename_encoded)),
-                        ];
-                        
-                        (headers, content).into_response()
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to read file: {}", e);
-                        Html(alert_redirect_template("读取文件失败", "/")).into_response()
-                    }
-                }
-            }
+
+    let upload = match UploadRepo::get_upload_by_id(&state.db_pool, upload_id).await {
+        Ok(Some(upload)) if upload.username.as_deref() == Some(username.as_str()) => upload,
+        Ok(_) => return Html(alert_redirect_template("上传记录不存在", "/uploads")).into_response(),
+        Err(e) => {
+            tracing::error!("Database error: {}", e);
+            return Html(alert_redirect_template("数据库错误", "/")).into_response();
+        }
+    };
+    let new_root = match upload.project_dir.as_ref() {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => return Html(alert_redirect_template("该上传没有通过检查的工程目录，无法对比", "/uploads")).into_response(),
+    };
+
+    // 同一学生的其他上传都可以作为对比对象
+    let others: Vec<UploadRecord> = match UploadRepo::get_user_uploads(&state.db_pool, target_user.id).await {
+        Ok(uploads) => uploads
+            .into_iter()
+            .filter(|other| other.id != upload.id && other.project_dir.is_some())
+            .collect(),
+        Err(e) => {
+            tracing::error!("Failed to get uploads: {}", e);
+            Vec::new()
         }
-        Err(_) => {
-            Html(alert_redirect_template("文件不存在", "/")).into_response()
+    };
+
+    let profile = upload.test_profile.as_deref().unwrap_or(crate::tester::DEFAULT_PROFILE);
+    let against = query.against.clone().unwrap_or_else(|| "baseline".to_string());
+    let old_root = if against == "baseline" {
+        state.test_queue.baseline_dir(profile)
+    } else {
+        against
+            .parse::<i32>()
+            .ok()
+            .and_then(|id| others.iter().find(|other| other.id == id))
+            .and_then(|other| other.project_dir.as_ref().map(std::path::PathBuf::from))
+    };
+    let old_root = match old_root {
+        Some(root) => root,
+        None if against == "baseline" => {
+            return Html(alert_redirect_template(&format!("题目 {} 没有配置初始代码", profile), "/uploads")).into_response()
+        }
+        None => return Html(alert_redirect_template("对比的上传不存在", "/uploads")).into_response(),
+    };
+
+    // 对比的两次上传都可能是在其他节点上传的，只恢复这两次上传
+    let restore_ids = std::iter::once(upload.id).chain(against.parse::<i32>().ok());
+    for id in restore_ids {
+        if let Err(e) = state.blobs.restore_upload(&state.db_pool, id, &username).await {
+            tracing::warn!("Failed to restore upload {} of {}: {}", id, username, e);
         }
     }
-}
 
-// 上传文件页面处理函数
-pub async fn upload_page(
-    Extension(_user): Extension<User>,
-) -> impl IntoResponse {
-    // 使用模板函数
-    Html(upload_page_template()).into_response()
-}
\ No newline at end of file
+    let split = query.mode.as_deref() == Some("split");
+    let file = query.file.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let summaries = diff::diff_trees(&old_root, &new_root, &[crate::tester::TEST_BUNDLE_DIR])?;
+        // 只允许查看摘要中列出的文件，防止通过路径访问工程之外的文件
+        let file_diff = match file.filter(|path| summaries.iter().any(|s| &s.path == path)) {
+            Some(path) => Some((diff::diff_file(&old_root, &new_root, &path)?, path)),
+            None => None,
+        };
+        Ok::<_, std::io::Error>((summaries, file_diff))
+    })
+    .await;
+    let (summaries, file_diff) = match result {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => {
+            tracing::error!("Failed to diff upload {}: {}", upload_id, e);
+            return Html(alert_redirect_template("对比提交失败", "/uploads")).into_response();
+        }
+        Err(e) => {
+            tracing::error!("Diff task for upload {} panicked: {}", upload_id, e);
+            return Html(alert_redirect_template("对比提交失败", "/uploads")).into_response();
+        }
+    };
+
+    Html(submission_diff_template(
+        &upload,
+        &others,
+        &against,
+        split,
+        &summaries,
+        file_diff.as_ref().map(|(diff, path)| (path.as_str(), diff)),
+    ))
+    .into_response()
+}
@@ -1,12 +1,17 @@
 mod admin;
 mod auth;
+mod download;
 mod files;
+mod retention;
+mod similarity;
 mod upload;
 mod test_results;
 
 // 重新导出所有公开函数
-pub use admin::{admin_panel, create_user, delete_user, update_user};
+pub use admin::{admin_panel, create_user, delete_user, judge_health, recheck_judge, submit_canary, update_user, update_user_quota, vendor_crates};
 pub use auth::{login_handler, login_page, logout_handler};
-pub use files::{download_file, view_user_files};
-pub use upload::{index_handler, upload_handler, view_uploads};
-pub use test_results::{view_results, view_result_detail};
\ No newline at end of file
+pub use files::{browse_user_files, diff_submission, download_file, download_user_archive, view_user_files};
+pub use retention::{preview_retention, retention_page, run_retention};
+pub use similarity::{export_similarity, similarity_page, similarity_pair, start_similarity};
+pub use upload::{delete_upload, index_handler, rename_upload, upload_handler, view_uploads};
+pub use test_results::{download_artifact, download_artifacts_zip, view_results, view_result_detail};
\ No newline at end of file
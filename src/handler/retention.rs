@@ -0,0 +1,60 @@
+use crate::models::{AppState, User, UserRole};
+use crate::templates::{alert_redirect_template, retention_template};
+use axum::{
+    extract::{Extension, State},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+
+// 存储清理页面：清理规则和最近一次清理的报告
+pub async fn retention_page(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+
+    let report = state.retention.last_report().await;
+    Html(retention_template(
+        state.retention.policy(),
+        report.as_deref(),
+        state.retention.is_running(),
+    ))
+    .into_response()
+}
+
+// 试运行：只生成将要删除的内容的报告
+pub async fn preview_retention(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+    start_retention(&state, true)
+}
+
+// 立即按规则清理
+pub async fn run_retention(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+    tracing::info!("管理员 {} 手动触发了存储清理", user.username);
+    start_retention(&state, false)
+}
+
+// 在后台清理，完成后报告显示在存储清理页面上
+fn start_retention(state: &AppState, dry_run: bool) -> Response {
+    if state.retention.is_running() {
+        return Html(alert_redirect_template("清理正在进行中，请稍后再试", "/admin/retention")).into_response();
+    }
+    let jobs = state.retention.clone();
+    let pool = state.db_pool.clone();
+    tokio::spawn(async move {
+        jobs.run(&pool, dry_run, false).await;
+    });
+    Redirect::to("/admin/retention").into_response()
+}
@@ -0,0 +1,171 @@
+use crate::database::UploadRepo;
+use crate::models::{AppState, SimilarityForm, SimilarityPairQuery, SimilarityQuery, User, UserRole};
+use crate::similarity::{self, Submission};
+use crate::templates::{alert_redirect_template, similarity_pair_template, similarity_template};
+use axum::{
+    extract::{Extension, Form, Query, State},
+    response::{Html, IntoResponse, Redirect},
+};
+use std::collections::HashSet;
+
+// 代码相似度分析页面
+pub async fn similarity_page(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Query(query): Query<SimilarityQuery>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+
+    let profile = query.profile.unwrap_or_else(|| crate::tester::DEFAULT_PROFILE.to_string());
+    if !state.test_queue.has_profile(&profile) {
+        return Html(alert_redirect_template("题目评测配置不存在", "/admin/similarity")).into_response();
+    }
+
+    let analysis = state.similarity.state(&profile).await;
+    Html(similarity_template(
+        &state.test_queue.profile_names(),
+        &profile,
+        analysis.as_ref(),
+    ))
+    .into_response()
+}
+
+// 在后台分析题目下每个学生最近一次提交的相似度
+pub async fn start_similarity(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Form(form): Form<SimilarityForm>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+    if !state.test_queue.has_profile(&form.profile) {
+        return Html(alert_redirect_template("题目评测配置不存在", "/admin/similarity")).into_response();
+    }
+    let page_url = format!("/admin/similarity?profile={}", urlencoding::encode(&form.profile));
+
+    let uploads = match UploadRepo::get_uploads_by_profile(&state.db_pool, &form.profile).await {
+        Ok(uploads) => uploads,
+        Err(e) => {
+            tracing::error!("Failed to get uploads for profile {}: {}", form.profile, e);
+            return Html(alert_redirect_template("数据库错误", &page_url)).into_response();
+        }
+    };
+
+    // 上传记录按从新到旧排列，文件在后台任务中从存储中取出，每个学生取最近一次提交
+    let candidates: Vec<Submission> = uploads
+        .into_iter()
+        .filter_map(|upload| {
+            Some(Submission {
+                upload_id: upload.id,
+                username: upload.username?,
+                root: std::path::PathBuf::from(upload.project_dir?),
+            })
+        })
+        .collect();
+    let students: HashSet<&str> = candidates.iter().map(|candidate| candidate.username.as_str()).collect();
+    if students.len() < 2 {
+        return Html(alert_redirect_template("该题目的提交少于两份，无法比较", &page_url)).into_response();
+    }
+
+    tracing::info!("开始分析题目 {} 的 {} 份提交的相似度", form.profile, students.len());
+    let started = state
+        .similarity
+        .start(
+            form.profile.clone(),
+            state.test_queue.source_dir(&form.profile).to_string(),
+            state.test_queue.baseline_dir(&form.profile),
+            candidates,
+            state.blobs.clone(),
+            state.db_pool.clone(),
+        )
+        .await;
+    if !started {
+        return Html(alert_redirect_template("该题目的相似度分析正在进行中", &page_url)).into_response();
+    }
+    Redirect::to(&page_url).into_response()
+}
+
+// 并排显示两份提交中相同的代码片段
+pub async fn similarity_pair(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Query(query): Query<SimilarityPairQuery>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+    let page_url = format!("/admin/similarity?profile={}", urlencoding::encode(&query.profile));
+
+    let report = match state.similarity.report(&query.profile).await {
+        Some(report) => report,
+        None => return Html(alert_redirect_template("该题目还没有完成的相似度分析", &page_url)).into_response(),
+    };
+    let pair = report.pairs.iter().find(|pair| {
+        let ids = (report.submissions[pair.a].upload_id, report.submissions[pair.b].upload_id);
+        ids == (query.a, query.b) || ids == (query.b, query.a)
+    });
+    let pair = match pair {
+        Some(pair) => pair.clone(),
+        None => return Html(alert_redirect_template("分析报告中没有这两份提交", &page_url)).into_response(),
+    };
+
+    let task_report = report.clone();
+    let task_pair = pair.clone();
+    let sources = tokio::task::spawn_blocking(move || {
+        let root_a = &task_report.submissions[task_pair.a].root;
+        let root_b = &task_report.submissions[task_pair.b].root;
+        task_pair
+            .regions
+            .iter()
+            .map(|region| {
+                (
+                    similarity::region_source(root_a, &task_report.source_dir, &region.file_a, region.lines_a),
+                    similarity::region_source(root_b, &task_report.source_dir, &region.file_b, region.lines_b),
+                )
+            })
+            .collect::<Vec<_>>()
+    })
+    .await;
+    let sources = match sources {
+        Ok(sources) => sources,
+        Err(e) => {
+            tracing::error!("Failed to read similarity regions: {}", e);
+            return Html(alert_redirect_template("读取源码失败", &page_url)).into_response();
+        }
+    };
+
+    Html(similarity_pair_template(&report, &pair, &sources)).into_response()
+}
+
+// 导出题目最近一次相似度分析的结果（CSV）
+pub async fn export_similarity(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Query(query): Query<SimilarityQuery>,
+) -> impl IntoResponse {
+    if !matches!(user.role, UserRole::Admin) {
+        return Html(alert_redirect_template("只有管理员才能访问此页面", "/")).into_response();
+    }
+
+    let profile = query.profile.unwrap_or_else(|| crate::tester::DEFAULT_PROFILE.to_string());
+    match state.similarity.report(&profile).await {
+        Some(report) => {
+            let headers = [
+                (axum::http::header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (
+                    axum::http::header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"similarity-{}.csv\"", urlencoding::encode(&profile)),
+                ),
+            ];
+            (headers, report.to_csv()).into_response()
+        }
+        None => Html(alert_redirect_template(
+            "该题目还没有完成的相似度分析",
+            &format!("/admin/similarity?profile={}", urlencoding::encode(&profile)),
+        ))
+        .into_response(),
+    }
+}
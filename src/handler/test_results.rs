@@ -1,9 +1,11 @@
-use crate::database::TestRepo;
-use crate::models::{AppState, User, UserRole}; // 移除 TestStatus, TestResult
+use crate::database::{TestCaseRepo, TestRepo};
+use crate::models::{AppState, ResultsQuery, User, UserRole}; // 移除 TestStatus, TestResult
 // Import new template functions and alert_redirect_template
-use crate::templates::{alert_redirect_template, test_results_list_template, build_test_results_content_html, test_results_detail_template};
+use crate::templates::{alert_redirect_template, test_results_list_template, build_test_results_content_html, build_results_search_form, test_results_detail_template};
+use super::download;
 use axum::{
-    extract::{Extension, State, Path},
+    extract::{Extension, State, Path, Query},
+    http::HeaderMap,
     response::{Html, IntoResponse},
 };
 
@@ -11,9 +13,16 @@ use axum::{
 pub async fn view_results(
     Extension(user): Extension<User>,
     State(state): State<AppState>,
+    Query(query): Query<ResultsQuery>,
 ) -> impl IntoResponse {
+    let is_admin = matches!(user.role, UserRole::Admin);
+    let keyword = query.env.unwrap_or_default();
+
     // 根据用户角色获取测试结果
-    let results = if matches!(user.role, UserRole::Admin) {
+    let results = if is_admin && !keyword.is_empty() {
+        // 管理员可以按评测环境指纹搜索
+        TestRepo::search_tests_by_fingerprint(&state.db_pool, &keyword).await
+    } else if is_admin {
         // 管理员可以查看所有测试结果
         TestRepo::get_all_tests(&state.db_pool).await
     } else {
@@ -26,7 +35,8 @@ pub async fn view_results(
             // 构建测试结果列表内容的 HTML - 使用模板函数
             let results_content_html = build_test_results_content_html(&results);
             // 返回测试结果列表页面 - 使用模板函数
-            Html(test_results_list_template(&results_content_html)).into_response()
+            let search_html = if is_admin { build_results_search_form(&keyword) } else { String::new() };
+            Html(test_results_list_template(&results_content_html, &search_html)).into_response()
         }
         Err(e) => {
             tracing::error!("Failed to get test results: {}", e);
@@ -50,8 +60,17 @@ pub async fn view_result_detail(
                 return Html(alert_redirect_template("您没有权限查看此测试结果", "/test_results")).into_response();
             }
 
+            // 获取各用例的结果，失败时仍然展示基本信息
+            let cases = TestCaseRepo::get_cases(&state.db_pool, id).await.unwrap_or_else(|e| {
+                tracing::error!("Failed to get test cases: {}", e);
+                Vec::new()
+            });
+
+            // 本次评测保存的产物
+            let artifacts = state.artifacts.list(id).await;
+
             // 构建测试结果详情页面 - 使用模板函数
-            Html(test_results_detail_template(&result)).into_response()
+            Html(test_results_detail_template(&result, &cases, &artifacts, state.artifacts.retention_days())).into_response()
         }
         Ok(None) => {
             // 使用模板
@@ -63,4 +82,67 @@ pub async fn view_result_detail(
             Html(alert_redirect_template("获取测试结果失败", "/test_results")).into_response()
         }
     }
-}
\ No newline at end of file
+}
+
+// 检查当前用户能否下载某次评测的产物，失败时返回提示页面
+async fn check_artifact_access(user: &User, state: &AppState, id: i32) -> Result<(), axum::response::Response> {
+    // 检查权限：只能下载自己的产物或者管理员可以下载所有产物
+    match TestRepo::get_test_by_id(&state.db_pool, id).await {
+        Ok(Some(result)) => {
+            if result.user_id != user.id && !matches!(user.role, UserRole::Admin) {
+                return Err(Html(alert_redirect_template("您没有权限下载此文件", "/test_results")).into_response());
+            }
+            Ok(())
+        }
+        Ok(None) => Err(Html(alert_redirect_template("测试结果不存在", "/test_results")).into_response()),
+        Err(e) => {
+            tracing::error!("Failed to get test result: {}", e);
+            Err(Html(alert_redirect_template("获取测试结果失败", "/test_results")).into_response())
+        }
+    }
+}
+
+// 下载评测产物
+pub async fn download_artifact(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path((id, name)): Path<(i32, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(response) = check_artifact_access(&user, &state, id).await {
+        return response;
+    }
+
+    // 只允许下载已知的产物文件
+    let file_path = match state.artifacts.path_of(id, &name) {
+        Some(path) => path,
+        None => return Html(alert_redirect_template("文件不存在", &format!("/test_results/{}", id))).into_response(),
+    };
+
+    match download::file_response(file_path.clone(), &format!("{}-{}", id, name), &headers).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Failed to read artifact {}: {}", file_path.display(), e);
+            Html(alert_redirect_template("产物不存在或已过期", &format!("/test_results/{}", id))).into_response()
+        }
+    }
+}
+
+// 把某次评测的所有产物打包成 zip 下载
+pub async fn download_artifacts_zip(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    if let Err(response) = check_artifact_access(&user, &state, id).await {
+        return response;
+    }
+
+    match download::zip_response(state.artifacts.run_dir(id), &format!("test-{}-artifacts.zip", id)).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Failed to archive artifacts of test {}: {}", id, e);
+            Html(alert_redirect_template("产物不存在或已过期", &format!("/test_results/{}", id))).into_response()
+        }
+    }
+}
@@ -1,16 +1,50 @@
-use crate::database::{UploadRepo, TestRepo};
-use crate::models::{AppState, User, UserRole, UploadRecord, TestTask};
-use crate::templates::{index_template, uploads_template, alert_redirect_template}; // Import alert_redirect_template
+use crate::blobstore::remove_local_upload;
+use crate::database::{BlobRepo, UploadRepo, TestRepo};
+use crate::models::{AppState, RenameUploadForm, UploadRecord, User, UserRole, TestTask};
+use crate::quota::StorageUsage;
+use super::files::get_dir_size;
+use crate::templates::{index_template, uploads_template, alert_redirect_template, layout_report_template}; // Import alert_redirect_template
 use axum::{
-    extract::{Extension, Multipart, State},
+    extract::{Extension, Form, Multipart, Path as UrlPath, State},
     response::{Html, IntoResponse},
 };
-use sqlx::Row;
 use std::{io::Write, path::{Path, PathBuf}};
 
 // 首页处理函数
-pub async fn index_handler(Extension(user): Extension<User>) -> impl IntoResponse {
-    Html(index_template(&user.username, &user.role))
+pub async fn index_handler(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let usage = storage_usage(&state, &user).await;
+    Html(index_template(&user.username, &user.role, &state.test_queue.profile_names(), &usage))
+}
+
+// 用户的存储使用情况，按上传文件表统计，与文件在哪个节点上无关
+async fn storage_usage(state: &AppState, user: &User) -> StorageUsage {
+    let used = BlobRepo::get_user_usage(&state.db_pool, user.id).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to measure storage of {}: {}", user.username, e);
+        0
+    });
+    StorageUsage {
+        used,
+        limit: state.quota.limit_for(user),
+    }
+}
+
+// 撤销一次上传：删除上传目录和上传记录
+async fn discard_upload(state: &AppState, upload_id: i32, upload_dir: &Path) {
+    let _ = std::fs::remove_dir_all(upload_dir);
+    if let Err(e) = UploadRepo::delete_upload(&state.db_pool, upload_id).await {
+        tracing::error!("Failed to delete upload record {}: {}", upload_id, e);
+    }
+}
+
+// 把上传目录存入内容寻址存储。失败时文件仍然保留在上传目录中，下次启动时会重新导入
+async fn store_upload(state: &AppState, username: &str, upload_id: i32, upload_dir: &Path) {
+    let user_dir = PathBuf::from(format!("uploads/{}", username));
+    if let Err(e) = state.blobs.ingest(&state.db_pool, upload_id, &user_dir, vec![upload_dir.to_path_buf()]).await {
+        tracing::warn!("Failed to store upload {} in blob store: {}", upload_id, e);
+    }
 }
 
 // 文件上传处理函数
@@ -28,53 +62,92 @@ pub async fn upload_handler(
         // 使用模板
         return Html(alert_redirect_template("创建用户目录失败！", "/")).into_response();
     }
+    let usage = storage_usage(&state, &user).await;
 
     let mut uploaded = false;
     let mut upload_info = None;
+    let mut profile = crate::tester::DEFAULT_PROFILE.to_string();
+    let mut force_rerun = false;
 
     while let Ok(Some(field)) = multipart.next_field().await {
+        // 选择的题目评测配置
+        if field.name() == Some("profile") {
+            if let Ok(name) = field.text().await {
+                if state.test_queue.has_profile(&name) {
+                    profile = name;
+                }
+            }
+            continue;
+        }
+
+        // 管理员可以跳过结果缓存，强制重新评测
+        if field.name() == Some("force_rerun") {
+            force_rerun = matches!(user.role, UserRole::Admin);
+            continue;
+        }
+
         if let Some(file_name) = field.file_name().map(|s| s.to_string()) {
-            if let Ok(data) = field.bytes().await {
-                let upload_path = PathBuf::from(&user_folder).join(&file_name);
-                let file_path_str = upload_path.to_string_lossy().to_string();
-
-                if let Ok(mut file) = std::fs::File::create(&upload_path) {
-                    if file.write_all(&data).is_ok() {
-                        // 记录上传到数据库
-                        match UploadRepo::record_upload(
-                            &state.db_pool,
-                            user.id,
-                            &file_name,
-                            &file_path_str,
-                            data.len() as i64,
-                        ).await {
-                            Ok(_) => {
-                                uploaded = true;
-                                upload_info = Some((file_name.clone(), data.len() as i64, upload_path));
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to record upload: {}", e);
-                                // 删除已上传的文件
-                                let _ = std::fs::remove_file(upload_path);
-                                // 使用模板
-                                return Html(alert_redirect_template(&format!("记录上传失败: {}", e), "/")).into_response();
-                            }
-                        }
-                    } else {
-                         // 使用模板
-                         return Html(alert_redirect_template("写入文件失败！", "/")).into_response();
-                    }
-                } else {
-                     // 使用模板
-                     return Html(alert_redirect_template("创建文件失败！", "/")).into_response();
+            // 只保留文件名部分，防止写到上传目录之外
+            let file_name = match Path::new(&file_name).file_name().and_then(|s| s.to_str()) {
+                Some(name) => name.to_string(),
+                None => return Html(alert_redirect_template("文件名无效！", "/")).into_response(),
+            };
+            let data = match field.bytes().await {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::warn!("Failed to read upload from {}: {}", user.username, e);
+                    return Html(alert_redirect_template("读取上传文件失败，文件可能超过了大小限制", "/")).into_response();
                 }
+            };
+            if usage.would_exceed(data.len() as u64) {
+                return Html(alert_redirect_template(&usage.exceeded_message(data.len() as u64), "/")).into_response();
             }
+
+            // 先记录上传得到上传ID，每次上传使用以ID命名的独立目录，不会覆盖排队或评测中的旧提交
+            let upload_id = match UploadRepo::record_upload(
+                &state.db_pool,
+                user.id,
+                &file_name,
+                "",
+                data.len() as i64,
+            ).await {
+                Ok(id) => id,
+                Err(e) => {
+                    tracing::error!("Failed to record upload: {}", e);
+                    // 使用模板
+                    return Html(alert_redirect_template(&format!("记录上传失败: {}", e), "/")).into_response();
+                }
+            };
+            let upload_dir = PathBuf::from(&user_folder).join(upload_id.to_string());
+            let upload_path = upload_dir.join(&file_name);
+
+            let written = std::fs::create_dir_all(&upload_dir)
+                .and_then(|_| std::fs::File::create(&upload_path))
+                .and_then(|mut file| file.write_all(&data));
+            let recorded = match written {
+                Ok(_) => UploadRepo::update_file_path(&state.db_pool, upload_id, &upload_path.to_string_lossy())
+                    .await
+                    .map_err(|e| format!("记录上传失败: {}", e)),
+                Err(e) => {
+                    tracing::error!("Failed to write upload {}: {}", upload_id, e);
+                    Err("写入文件失败！".to_string())
+                }
+            };
+            if let Err(message) = recorded {
+                // 撤销本次上传
+                discard_upload(&state, upload_id, &upload_dir).await;
+                // 使用模板
+                return Html(alert_redirect_template(&message, "/")).into_response();
+            }
+
+            uploaded = true;
+            upload_info = Some((upload_id, file_name.clone(), data.len() as i64, upload_dir, upload_path));
         }
     }
 
     // 如果文件上传成功且是zip文件，尝试解压并替换user目录
     if uploaded {
-        if let Some((filename, size, upload_path)) = upload_info {
+        if let Some((upload_id, filename, size, upload_dir, upload_path)) = upload_info {
             // 检查是否为zip文件
             if filename.ends_with(".zip") {
                 // 计算不带扩展名的文件名
@@ -86,47 +159,89 @@ pub async fn upload_handler(
                 // 创建解压目录名称：filename_out
                 let extract_dir_name = format!("{}_out", file_stem);
 
-                match extract_zip_and_replace_user_dir(&upload_path, &user.username, &extract_dir_name) {
-                    Ok(_) => {
-                        // 创建测试记录并添加到队列
-                        match TestRepo::create_test(&state.db_pool, user.id).await {
-                            Ok(test_id) => {
-                                // 添加到测试队列
-                                let task = TestTask {
-                                    id: test_id,
-                                    user_id: user.id,
-                                    username: user.username.clone(),
-                                    work_dir: format!("uploads/{}/{}", user.username, extract_dir_name),
-                                };
-
-                                state.test_queue.add_task(task).await;
-
-                                // 使用模板
-                                return Html(alert_redirect_template(
-                                    "文件上传成功！已加入测试队列，请稍后查看测试结果。",
-                                    "/test_results"
-                                )).into_response();
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to create test record: {}", e);
-                                // 使用模板
-                                return Html(alert_redirect_template(
-                                    &format!("文件上传成功，但创建测试记录失败: {}", e),
-                                    "/"
-                                )).into_response();
+                let extract_dir = upload_dir.join(extract_dir_name);
+                if let Err(e) = extract_zip(&upload_path, &extract_dir) {
+                    tracing::error!("解压缩失败: {}", e);
+                    // 使用模板
+                    return Html(alert_redirect_template(
+                        &format!("文件上传成功，但解压失败: {}", e),
+                        "/"
+                    )).into_response();
+                }
+
+                // 解压后的文件同样计入配额
+                let total = get_dir_size(&upload_dir).await.unwrap_or(0);
+                if usage.would_exceed(total) {
+                    discard_upload(&state, upload_id, &upload_dir).await;
+                    return Html(alert_redirect_template(&usage.exceeded_message(total), "/")).into_response();
+                }
+                store_upload(&state, &user.username, upload_id, &upload_dir).await;
+
+                // 加入队列之前检查目录结构，有问题时直接把报告展示给学生
+                let report = state.test_queue.validate_submission(&extract_dir, &profile);
+                let project_root = match report.root.clone().filter(|_| report.is_ok()) {
+                    Some(root) => root,
+                    None => {
+                        tracing::info!("Submission {} of user {} rejected by layout check", filename, user.username);
+                        return Html(layout_report_template(&filename, &profile, &report)).into_response();
+                    }
+                };
+
+                if let Err(e) = UploadRepo::update_project_dir(&state.db_pool, upload_id, &project_root.to_string_lossy()).await {
+                    tracing::error!("Failed to record project dir of upload {}: {}", upload_id, e);
+                }
+
+                // 创建测试记录并添加到队列
+                match TestRepo::create_test(&state.db_pool, user.id, &profile, Some(upload_id)).await {
+                    Ok(test_id) => {
+                        let work_dir = project_root.to_string_lossy().to_string();
+
+                        // 相同的提交内容、测试包、评测配置和评测环境直接复用之前没有出错的结果
+                        if let Some(source_id) = find_reusable_result(&state, test_id, &work_dir, &profile, force_rerun).await {
+                            match TestRepo::reuse_result(&state.db_pool, test_id, source_id).await {
+                                Ok(_) => {
+                                    tracing::info!("Test {} reuses the result of test {}", test_id, source_id);
+                                    if let Err(e) = state.artifacts.copy_run(source_id, test_id).await {
+                                        tracing::warn!("Failed to copy artifacts of test {} to test {}: {}", source_id, test_id, e);
+                                    }
+                                    return Html(alert_redirect_template(
+                                        "文件上传成功！与之前的提交内容相同，已直接复用评测结果。",
+                                        &format!("/test_results/{}", test_id)
+                                    )).into_response();
+                                }
+                                Err(e) => tracing::error!("Failed to reuse test result: {}", e),
                             }
                         }
+
+                        // 添加到测试队列
+                        let task = TestTask {
+                            id: test_id,
+                            user_id: user.id,
+                            username: user.username.clone(),
+                            work_dir,
+                            profile: profile.clone(),
+                            upload_id: Some(upload_id),
+                        };
+
+                        state.test_queue.add_task(task).await;
+
+                        // 使用模板
+                        return Html(alert_redirect_template(
+                            "文件上传成功！已加入测试队列，请稍后查看测试结果。",
+                            "/test_results"
+                        )).into_response();
                     }
                     Err(e) => {
-                        tracing::error!("解压缩失败: {}", e);
+                        tracing::error!("Failed to create test record: {}", e);
                         // 使用模板
                         return Html(alert_redirect_template(
-                            &format!("文件上传成功，但解压失败: {}", e),
+                            &format!("文件上传成功，但创建测试记录失败: {}", e),
                             "/"
                         )).into_response();
                     }
                 }
             } else {
+                store_upload(&state, &user.username, upload_id, &upload_dir).await;
                 // 使用模板
                 return Html(alert_redirect_template(
                     &format!("文件 {} ({} 字节) 上传成功！", filename, size),
@@ -157,20 +272,7 @@ pub async fn view_uploads(
     };
 
     match uploads_result {
-        Ok(rows) => {
-            let uploads: Vec<UploadRecord> = rows.into_iter().map(|row| {
-                UploadRecord {
-                    id: row.get("id"),
-                    filename: row.get("filename"),
-                    file_path: row.get("file_path"),
-                    file_size: row.get("file_size"),
-                    uploaded_at: row.get("uploaded_at"),
-                    username: row.try_get("username").ok(),
-                }
-            }).collect();
-
-            Html(uploads_template(&user, &uploads)).into_response()
-        }
+        Ok(uploads) => Html(uploads_template(&user, &uploads)).into_response(),
         Err(e) => {
             tracing::error!("Failed to get uploads: {}", e);
             // 使用模板
@@ -179,16 +281,166 @@ pub async fn view_uploads(
     }
 }
 
-// 解压ZIP文件并替换user目录的函数
-fn extract_zip_and_replace_user_dir(zip_path: &PathBuf, username: &str, extract_dir_name: &str) -> Result<(), String> {
-    // 创建解压时目录，改为使用传入的目录名
-    let extract_dir = format!("uploads/{}/{}", username, extract_dir_name);
+// 获取当前用户可以修改的上传记录（自己的上传，管理员可以修改所有人的）
+async fn owned_upload(state: &AppState, user: &User, upload_id: i32) -> Result<UploadRecord, &'static str> {
+    match UploadRepo::get_upload_by_id(&state.db_pool, upload_id).await {
+        Ok(Some(upload)) => {
+            if upload.username.as_deref() != Some(user.username.as_str()) && !matches!(user.role, UserRole::Admin) {
+                return Err("您没有权限修改此上传");
+            }
+            Ok(upload)
+        }
+        Ok(None) => Err("上传记录不存在"),
+        Err(e) => {
+            tracing::error!("Failed to get upload {}: {}", upload_id, e);
+            Err("数据库错误")
+        }
+    }
+}
+
+// 删除上传：删除上传目录（包括解压出的工程）和上传记录
+pub async fn delete_upload(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    UrlPath(upload_id): UrlPath<i32>,
+) -> impl IntoResponse {
+    let upload = match owned_upload(&state, &user, upload_id).await {
+        Ok(upload) => upload,
+        Err(message) => return Html(alert_redirect_template(message, "/uploads")).into_response(),
+    };
+
+    // 排队或评测中的提交还会被评测工作器使用
+    if matches!(upload.test_status.as_deref(), Some("Pending") | Some("Running")) {
+        return Html(alert_redirect_template("该上传正在评测，请等评测结束后再删除", "/uploads")).into_response();
+    }
+
+    if let Err(e) = remove_local_upload(&upload).await {
+        tracing::error!("Failed to remove files of upload {}: {}", upload.id, e);
+        return Html(alert_redirect_template("删除文件失败", "/uploads")).into_response();
+    }
+
+    match UploadRepo::remove_upload(&state.db_pool, upload.id).await {
+        Ok(_) => {
+            tracing::info!("Upload {} deleted by {}", upload.id, user.username);
+            Html(alert_redirect_template(&format!("已删除上传 #{}", upload.id), "/uploads")).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to delete upload record {}: {}", upload.id, e);
+            Html(alert_redirect_template("文件已删除，但删除上传记录失败，请重试", "/uploads")).into_response()
+        }
+    }
+}
+
+// 重命名上传的文件，解压出的工程目录不受影响
+pub async fn rename_upload(
+    Extension(user): Extension<User>,
+    State(state): State<AppState>,
+    UrlPath(upload_id): UrlPath<i32>,
+    Form(form): Form<RenameUploadForm>,
+) -> impl IntoResponse {
+    let upload = match owned_upload(&state, &user, upload_id).await {
+        Ok(upload) => upload,
+        Err(message) => return Html(alert_redirect_template(message, "/uploads")).into_response(),
+    };
+
+    // 新文件名只能是单个文件名，不能包含路径
+    let new_name = form.filename.trim();
+    let valid = !new_name.is_empty()
+        && new_name.len() <= 255
+        && new_name != "."
+        && new_name != ".."
+        && !new_name.contains(['/', '\\', '\0']);
+    if !valid {
+        return Html(alert_redirect_template("文件名无效！", "/uploads")).into_response();
+    }
+    if new_name == upload.filename {
+        return Html(alert_redirect_template("文件名没有变化", "/uploads")).into_response();
+    }
+
+    let old_path = PathBuf::from(&upload.file_path);
+    let new_path = old_path.with_file_name(new_name);
+    if new_path.exists() {
+        return Html(alert_redirect_template("已存在同名文件", "/uploads")).into_response();
+    }
+    if let Err(e) = tokio::fs::rename(&old_path, &new_path).await {
+        tracing::error!("Failed to rename upload {}: {}", upload.id, e);
+        return Html(alert_redirect_template("重命名文件失败", "/uploads")).into_response();
+    }
+
+    // 上传文件表中的路径相对用户目录
+    let user_dir = PathBuf::from(format!("uploads/{}", upload.username.as_deref().unwrap_or_default()));
+    let entry = |path: &Path| {
+        path.strip_prefix(&user_dir)
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default()
+    };
+    match UploadRepo::rename_upload(
+        &state.db_pool,
+        upload.id,
+        new_name,
+        &new_path.to_string_lossy(),
+        &entry(&old_path),
+        &entry(&new_path),
+    ).await {
+        Ok(_) => Html(alert_redirect_template("重命名成功！", "/uploads")).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to record rename of upload {}: {}", upload.id, e);
+            // 记录没有更新时把文件改回原名，保持与数据库一致
+            if let Err(e) = tokio::fs::rename(&new_path, &old_path).await {
+                tracing::error!("Failed to restore name of upload {}: {}", upload.id, e);
+            }
+            Html(alert_redirect_template("重命名失败", "/uploads")).into_response()
+        }
+    }
+}
+
+// 计算并记录提交的哈希，返回可以复用结果的评测ID
+async fn find_reusable_result(
+    state: &AppState,
+    test_id: i32,
+    work_dir: &str,
+    profile: &str,
+    force_rerun: bool,
+) -> Option<i32> {
+    let dir = PathBuf::from(work_dir);
+    let hashes = tokio::task::spawn_blocking(move || {
+        Ok::<_, std::io::Error>((crate::tester::submission_hash(&dir)?, crate::tester::bundle_hash()?))
+    })
+    .await;
+    let (submission_hash, bundle_hash) = match hashes {
+        Ok(Ok(hashes)) => hashes,
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to hash submission {}: {}", work_dir, e);
+            return None;
+        }
+        Err(e) => {
+            tracing::warn!("Hashing task for {} panicked: {}", work_dir, e);
+            return None;
+        }
+    };
+
+    if let Err(e) = TestRepo::save_hashes(&state.db_pool, test_id, &submission_hash, &bundle_hash).await {
+        tracing::error!("Failed to save submission hash: {}", e);
+        return None;
+    }
+    if force_rerun {
+        return None;
+    }
+    // 评测工作器启动前还不知道当前的评测环境，不复用结果
+    let env_fingerprint = state.test_queue.env_fingerprint()?;
 
-    // 确保解压目录存在并为空
-    if std::path::Path::new(&extract_dir).exists() {
-        std::fs::remove_dir_all(&extract_dir).map_err(|e| format!("无法清理解压目录: {}", e))?;
+    match TestRepo::find_cached_result(&state.db_pool, test_id, &submission_hash, &bundle_hash, profile, env_fingerprint).await {
+        Ok(source_id) => source_id,
+        Err(e) => {
+            tracing::error!("Failed to look up cached result: {}", e);
+            None
+        }
     }
-    std::fs::create_dir_all(&extract_dir).map_err(|e| format!("无法创建解压目录: {}", e))?;
+}
+
+// 解压ZIP文件到指定目录（每次上传的目录都是新建的）
+fn extract_zip(zip_path: &Path, extract_dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(extract_dir).map_err(|e| format!("无法创建解压目录: {}", e))?;
 
     // 打开zip文件
     let file = std::fs::File::open(zip_path).map_err(|e| format!("无法打开ZIP文件: {}", e))?;
@@ -197,7 +449,14 @@ fn extract_zip_and_replace_user_dir(zip_path: &PathBuf, username: &str, extract_
     // 解压文件
     for i in 0..archive.len() {
         let mut file = archive.by_index(i).map_err(|e| format!("解压错误: {}", e))?;
-        let outpath = std::path::Path::new(&extract_dir).join(file.name());
+        // 跳过绝对路径或包含 .. 的条目，防止写到解压目录之外
+        let outpath = match file.enclosed_name() {
+            Some(name) => extract_dir.join(name),
+            None => {
+                tracing::warn!("跳过不安全的ZIP条目: {}", file.name());
+                continue;
+            }
+        };
 
         if file.name().ends_with('/') {
             std::fs::create_dir_all(&outpath).map_err(|e| format!("无法创建目录 {}: {}", outpath.display(), e))?;
@@ -214,86 +473,37 @@ fn extract_zip_and_replace_user_dir(zip_path: &PathBuf, username: &str, extract_
         }
     }
 
-    tracing::info!("解压完成: {}", extract_dir);
-
-    // 查找并替换user目录
-    let user_dir_in_zip = find_user_dir(&extract_dir)?;
-
-    // 确保项目当前目录中的user目录存在
-    let project_user_dir = "user";
-    if !std::path::Path::new(project_user_dir).exists() {
-        std::fs::create_dir_all(project_user_dir).map_err(|e| format!("无法创建项目user目录: {}", e))?;
-    }
-
-    // 替换解压后的user目录
-    if user_dir_in_zip.exists() && std::path::Path::new(project_user_dir).exists() {
-        // 将项目中的user目录复制到解压后的目录中
-        copy_dir_recursively(project_user_dir, user_dir_in_zip.parent().unwrap())
-            .map_err(|e| format!("无法替换user目录: {}", e))?;
-        tracing::info!("已成功替换user目录");
-    } else {
-        tracing::warn!("在解压的文件中未找到user目录或项目user目录不存在");
-    }
-
+    tracing::info!("解压完成: {}", extract_dir.display());
     Ok(())
 }
 
-// 查找解压目录中的user目录
-fn find_user_dir(extract_dir: &str) -> Result<std::path::PathBuf, String> {
-    // 首先尝试在根目录查找
-    let direct_user_dir = std::path::Path::new(extract_dir).join("user");
-    if direct_user_dir.exists() && direct_user_dir.is_dir() {
-        return Ok(direct_user_dir);
-    }
-
-    // 尝试查找第一级子目录中的user目录
-    let entries = std::fs::read_dir(extract_dir)
-        .map_err(|e| format!("无法读取解压目录: {}", e))?;
-
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_dir() {
-                let potential_user_dir = path.join("user");
-                if potential_user_dir.exists() && potential_user_dir.is_dir() {
-                    return Ok(potential_user_dir);
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_zip_skips_entries_outside_the_target() {
+        let base = std::env::temp_dir().join(format!("extract_zip_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(&base).unwrap();
+        let zip_path = base.join("upload.zip");
+
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        let options = zip::write::FileOptions::default();
+        for name in ["os/src/main.rs", "../escaped.txt", "/abs.txt"] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(b"fn main() {}").unwrap();
         }
-    }
+        writer.add_directory("user/", options).unwrap();
+        writer.finish().unwrap();
 
-    // 如果未找到，返回根目录下的user路径（即使它不存在）
-    Ok(direct_user_dir)
-}
-
-// 递归复制目录
-fn copy_dir_recursively(src: &str, dst: &std::path::Path) -> std::io::Result<()> {
-    let src_path = std::path::Path::new(src);
-    let dst_path = dst.join(src_path.file_name().unwrap());
+        let extract_dir = base.join("out");
+        extract_zip(&zip_path, &extract_dir).unwrap();
 
-    // 如果目标已存在，先删除
-    if dst_path.exists() {
-        std::fs::remove_dir_all(&dst_path)?;
+        assert!(extract_dir.join("os/src/main.rs").is_file());
+        assert!(extract_dir.join("user").is_dir());
+        assert!(!base.join("escaped.txt").exists());
+        assert!(!Path::new("/abs.txt").exists());
+        std::fs::remove_dir_all(&base).unwrap();
     }
-
-    // 创建目标目录
-    std::fs::create_dir_all(&dst_path)?;
-
-    // 遍历源目录中的所有条目
-    for entry in std::fs::read_dir(src)? {
-        let entry = entry?;
-        let entry_path = entry.path();
-        let file_name = entry.file_name();
-        let dst_file = dst_path.join(file_name);
-
-        if entry_path.is_dir() {
-            // 递归复制子目录
-            copy_dir_recursively(entry_path.to_str().unwrap(), &dst_path)?;
-        } else {
-            // 复制文件
-            std::fs::copy(&entry_path, &dst_file)?;
-        }
-    }
-
-    Ok(())
-}
\ No newline at end of file
+}
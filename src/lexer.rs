@@ -0,0 +1,315 @@
+// 简单的 Rust 词法分析器，用于源码高亮和代码相似度比较。
+// 不追求完整的语法，只需要正确切分注释、字符串和标识符
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Whitespace,
+    Comment,
+    Keyword,
+    Ident,
+    Lifetime,
+    Number,
+    Str,
+    Char,
+    Punct,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub offset: usize, // 在源码中的字节偏移
+}
+
+const KEYWORDS: [&str; 39] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "union",
+];
+
+pub fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let rest = &src[pos..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        let kind = if c.is_whitespace() {
+            pos += take_while(rest, char::is_whitespace);
+            TokenKind::Whitespace
+        } else if rest.starts_with("//") {
+            pos += rest.find('\n').unwrap_or(rest.len());
+            TokenKind::Comment
+        } else if rest.starts_with("/*") {
+            pos += block_comment_len(rest);
+            TokenKind::Comment
+        } else if let Some(len) = raw_string_len(rest) {
+            pos += len;
+            TokenKind::Str
+        } else if c == '"' || rest.starts_with("b\"") {
+            pos += quoted_len(rest, '"');
+            TokenKind::Str
+        } else if c == '\'' || rest.starts_with("b'") {
+            match char_literal_len(rest) {
+                Some(len) => {
+                    pos += len;
+                    TokenKind::Char
+                }
+                None => {
+                    pos += 1 + take_while(&rest[1..], is_ident_char);
+                    TokenKind::Lifetime
+                }
+            }
+        } else if c.is_ascii_digit() {
+            pos += number_len(rest);
+            TokenKind::Number
+        } else if c.is_alphabetic() || c == '_' {
+            pos += take_while(rest, is_ident_char);
+            if KEYWORDS.contains(&&src[start..pos]) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Ident
+            }
+        } else {
+            pos += c.len_utf8();
+            TokenKind::Punct
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &src[start..pos],
+            offset: start,
+        });
+    }
+
+    tokens
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn take_while(text: &str, pred: impl Fn(char) -> bool) -> usize {
+    text.char_indices().find(|&(_, c)| !pred(c)).map_or(text.len(), |(i, _)| i)
+}
+
+// 块注释可以嵌套，未闭合时一直到文件末尾
+fn block_comment_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    text.len()
+}
+
+// r"..."、r#"..."#、br"..." 形式的原始字符串
+fn raw_string_len(text: &str) -> Option<usize> {
+    let prefix = if text.starts_with("br") { 2 } else if text.starts_with('r') { 1 } else { return None };
+    let hashes = text[prefix..].bytes().take_while(|&b| b == b'#').count();
+    if text.as_bytes().get(prefix + hashes) != Some(&b'"') {
+        return None;
+    }
+    let body_start = prefix + hashes + 1;
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    Some(
+        text[body_start..]
+            .find(&terminator)
+            .map_or(text.len(), |end| body_start + end + terminator.len()),
+    )
+}
+
+// 带转义的字符串，未闭合时一直到文件末尾
+fn quoted_len(text: &str, quote: char) -> usize {
+    let start = text.find(quote).map_or(0, |i| i + 1);
+    let mut chars = text[start..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return start + i + 1;
+        }
+    }
+    text.len()
+}
+
+// 字符字面量，如 'a'、'\n'、b'x'；不是字符字面量时（生命周期）返回 None
+fn char_literal_len(text: &str) -> Option<usize> {
+    let start = text.find('\'')? + 1;
+    let body = &text[start..];
+    let mut chars = body.char_indices();
+    let (_, first) = chars.next()?;
+    let end = if first == '\\' {
+        body.find('\'').filter(|&i| i > 1).or_else(|| body[2..].find('\'').map(|i| i + 2))?
+    } else {
+        let (i, second) = chars.next()?;
+        if second != '\'' {
+            return None;
+        }
+        i
+    };
+    Some(start + end + 1)
+}
+
+// 数字字面量，包括后缀、十六进制和小数
+fn number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let is_fraction = b == b'.' && bytes.get(i + 1).map_or(false, u8::is_ascii_digit);
+        if b.is_ascii_alphanumeric() || b == b'_' || is_fraction {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn highlight_class(kind: TokenKind) -> Option<&'static str> {
+    match kind {
+        TokenKind::Comment => Some("hl-comment"),
+        TokenKind::Keyword => Some("hl-keyword"),
+        TokenKind::Str | TokenKind::Char => Some("hl-string"),
+        TokenKind::Number => Some("hl-number"),
+        TokenKind::Lifetime => Some("hl-lifetime"),
+        _ => None,
+    }
+}
+
+fn push_highlighted(html: &mut String, class: Option<&str>, text: &str) {
+    match class {
+        Some(class) if !text.is_empty() => html.push_str(&format!(
+            r#"<span class="{}">{}</span>"#,
+            class,
+            html_escape::encode_text(text)
+        )),
+        _ => html.push_str(&html_escape::encode_text(text)),
+    }
+}
+
+// 对一行源码做语法高亮，返回转义后的 HTML
+pub fn highlight_line(line: &str) -> String {
+    let mut html = String::with_capacity(line.len() * 2);
+    for token in tokenize(line) {
+        push_highlighted(&mut html, highlight_class(token.kind), token.text);
+    }
+    html
+}
+
+// 对整个文件做语法高亮，按行返回转义后的 HTML。
+// 与逐行高亮不同，跨行的块注释和字符串在每一行都能正确着色
+pub fn highlight_source(src: &str) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for token in tokenize(src) {
+        let class = highlight_class(token.kind);
+        let mut parts = token.text.split('\n').peekable();
+        while let Some(part) = parts.next() {
+            let line = lines.last_mut().expect("至少有一行");
+            push_highlighted(line, class, part.strip_suffix('\r').unwrap_or(part));
+            if parts.peek().is_some() {
+                lines.push(String::new());
+            }
+        }
+    }
+    // 与 str::lines 一致，空文件没有行，末尾的换行不产生空行
+    if src.is_empty() || src.ends_with('\n') {
+        lines.pop();
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(src)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_literals_comments_and_lifetimes() {
+        assert_eq!(
+            kinds(r##"fn f<'a>(s: &'a str) -> char { '\'' } // 注释"##),
+            [
+                (TokenKind::Keyword, "fn"),
+                (TokenKind::Ident, "f"),
+                (TokenKind::Punct, "<"),
+                (TokenKind::Lifetime, "'a"),
+                (TokenKind::Punct, ">"),
+                (TokenKind::Punct, "("),
+                (TokenKind::Ident, "s"),
+                (TokenKind::Punct, ":"),
+                (TokenKind::Punct, "&"),
+                (TokenKind::Lifetime, "'a"),
+                (TokenKind::Ident, "str"),
+                (TokenKind::Punct, ")"),
+                (TokenKind::Punct, "-"),
+                (TokenKind::Punct, ">"),
+                (TokenKind::Ident, "char"),
+                (TokenKind::Punct, "{"),
+                (TokenKind::Char, r"'\''"),
+                (TokenKind::Punct, "}"),
+                (TokenKind::Comment, "// 注释"),
+            ]
+        );
+        assert_eq!(
+            kinds(r###"r#"a "quoted" b"# b"x\"y" b'\n' '\u{1F600}' 0x8020_0000u64 1.5 /* a /* b */ c */ return"###),
+            [
+                (TokenKind::Str, r###"r#"a "quoted" b"#"###),
+                (TokenKind::Str, r#"b"x\"y""#),
+                (TokenKind::Char, r"b'\n'"),
+                (TokenKind::Char, r"'\u{1F600}'"),
+                (TokenKind::Number, "0x8020_0000u64"),
+                (TokenKind::Number, "1.5"),
+                (TokenKind::Comment, "/* a /* b */ c */"),
+                (TokenKind::Keyword, "return"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_literals_run_to_end_of_input() {
+        assert_eq!(kinds("\"abc"), [(TokenKind::Str, "\"abc")]);
+        assert_eq!(kinds("/* abc"), [(TokenKind::Comment, "/* abc")]);
+        // 分词结果首尾相接，覆盖整个输入
+        let src = "let s = \"未闭合\n/* x";
+        let tokens = tokenize(src);
+        assert_eq!(tokens.iter().map(|token| token.text).collect::<String>(), src);
+        assert!(tokens.windows(2).all(|pair| pair[0].offset + pair[0].text.len() == pair[1].offset));
+    }
+
+    #[test]
+    fn highlights_multiline_comments_on_every_line() {
+        let lines = highlight_source("/* a\r\nb */ let x = \"<&>\";\n");
+        assert_eq!(
+            lines,
+            [
+                r#"<span class="hl-comment">/* a</span>"#,
+                r#"<span class="hl-comment">b */</span> <span class="hl-keyword">let</span> x = <span class="hl-string">"&lt;&amp;&gt;"</span>;"#,
+            ]
+        );
+        assert!(highlight_source("").is_empty());
+        assert_eq!(highlight_line("'a"), r#"<span class="hl-lifetime">'a</span>"#);
+    }
+}
@@ -1,10 +1,23 @@
+mod archive;
+mod artifacts;
 mod auth;
+mod blobstore;
 mod database;
+mod diff;
+mod digest;
 mod handler;
+mod lexer;
 mod models;
+mod quota;
+mod retention;
+mod similarity;
+mod storage;
 mod templates;
 mod tester; // 新模块
 
+use artifacts::ArtifactStore;
+use blobstore::BlobStore;
+use retention::RetentionJobs;
 use auth::auth_middleware;
 use database::init_db;
 use handler::{
@@ -19,6 +32,7 @@ use std::sync::Arc;
 use tower_http::services::ServeDir; // 新增：导入 ServeDir
 
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{get, post}, // Remove get_service import
     Router,
@@ -95,21 +109,78 @@ async fn main() {
         }
     };
 
+    // 初始化评测产物存储
+    let artifacts = Arc::new(ArtifactStore::from_env());
+
+    // 定期按规则清理旧的上传、编译产物和过期的评测产物
+    let retention = Arc::new(RetentionJobs::from_env(artifacts.clone()));
+    tokio::spawn(retention::run_retention_task(retention.clone(), db_pool.clone()));
+
+    // 初始化上传文件的存储后端和内容寻址存储，并定期回收没有引用的文件
+    let storage = match storage::from_env() {
+        Ok(storage) => storage,
+        Err(e) => {
+            tracing::error!("存储配置错误: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let blobs = Arc::new(BlobStore::from_env(storage));
+    tokio::spawn(blobstore::run_gc_task(blobs.clone(), db_pool.clone()));
+
     // 初始化测试队列
-    let test_queue = Arc::new(TestQueue::new(Arc::new(db_pool.clone())));
+    let test_queue = Arc::new(TestQueue::new(Arc::new(db_pool.clone()), artifacts.clone(), blobs.clone()));
     
+    // 启动前检查评测环境，缺少工具时所有提交都会失败
+    if !test_queue.run_preflight().await {
+        tracing::error!("评测环境检查未通过，详情见管理员的评测机状态页面");
+    }
+
     // 启动测试工作器
     let worker_queue = test_queue.clone();
     tokio::spawn(async move {
         worker_queue.start_worker().await;
     });
 
+    // 定期提交金丝雀评测
+    tokio::spawn(test_queue.clone().run_canary_task());
+
     // 初始化应用状态
-    let state = AppState::new(db_pool, test_queue);
+    let state = AppState::new(db_pool, test_queue, artifacts, blobs, retention);
 
     // 创建需要认证的路由
     let protected_routes = Router::new()
         .route("/", get(handler::index_handler)) // 添加首页路由
+        .route(
+            "/upload",
+            post(handler::upload_handler).layer(DefaultBodyLimit::max(state.quota.max_upload_bytes())),
+        )
+        .route("/uploads", get(handler::view_uploads))
+        .route("/test_results", get(handler::view_results))
+        .route("/test_results/:id", get(handler::view_result_detail))
+        .route("/admin/users", get(handler::admin_panel))
+        .route("/admin/users/create", post(handler::create_user))
+        .route("/admin/users/:username/update", post(handler::update_user))
+        .route("/admin/users/:username/delete", post(handler::delete_user))
+        .route("/test_results/:id/artifacts", get(handler::download_artifacts_zip))
+        .route("/test_results/:id/artifacts/:name", get(handler::download_artifact))
+        .route("/uploads/:id/delete", post(handler::delete_upload))
+        .route("/uploads/:id/rename", post(handler::rename_upload))
+        .route("/admin/users/:username/quota", post(handler::update_user_quota))
+        .route("/admin/health", get(handler::judge_health))
+        .route("/admin/health/recheck", post(handler::recheck_judge))
+        .route("/admin/health/canary", post(handler::submit_canary))
+        .route("/admin/crates", get(handler::vendor_crates))
+        .route("/files/:username", get(handler::view_user_files))
+        .route("/files/:username/archive", get(handler::download_user_archive))
+        .route("/files/:username/browse/*path", get(handler::browse_user_files))
+        .route("/files/:username/raw/*path", get(handler::download_file))
+        .route("/files/:username/diff/:upload_id", get(handler::diff_submission))
+        .route("/admin/similarity", get(handler::similarity_page).post(handler::start_similarity))
+        .route("/admin/similarity/pair", get(handler::similarity_pair))
+        .route("/admin/similarity/export", get(handler::export_similarity))
+        .route("/admin/retention", get(handler::retention_page))
+        .route("/admin/retention/preview", post(handler::preview_retention))
+        .route("/admin/retention/run", post(handler::run_retention))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware
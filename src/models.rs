@@ -2,6 +2,11 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use sqlx::mysql::MySqlPool;
 
+use crate::artifacts::ArtifactStore;
+use crate::blobstore::BlobStore;
+use crate::quota::QuotaConfig;
+use crate::retention::RetentionJobs;
+use crate::similarity::SimilarityJobs;
 use crate::tester::TestQueue;
 
 // 用户角色枚举
@@ -18,6 +23,7 @@ pub struct User {
     pub username: String,
     pub password: String, // 实际应用中应该存储密码哈希
     pub role: UserRole,
+    pub quota_mb: Option<i64>, // 管理员设置的存储配额，为空时使用角色的默认配额
 }
 
 // 会话结构
@@ -31,6 +37,11 @@ pub struct Session {
 pub struct AppState {
     pub db_pool: Arc<MySqlPool>,
     pub test_queue: Arc<TestQueue>, // 新增
+    pub artifacts: Arc<ArtifactStore>,
+    pub blobs: Arc<BlobStore>, // 上传文件的内容寻址存储
+    pub similarity: Arc<SimilarityJobs>, // 各题目的代码相似度分析
+    pub quota: Arc<QuotaConfig>,
+    pub retention: Arc<RetentionJobs>, // 存储清理
 }
 
 // 登录表单
@@ -54,6 +65,52 @@ pub struct UserUpdateForm {
     pub role: Option<String>,
 }
 
+// 设置用户存储配额的表单，留空表示使用默认配额
+#[derive(Deserialize)]
+pub struct QuotaForm {
+    pub quota_mb: String,
+}
+
+// 重命名上传文件的表单
+#[derive(Deserialize)]
+pub struct RenameUploadForm {
+    pub filename: String,
+}
+
+// 提交对比页面的查询参数
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub against: Option<String>, // baseline 或另一次上传的ID
+    pub file: Option<String>,    // 查看单个文件的差异
+    pub mode: Option<String>,    // unified 或 split
+}
+
+// 相似度分析页面的查询参数
+#[derive(Deserialize)]
+pub struct SimilarityQuery {
+    pub profile: Option<String>,
+}
+
+// 开始相似度分析的表单
+#[derive(Deserialize)]
+pub struct SimilarityForm {
+    pub profile: String,
+}
+
+// 查看两份提交相同片段的查询参数
+#[derive(Deserialize)]
+pub struct SimilarityPairQuery {
+    pub profile: String,
+    pub a: i32, // 上传ID
+    pub b: i32,
+}
+
+// 测试结果列表的查询参数
+#[derive(Deserialize)]
+pub struct ResultsQuery {
+    pub env: Option<String>, // 按评测环境指纹搜索（管理员使用）
+}
+
 // 文件上传记录
 #[derive(Debug, Clone, Serialize)]
 pub struct UploadRecord {
@@ -63,6 +120,21 @@ pub struct UploadRecord {
     pub file_size: i64,
     pub uploaded_at: chrono::DateTime<chrono::Utc>,
     pub username: Option<String>,
+    pub project_dir: Option<String>, // 通过检查的工程根目录
+    pub test_id: Option<i32>, // 该上传最近一次评测
+    pub test_status: Option<String>, // 最近一次评测的状态
+    pub test_profile: Option<String>, // 最近一次评测的题目
+}
+
+// 已结束评测的概要，用于清理时判断最好成绩
+#[derive(Debug, Clone)]
+pub struct ResultSummary {
+    pub id: i32,
+    pub user_id: i32,
+    pub profile: Option<String>,
+    pub status: String,
+    pub upload_id: Option<i32>,
+    pub passed_cases: u64, // 通过的测试点数量
 }
 
 // 评测状态枚举
@@ -82,12 +154,35 @@ pub struct TestResult {
     pub user_id: i32,
     pub username: String,
     pub status: TestStatus,
+    pub profile: Option<String>,
     pub output: Option<String>,
     pub error: Option<String>,
+    pub hang_report: Option<String>, // 超时时通过QEMU monitor采集的CPU状态
+    pub env_fingerprint: Option<String>, // 评测机环境指纹
+    pub cached_from: Option<i32>, // 复用了哪一次评测的结果
+    pub upload_id: Option<i32>, // 被评测的上传
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+// 单个用例的判定结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CaseVerdict {
+    Passed,            // 通过
+    Failed,            // 退出码不符合预期
+    TimeLimitExceeded, // 超时（TLE）
+    NotRun,            // 未运行
+}
+
+// 单个用例的评测结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCaseResult {
+    pub name: String,
+    pub verdict: CaseVerdict,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<i64>,
+}
+
 // 评测任务
 #[derive(Debug, Clone)]
 pub struct TestTask {
@@ -95,13 +190,26 @@ pub struct TestTask {
     pub user_id: i32,
     pub username: String,
     pub work_dir: String,
+    pub profile: String, // 使用的题目评测配置
+    pub upload_id: Option<i32>, // 评测的上传，评测节点从存储中取出其中的文件
 }
 
 impl AppState {
-    pub fn new(pool: MySqlPool, test_queue: Arc<TestQueue>) -> Self {
+    pub fn new(
+        pool: MySqlPool,
+        test_queue: Arc<TestQueue>,
+        artifacts: Arc<ArtifactStore>,
+        blobs: Arc<BlobStore>,
+        retention: Arc<RetentionJobs>,
+    ) -> Self {
         AppState {
             db_pool: Arc::new(pool),
             test_queue,
+            artifacts,
+            blobs,
+            similarity: Arc::new(SimilarityJobs::new()),
+            quota: Arc::new(QuotaConfig::from_env()),
+            retention,
         }
     }
 }
\ No newline at end of file
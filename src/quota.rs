@@ -0,0 +1,134 @@
+use crate::models::{User, UserRole};
+
+const MB: u64 = 1024 * 1024;
+
+// 各角色默认的存储配额
+pub struct QuotaConfig {
+    regular_mb: u64,
+    admin_mb: u64,
+    max_upload_mb: u64,
+}
+
+impl QuotaConfig {
+    // 从环境变量读取配置：
+    // UPLOAD_QUOTA_REGULAR_MB  普通用户的默认配额（MB），默认 500
+    // UPLOAD_QUOTA_ADMIN_MB    管理员的默认配额（MB），默认 0
+    // UPLOAD_MAX_MB            单次上传请求的大小上限（MB），默认 200
+    // 配额为 0 表示不限制；管理员可以为单个用户设置配额覆盖默认值
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            regular_mb: read("UPLOAD_QUOTA_REGULAR_MB", 500),
+            admin_mb: read("UPLOAD_QUOTA_ADMIN_MB", 0),
+            max_upload_mb: read("UPLOAD_MAX_MB", 200).max(1),
+        }
+    }
+
+    // 上传请求体的大小上限（字节）。上传的文件整个读入内存后再检查配额，因此需要单独的上限
+    pub fn max_upload_bytes(&self) -> usize {
+        (self.max_upload_mb * MB) as usize
+    }
+
+    // 用户的配额（字节），None 表示不限制
+    pub fn limit_for(&self, user: &User) -> Option<u64> {
+        let mb = match user.quota_mb {
+            Some(mb) => mb.max(0) as u64,
+            None => match user.role {
+                UserRole::Admin => self.admin_mb,
+                UserRole::Regular => self.regular_mb,
+            },
+        };
+        if mb == 0 {
+            None
+        } else {
+            Some(mb * MB)
+        }
+    }
+}
+
+// 用户目录的存储使用情况
+pub struct StorageUsage {
+    pub used: u64,
+    pub limit: Option<u64>,
+}
+
+impl StorageUsage {
+    // 再写入 extra 字节后是否超出配额
+    pub fn would_exceed(&self, extra: u64) -> bool {
+        self.limit.map_or(false, |limit| self.used + extra > limit)
+    }
+
+    // 超出配额时的提示
+    pub fn exceeded_message(&self, extra: u64) -> String {
+        format!(
+            "存储空间不足：已使用 {}，本次需要 {}，配额为 {}。请先删除不需要的上传",
+            format_mb(self.used),
+            format_mb(extra),
+            self.limit.map_or("不限".to_string(), format_mb)
+        )
+    }
+
+    pub fn describe(&self) -> String {
+        match self.limit {
+            Some(limit) => format!(
+                "{} / {}（{:.0}%）",
+                format_mb(self.used),
+                format_mb(limit),
+                self.used as f64 * 100.0 / limit as f64
+            ),
+            None => format!("{}（不限）", format_mb(self.used)),
+        }
+    }
+}
+
+fn format_mb(bytes: u64) -> String {
+    format!("{:.2} MB", bytes as f64 / MB as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: UserRole, quota_mb: Option<i64>) -> User {
+        User {
+            id: 1,
+            username: "alice".to_string(),
+            password: String::new(),
+            role,
+            quota_mb,
+        }
+    }
+
+    #[test]
+    fn over_quota_upload_is_rejected() {
+        let config = QuotaConfig {
+            regular_mb: 10,
+            admin_mb: 0,
+            max_upload_mb: 200,
+        };
+        let usage = StorageUsage {
+            used: 9 * MB,
+            limit: config.limit_for(&user(UserRole::Regular, None)),
+        };
+        assert!(!usage.would_exceed(MB));
+        assert!(usage.would_exceed(MB + 1));
+        assert!(usage.exceeded_message(2 * MB).contains("配额为 10.00 MB"));
+    }
+
+    #[test]
+    fn per_user_quota_overrides_role_default() {
+        let config = QuotaConfig {
+            regular_mb: 10,
+            admin_mb: 0,
+            max_upload_mb: 200,
+        };
+        assert_eq!(config.limit_for(&user(UserRole::Admin, None)), None);
+        assert_eq!(config.limit_for(&user(UserRole::Regular, Some(0))), None);
+        assert_eq!(config.limit_for(&user(UserRole::Admin, Some(5))), Some(5 * MB));
+    }
+}
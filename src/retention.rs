@@ -0,0 +1,403 @@
+use crate::artifacts::ArtifactStore;
+use crate::blobstore::remove_local_upload;
+use crate::database::{BlobRepo, DbPool, TestRepo, UploadRepo};
+use crate::models::{ResultSummary, UploadRecord};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+// 存储清理规则
+pub struct RetentionPolicy {
+    pub keep_latest: usize,          // 每个学生每道题保留最近几次上传，0 表示全部保留
+    pub purge_build_outputs: bool,   // 评测结束后删除提交中 os 和 user 的 target 目录
+    pub artifact_days: u64,          // 评测产物保留天数，0 表示永久保留
+    pub interval: Duration,          // 定时清理的间隔
+    pub dry_run: bool,               // 定时清理只生成报告，不删除文件
+}
+
+impl RetentionPolicy {
+    // 从环境变量读取配置：
+    // RETENTION_KEEP_LATEST          每个学生每道题保留最近几次上传，默认 0 表示全部保留
+    // RETENTION_PURGE_BUILD_OUTPUTS  评测结束后删除 target 目录，默认开启，0 表示关闭
+    // RETENTION_INTERVAL_HOURS       定时清理的间隔（小时），默认 1
+    // RETENTION_DRY_RUN              非 0 时定时清理只生成报告，默认 0
+    // 评测产物的保留天数沿用 ARTIFACT_RETENTION_DAYS；每个学生每道题的最好成绩总是保留
+    pub fn from_env(artifacts: &ArtifactStore) -> Self {
+        let hours = std::env::var("RETENTION_INTERVAL_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|hours| *hours > 0)
+            .unwrap_or(1);
+        Self {
+            keep_latest: std::env::var("RETENTION_KEEP_LATEST")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            purge_build_outputs: std::env::var("RETENTION_PURGE_BUILD_OUTPUTS").map_or(true, |v| v != "0"),
+            artifact_days: artifacts.retention_days(),
+            interval: Duration::from_secs(hours * 60 * 60),
+            dry_run: std::env::var("RETENTION_DRY_RUN").map_or(false, |v| v != "0"),
+        }
+    }
+}
+
+// 一次上传中 target 目录下的编译产物
+pub struct BuildOutputs {
+    pub upload_id: i32,
+    pub username: String,
+    pub dirs: Vec<String>, // target 目录，相对 uploads/<用户名>
+    pub files: usize,
+    pub size: u64,
+}
+
+// 一次清理删除的内容；试运行时是将要删除的内容
+pub struct RetentionReport {
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub dry_run: bool,
+    pub scheduled: bool, // 定时任务触发，否则是管理员手动触发
+    pub uploads: Vec<UploadRecord>,
+    pub build_outputs: Vec<BuildOutputs>,
+    pub artifacts: Vec<(i32, u64)>, // (评测ID, 字节数)
+    pub kept_best: usize,           // 已过期但因为是最好成绩而保留的产物目录数
+    pub errors: Vec<String>,
+}
+
+impl RetentionReport {
+    fn new(dry_run: bool, scheduled: bool) -> Self {
+        Self {
+            finished_at: chrono::Utc::now(),
+            dry_run,
+            scheduled,
+            uploads: Vec::new(),
+            build_outputs: Vec::new(),
+            artifacts: Vec::new(),
+            kept_best: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uploads.is_empty() && self.build_outputs.is_empty() && self.artifacts.is_empty()
+    }
+
+    // 释放的空间（字节）。上传按压缩包大小计算，内容相同的文件可能仍被其他上传引用
+    pub fn total_size(&self) -> u64 {
+        self.uploads.iter().map(|upload| upload.file_size.max(0) as u64).sum::<u64>()
+            + self.build_outputs.iter().map(|outputs| outputs.size).sum::<u64>()
+            + self.artifacts.iter().map(|(_, size)| size).sum::<u64>()
+    }
+}
+
+// 按规则清理旧提交、编译产物和评测产物，保存最近一次的报告
+pub struct RetentionJobs {
+    policy: RetentionPolicy,
+    artifacts: Arc<ArtifactStore>,
+    running: Mutex<()>,
+    last: Mutex<Option<Arc<RetentionReport>>>,
+}
+
+impl RetentionJobs {
+    pub fn from_env(artifacts: Arc<ArtifactStore>) -> Self {
+        Self {
+            policy: RetentionPolicy::from_env(&artifacts),
+            artifacts,
+            running: Mutex::new(()),
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.try_lock().is_err()
+    }
+
+    pub async fn last_report(&self) -> Option<Arc<RetentionReport>> {
+        self.last.lock().await.clone()
+    }
+
+    // 按规则清理一次，dry_run 时只生成报告。已有清理在进行时返回 None
+    pub async fn run(&self, pool: &DbPool, dry_run: bool, scheduled: bool) -> Option<Arc<RetentionReport>> {
+        let _running = self.running.try_lock().ok()?;
+
+        let mut report = match self.plan(pool, dry_run, scheduled).await {
+            Ok(report) => report,
+            Err(e) => {
+                let mut report = RetentionReport::new(dry_run, scheduled);
+                report.errors.push(e);
+                report
+            }
+        };
+        if !dry_run {
+            self.apply(pool, &mut report).await;
+        }
+        for e in &report.errors {
+            tracing::error!("存储清理出错: {}", e);
+        }
+        report.finished_at = chrono::Utc::now();
+
+        let report = Arc::new(report);
+        *self.last.lock().await = Some(report.clone());
+        Some(report)
+    }
+
+    // 找出按规则应当删除的内容
+    async fn plan(&self, pool: &DbPool, dry_run: bool, scheduled: bool) -> Result<RetentionReport, String> {
+        let mut report = RetentionReport::new(dry_run, scheduled);
+
+        let summaries = TestRepo::get_finished_summaries(pool)
+            .await
+            .map_err(|e| format!("读取评测结果失败: {}", e))?;
+        let best = best_results(&summaries);
+        let best_uploads: HashSet<i32> = summaries
+            .iter()
+            .filter(|summary| best.contains(&summary.id))
+            .filter_map(|summary| summary.upload_id)
+            .collect();
+
+        if self.policy.keep_latest > 0 {
+            let uploads = UploadRepo::get_all_uploads(pool)
+                .await
+                .map_err(|e| format!("读取上传记录失败: {}", e))?;
+            report.uploads = stale_uploads(uploads, self.policy.keep_latest, &best_uploads);
+        }
+
+        if self.policy.purge_build_outputs {
+            let deleted: HashSet<i32> = report.uploads.iter().map(|upload| upload.id).collect();
+            let files = BlobRepo::get_build_outputs(pool)
+                .await
+                .map_err(|e| format!("读取编译产物失败: {}", e))?;
+            for (upload_id, username, project_dir, path, size) in files {
+                if deleted.contains(&upload_id) {
+                    continue;
+                }
+                let dir = match crate_target_dir(&path, &username, &project_dir) {
+                    Some(dir) => dir,
+                    None => continue,
+                };
+                // 记录按上传ID排列，同一次上传的文件相邻
+                if report.build_outputs.last().map_or(true, |outputs| outputs.upload_id != upload_id) {
+                    report.build_outputs.push(BuildOutputs {
+                        upload_id,
+                        username,
+                        dirs: Vec::new(),
+                        files: 0,
+                        size: 0,
+                    });
+                }
+                let outputs = report.build_outputs.last_mut().unwrap();
+                if !outputs.dirs.iter().any(|d| d == dir) {
+                    outputs.dirs.push(dir.to_string());
+                }
+                outputs.files += 1;
+                outputs.size += size;
+            }
+        }
+
+        let expired = self
+            .artifacts
+            .expired_runs()
+            .await
+            .map_err(|e| format!("读取评测产物失败: {}", e))?;
+        for (test_id, size) in expired {
+            if best.contains(&test_id) {
+                report.kept_best += 1;
+            } else {
+                report.artifacts.push((test_id, size));
+            }
+        }
+
+        Ok(report)
+    }
+
+    // 删除报告中列出的内容，失败的项目记录在报告中
+    async fn apply(&self, pool: &DbPool, report: &mut RetentionReport) {
+        let mut errors = Vec::new();
+
+        for upload in &report.uploads {
+            // 生成报告之后可能又提交了评测
+            match UploadRepo::get_upload_by_id(pool, upload.id).await {
+                Ok(Some(current)) if !is_judging(&current) => {}
+                Ok(_) => continue,
+                Err(e) => {
+                    errors.push(format!("读取上传 #{} 失败: {}", upload.id, e));
+                    continue;
+                }
+            }
+            if let Err(e) = remove_local_upload(upload).await {
+                errors.push(format!("删除上传 #{} 的文件失败: {}", upload.id, e));
+                continue;
+            }
+            if let Err(e) = UploadRepo::remove_upload(pool, upload.id).await {
+                errors.push(format!("删除上传记录 #{} 失败: {}", upload.id, e));
+            }
+        }
+
+        for outputs in &report.build_outputs {
+            // 先删除文件记录，之后恢复上传时不会再取回这些文件；存储中的对象由垃圾回收删除
+            if let Err(e) = BlobRepo::remove_build_outputs(pool, outputs.upload_id, &outputs.dirs).await {
+                errors.push(format!("删除上传 #{} 的编译产物记录失败: {}", outputs.upload_id, e));
+                continue;
+            }
+            let user_dir = PathBuf::from(format!("uploads/{}", outputs.username));
+            for dir in &outputs.dirs {
+                match tokio::fs::remove_dir_all(user_dir.join(dir)).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        errors.push(format!("删除 {} 失败: {}", dir, e));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for (test_id, _) in &report.artifacts {
+            if let Err(e) = self.artifacts.remove_run(*test_id).await {
+                errors.push(format!("删除评测 #{} 的产物失败: {}", test_id, e));
+            }
+        }
+
+        report.errors.extend(errors);
+    }
+}
+
+// 排队或评测中的上传还会被评测工作器使用
+fn is_judging(upload: &UploadRecord) -> bool {
+    matches!(upload.test_status.as_deref(), Some("Pending") | Some("Running"))
+}
+
+// 每个学生在每道题上的最好成绩：先比较是否通过，再比较通过的测试点数，都相同时取最新的一次
+fn best_results(summaries: &[ResultSummary]) -> HashSet<i32> {
+    let rank = |summary: &ResultSummary| (summary.status == "Passed", summary.passed_cases, summary.id);
+    let mut best: HashMap<(i32, Option<&str>), &ResultSummary> = HashMap::new();
+    for summary in summaries {
+        let key = (summary.user_id, summary.profile.as_deref());
+        match best.get(&key) {
+            Some(current) if rank(current) >= rank(summary) => {}
+            _ => {
+                best.insert(key, summary);
+            }
+        }
+    }
+    best.values().map(|summary| summary.id).collect()
+}
+
+// 每个学生每道题保留最近的 keep_latest 次上传，返回其余可以删除的上传。
+// 上传记录按从新到旧排列；没有评测过的上传不属于任何题目，最好成绩对应的上传和评测中的上传都不删除
+fn stale_uploads(uploads: Vec<UploadRecord>, keep_latest: usize, best_uploads: &HashSet<i32>) -> Vec<UploadRecord> {
+    let mut counts: HashMap<(Option<String>, String), usize> = HashMap::new();
+    let mut stale = Vec::new();
+    for upload in uploads {
+        let profile = match upload.test_profile.clone() {
+            Some(profile) => profile,
+            None => continue,
+        };
+        let count = counts.entry((upload.username.clone(), profile)).or_insert(0);
+        *count += 1;
+        if *count <= keep_latest || best_uploads.contains(&upload.id) || is_judging(&upload) {
+            continue;
+        }
+        stale.push(upload);
+    }
+    stale
+}
+
+// 文件所在的编译产物目录，只认工程根目录下 os 和 user 两个crate的 target 目录，
+// 如工程根目录为 uploads/alice/12/os_out 时，12/os_out/os/target/debug/os 对应 12/os_out/os/target
+fn crate_target_dir<'a>(path: &'a str, username: &str, project_dir: &str) -> Option<&'a str> {
+    let root = project_dir.strip_prefix(&format!("uploads/{}/", username))?.trim_end_matches('/');
+    ["os", "user"].iter().find_map(|name| {
+        let dir = format!("{}/{}/target", root, name);
+        path.strip_prefix(&dir)
+            .filter(|rest| rest.starts_with('/'))
+            .map(|_| &path[..dir.len()])
+    })
+}
+
+// 定期按规则清理
+pub async fn run_retention_task(jobs: Arc<RetentionJobs>, pool: DbPool) {
+    loop {
+        let dry_run = jobs.policy.dry_run;
+        if let Some(report) = jobs.run(&pool, dry_run, true).await {
+            if !report.is_empty() {
+                tracing::info!(
+                    "定时清理{}: {} 个上传、{} 个上传的编译产物、{} 个评测产物目录，共 {} 字节",
+                    if dry_run { "（试运行）" } else { "" },
+                    report.uploads.len(),
+                    report.build_outputs.len(),
+                    report.artifacts.len(),
+                    report.total_size()
+                );
+            }
+        }
+        tokio::time::sleep(jobs.policy.interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(id: i32, username: &str, test: Option<(&str, &str)>) -> UploadRecord {
+        UploadRecord {
+            id,
+            filename: "os.zip".to_string(),
+            file_path: format!("uploads/{}/{}/os.zip", username, id),
+            file_size: 2048,
+            uploaded_at: chrono::Utc::now(),
+            username: Some(username.to_string()),
+            project_dir: None,
+            test_id: test.map(|_| id),
+            test_status: test.map(|(_, status)| status.to_string()),
+            test_profile: test.map(|(profile, _)| profile.to_string()),
+        }
+    }
+
+    fn ids(uploads: &[UploadRecord]) -> Vec<i32> {
+        uploads.iter().map(|upload| upload.id).collect()
+    }
+
+    #[test]
+    fn keeps_latest_uploads_per_student_and_problem() {
+        let uploads = vec![
+            upload(6, "alice", Some(("ch3", "Failed"))),
+            upload(5, "alice", Some(("ch4", "Failed"))),
+            upload(4, "bob", Some(("ch3", "Failed"))),
+            upload(3, "alice", Some(("ch3", "Failed"))),
+            upload(2, "alice", Some(("ch3", "Passed"))),
+            upload(1, "alice", Some(("ch3", "Failed"))),
+        ];
+        let best = HashSet::from([2]);
+        assert_eq!(ids(&stale_uploads(uploads, 1, &best)), vec![3, 1]);
+    }
+
+    #[test]
+    fn untested_and_judging_uploads_are_kept() {
+        let uploads = vec![
+            upload(4, "alice", Some(("ch3", "Passed"))),
+            upload(3, "alice", None),
+            upload(2, "alice", None),
+            upload(1, "alice", Some(("ch3", "Running"))),
+        ];
+        assert!(stale_uploads(uploads, 1, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn only_crate_target_dirs_are_build_outputs() {
+        let root = "uploads/alice/12/os_out";
+        assert_eq!(crate_target_dir("12/os_out/os/target/debug/os", "alice", root), Some("12/os_out/os/target"));
+        assert_eq!(
+            crate_target_dir("12/os_out/user/target/riscv64gc-unknown-none-elf/release/ch3_sleep", "alice", root),
+            Some("12/os_out/user/target")
+        );
+        // 学生自己的目录即使叫 target 也不删除
+        assert_eq!(crate_target_dir("12/os_out/os/src/target/mod.rs", "alice", root), None);
+        assert_eq!(crate_target_dir("12/os_out/docs/os/target/notes.md", "alice", root), None);
+        assert_eq!(crate_target_dir("12/os_out/easy-fs/target/debug/easy-fs", "alice", root), None);
+        assert_eq!(crate_target_dir("12/os_out/os/targets/x", "alice", root), None);
+        assert_eq!(crate_target_dir("12/os_out/os/target/debug/os", "bob", root), None);
+    }
+}
@@ -0,0 +1,574 @@
+use crate::blobstore::BlobStore;
+use crate::database::DbPool;
+use crate::lexer::{tokenize, TokenKind};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// winnowing 参数：k 个连续 token 组成一个 k-gram，每 WINDOW 个 k-gram 中至少选出一个指纹。
+// 长度不小于 KGRAM + WINDOW - 1 个 token 的相同片段一定能被发现
+const KGRAM: usize = 12;
+const WINDOW: usize = 8;
+
+// 报告中保留的相似度最高的提交对数量
+const MAX_REPORTED_PAIRS: usize = 200;
+
+// 每对提交最多列出的相同片段数量
+const MAX_REGIONS_PER_PAIR: usize = 50;
+
+// 相同片段之间的行号间隔不超过此值时合并为一段
+const MERGE_GAP_LINES: usize = 2;
+
+// 参与比较的一份提交
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub upload_id: i32,
+    pub username: String,
+    pub root: PathBuf, // 工程根目录
+}
+
+// 一个指纹及其在源码中的位置
+#[derive(Debug, Clone)]
+struct Fingerprint {
+    hash: u64,
+    file: usize,
+    start_line: usize,
+    end_line: usize,
+}
+
+// 一份提交的所有指纹
+struct Fingerprinted {
+    files: Vec<String>,
+    prints: Vec<Fingerprint>,
+}
+
+// 起止行号，从 1 开始（闭区间）
+pub type LineRange = (usize, usize);
+
+// 两份提交中相同的一段代码
+#[derive(Debug, Clone)]
+pub struct MatchRegion {
+    pub file_a: String,
+    pub lines_a: LineRange,
+    pub file_b: String,
+    pub lines_b: LineRange,
+}
+
+#[derive(Debug, Clone)]
+pub struct PairReport {
+    pub a: usize, // 在 submissions 中的下标
+    pub b: usize,
+    pub shared: usize,   // 相同的指纹数
+    pub similarity: f64, // 相同指纹数占较少一方指纹数的比例
+    pub regions: Vec<MatchRegion>,
+}
+
+pub struct SimilarityReport {
+    pub profile: String,
+    pub source_dir: String,
+    pub submissions: Vec<Submission>,
+    pub fingerprint_counts: Vec<usize>,
+    pub pairs: Vec<PairReport>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl SimilarityReport {
+    // 导出为 CSV，每行一对提交
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("user_a,upload_a,user_b,upload_b,similarity,shared_fingerprints,regions\n");
+        for pair in &self.pairs {
+            let a = &self.submissions[pair.a];
+            let b = &self.submissions[pair.b];
+            csv.push_str(&format!(
+                "{},{},{},{},{:.4},{},{}\n",
+                csv_field(&a.username),
+                a.upload_id,
+                csv_field(&b.username),
+                b.upload_id,
+                pair.similarity,
+                pair.shared,
+                pair.regions.len()
+            ));
+        }
+        csv
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// 对每份提交的 source_dir 下的 .rs 文件计算指纹，去掉初始代码中也存在的指纹后两两比较
+pub fn analyze(profile: &str, source_dir: &str, baseline: Option<&Path>, submissions: Vec<Submission>) -> SimilarityReport {
+    let excluded: HashSet<u64> = baseline
+        .map(|root| fingerprint_tree(&root.join(source_dir)).prints.into_iter().map(|p| p.hash).collect())
+        .unwrap_or_default();
+
+    let fingerprinted: Vec<Fingerprinted> = submissions
+        .iter()
+        .map(|submission| {
+            let mut result = fingerprint_tree(&submission.root.join(source_dir));
+            result.prints.retain(|p| !excluded.contains(&p.hash));
+            result
+        })
+        .collect();
+    let unique: Vec<HashSet<u64>> = fingerprinted
+        .iter()
+        .map(|f| f.prints.iter().map(|p| p.hash).collect())
+        .collect();
+
+    // 倒排索引：每个指纹出现在哪些提交中
+    let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, hashes) in unique.iter().enumerate() {
+        for &hash in hashes {
+            index.entry(hash).or_default().push(i);
+        }
+    }
+    let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+    for owners in index.values() {
+        for (x, &a) in owners.iter().enumerate() {
+            for &b in &owners[x + 1..] {
+                *shared.entry((a, b)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<PairReport> = shared
+        .into_iter()
+        .map(|((a, b), count)| {
+            let smaller = unique[a].len().min(unique[b].len()).max(1);
+            PairReport {
+                a,
+                b,
+                shared: count,
+                similarity: count as f64 / smaller as f64,
+                regions: Vec::new(),
+            }
+        })
+        .collect();
+    pairs.sort_by(|x, y| y.similarity.total_cmp(&x.similarity).then(y.shared.cmp(&x.shared)));
+    pairs.truncate(MAX_REPORTED_PAIRS);
+    for pair in &mut pairs {
+        pair.regions = match_regions(&fingerprinted[pair.a], &fingerprinted[pair.b]);
+    }
+
+    SimilarityReport {
+        profile: profile.to_string(),
+        source_dir: source_dir.to_string(),
+        fingerprint_counts: unique.iter().map(HashSet::len).collect(),
+        submissions,
+        pairs,
+        finished_at: chrono::Utc::now(),
+    }
+}
+
+// 计算目录下所有 .rs 文件的指纹，文件之间不跨越
+fn fingerprint_tree(dir: &Path) -> Fingerprinted {
+    let mut paths = Vec::new();
+    collect_sources(dir, dir, &mut paths);
+    paths.sort();
+
+    let mut result = Fingerprinted {
+        files: Vec::new(),
+        prints: Vec::new(),
+    };
+    for relative in paths {
+        let source = match std::fs::read_to_string(dir.join(&relative)) {
+            Ok(source) => source,
+            Err(_) => continue,
+        };
+        let file = result.files.len();
+        result.files.push(relative);
+        for (hash, start_line, end_line) in winnow(&source) {
+            result.prints.push(Fingerprint {
+                hash,
+                file,
+                start_line,
+                end_line,
+            });
+        }
+    }
+    result
+}
+
+fn collect_sources(root: &Path, dir: &Path, paths: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        if file_type.is_dir() {
+            collect_sources(root, &path, paths);
+        } else if file_type.is_file() && path.extension().map_or(false, |ext| ext == "rs") {
+            if let Ok(relative) = path.strip_prefix(root) {
+                paths.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+}
+
+// 去掉空白和注释，并把标识符和字面量替换为占位符，使改名和改常量不影响比较结果
+fn normalized_tokens(source: &str) -> Vec<(&str, usize)> {
+    let mut line = 1;
+    let mut last_offset = 0;
+    let mut tokens = Vec::new();
+    for token in tokenize(source) {
+        line += source[last_offset..token.offset].matches('\n').count();
+        last_offset = token.offset;
+        let text = match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment => continue,
+            TokenKind::Ident => "$id",
+            TokenKind::Number => "$num",
+            TokenKind::Str | TokenKind::Char => "$str",
+            TokenKind::Lifetime => "$lt",
+            TokenKind::Keyword | TokenKind::Punct => token.text,
+        };
+        tokens.push((text, line));
+    }
+    tokens
+}
+
+// winnowing：在每个窗口中选出哈希最小的 k-gram（相同时取最右边的），返回 (哈希, 起始行, 结束行)
+fn winnow(source: &str) -> Vec<(u64, usize, usize)> {
+    let tokens = normalized_tokens(source);
+    if tokens.len() < KGRAM {
+        return Vec::new();
+    }
+    let grams: Vec<(u64, usize, usize)> = tokens
+        .windows(KGRAM)
+        .map(|gram| {
+            let mut hasher = DefaultHasher::new();
+            for (text, _) in gram {
+                text.hash(&mut hasher);
+            }
+            (hasher.finish(), gram[0].1, gram[KGRAM - 1].1)
+        })
+        .collect();
+
+    let mut selected = Vec::new();
+    let mut last = None;
+    for start in 0..grams.len().saturating_sub(WINDOW - 1).max(1) {
+        let window = &grams[start..(start + WINDOW).min(grams.len())];
+        let (offset, _) = window
+            .iter()
+            .enumerate()
+            .rev()
+            .min_by_key(|(_, (hash, _, _))| *hash)
+            .expect("窗口不为空");
+        let position = start + offset;
+        if last != Some(position) {
+            selected.push(grams[position]);
+            last = Some(position);
+        }
+    }
+    selected
+}
+
+// 找出两份提交中相同的代码片段，相邻的片段合并为一段
+fn match_regions(a: &Fingerprinted, b: &Fingerprinted) -> Vec<MatchRegion> {
+    let mut first_in_b: HashMap<u64, &Fingerprint> = HashMap::new();
+    for print in &b.prints {
+        first_in_b.entry(print.hash).or_insert(print);
+    }
+
+    let mut matches: Vec<(&Fingerprint, &Fingerprint)> = a
+        .prints
+        .iter()
+        .filter_map(|pa| first_in_b.get(&pa.hash).map(|pb| (pa, *pb)))
+        .collect();
+    matches.sort_by_key(|(pa, pb)| (pa.file, pb.file, pa.start_line, pb.start_line));
+
+    let mut regions: Vec<(usize, LineRange, usize, LineRange)> = Vec::new();
+    for (pa, pb) in matches {
+        if let Some(last) = regions.last_mut() {
+            let adjacent = |range: LineRange, start: usize, end: usize| {
+                start <= range.1 + MERGE_GAP_LINES && end + MERGE_GAP_LINES >= range.0
+            };
+            if last.0 == pa.file
+                && last.2 == pb.file
+                && adjacent(last.1, pa.start_line, pa.end_line)
+                && adjacent(last.3, pb.start_line, pb.end_line)
+            {
+                last.1 = (last.1 .0.min(pa.start_line), last.1 .1.max(pa.end_line));
+                last.3 = (last.3 .0.min(pb.start_line), last.3 .1.max(pb.end_line));
+                continue;
+            }
+        }
+        regions.push((pa.file, (pa.start_line, pa.end_line), pb.file, (pb.start_line, pb.end_line)));
+    }
+
+    // 优先列出较长的片段
+    regions.sort_by_key(|region| std::cmp::Reverse(region.1 .1 - region.1 .0));
+    regions.truncate(MAX_REGIONS_PER_PAIR);
+    regions
+        .into_iter()
+        .map(|(file_a, lines_a, file_b, lines_b)| MatchRegion {
+            file_a: a.files[file_a].clone(),
+            lines_a,
+            file_b: b.files[file_b].clone(),
+            lines_b,
+        })
+        .collect()
+}
+
+// 后台分析任务的状态
+pub enum AnalysisState {
+    Running { started_at: chrono::DateTime<chrono::Utc>, submissions: usize },
+    Done(Arc<SimilarityReport>),
+    Failed(String),
+}
+
+// 各题目最近一次相似度分析，分析在后台线程中进行
+#[derive(Default)]
+pub struct SimilarityJobs {
+    jobs: Mutex<HashMap<String, AnalysisState>>,
+}
+
+impl SimilarityJobs {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // 开始分析，同一题目已有分析在进行时返回 false。
+    // candidates 按从新到旧排列，可以包含同一学生的多次上传，后台任务从存储中取出后每个学生只取最近的一份
+    pub async fn start(
+        self: &Arc<Self>,
+        profile: String,
+        source_dir: String,
+        baseline: Option<PathBuf>,
+        candidates: Vec<Submission>,
+        blobs: Arc<BlobStore>,
+        pool: Arc<DbPool>,
+    ) -> bool {
+        let students: HashSet<&str> = candidates.iter().map(|candidate| candidate.username.as_str()).collect();
+        let students = students.len();
+        {
+            let mut jobs = self.jobs.lock().await;
+            if matches!(jobs.get(&profile), Some(AnalysisState::Running { .. })) {
+                return false;
+            }
+            jobs.insert(
+                profile.clone(),
+                AnalysisState::Running {
+                    started_at: chrono::Utc::now(),
+                    submissions: students,
+                },
+            );
+        }
+
+        let jobs = self.clone();
+        tokio::spawn(async move {
+            let submissions = restore_latest(&blobs, &pool, candidates).await;
+            if submissions.len() < 2 {
+                tracing::warn!("题目 {} 可以取出的提交少于两份，无法比较", profile);
+                let state = AnalysisState::Failed("可以取出的提交少于两份，无法比较".to_string());
+                jobs.jobs.lock().await.insert(profile, state);
+                return;
+            }
+            let name = profile.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                analyze(&name, &source_dir, baseline.as_deref(), submissions)
+            })
+            .await;
+            let state = match result {
+                Ok(report) => {
+                    tracing::info!("题目 {} 的相似度分析完成，共 {} 对可疑提交", profile, report.pairs.len());
+                    AnalysisState::Done(Arc::new(report))
+                }
+                Err(e) => {
+                    tracing::error!("题目 {} 的相似度分析异常退出: {}", profile, e);
+                    AnalysisState::Failed(e.to_string())
+                }
+            };
+            jobs.jobs.lock().await.insert(profile, state);
+        });
+        true
+    }
+
+    // 题目最近一次分析的状态
+    pub async fn state(&self, profile: &str) -> Option<AnalysisState> {
+        self.jobs.lock().await.get(profile).map(|state| match state {
+            AnalysisState::Running { started_at, submissions } => AnalysisState::Running {
+                started_at: *started_at,
+                submissions: *submissions,
+            },
+            AnalysisState::Done(report) => AnalysisState::Done(report.clone()),
+            AnalysisState::Failed(reason) => AnalysisState::Failed(reason.clone()),
+        })
+    }
+
+    // 题目最近一次完成的分析报告
+    pub async fn report(&self, profile: &str) -> Option<Arc<SimilarityReport>> {
+        match self.jobs.lock().await.get(profile) {
+            Some(AnalysisState::Done(report)) => Some(report.clone()),
+            _ => None,
+        }
+    }
+}
+
+// 在其他节点上传的提交先从存储中取到本地，每个学生取最近一份工程目录存在的提交
+async fn restore_latest(blobs: &BlobStore, pool: &DbPool, candidates: Vec<Submission>) -> Vec<Submission> {
+    let mut seen = HashSet::new();
+    let mut submissions = Vec::new();
+    for candidate in candidates {
+        if seen.contains(&candidate.username) {
+            continue;
+        }
+        if let Err(e) = blobs.restore_upload(pool, candidate.upload_id, &candidate.username).await {
+            tracing::warn!("Failed to restore files of upload {}: {}", candidate.upload_id, e);
+        }
+        if candidate.root.is_dir() {
+            seen.insert(candidate.username.clone());
+            submissions.push(candidate);
+        }
+    }
+    submissions
+}
+
+// 读取一段相同片段的源码，行号从 1 开始（闭区间）；文件已被删除时返回空
+pub fn region_source(root: &Path, source_dir: &str, file: &str, lines: (usize, usize)) -> Vec<String> {
+    std::fs::read_to_string(root.join(source_dir).join(file))
+        .map(|source| {
+            source
+                .lines()
+                .skip(lines.0.saturating_sub(1))
+                .take(lines.1 + 1 - lines.0.max(1))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
+    // 只支持标准输出
+    match fd {
+        FD_STDOUT => {
+            let slice = unsafe { core::slice::from_raw_parts(buf, len) };
+            let text = core::str::from_utf8(slice).unwrap();
+            print!(\"{}\", text);
+            len as isize
+        }
+        _ => panic!(\"Unsupported fd in sys_write!\"),
+    }
+}
+";
+
+    fn hashes(source: &str) -> Vec<u64> {
+        winnow(source).into_iter().map(|(hash, _, _)| hash).collect()
+    }
+
+    fn print(hash: u64, file: usize, lines: (usize, usize)) -> Fingerprint {
+        Fingerprint {
+            hash,
+            file,
+            start_line: lines.0,
+            end_line: lines.1,
+        }
+    }
+
+    #[test]
+    fn winnow_ignores_renames_literals_and_comments() {
+        let renamed = SOURCE
+            .replace("sys_write", "write_impl")
+            .replace("slice", "bytes")
+            .replace("// 只支持标准输出", "/* stdout only */")
+            .replace("Unsupported fd in sys_write!", "bad fd");
+        assert!(!hashes(SOURCE).is_empty());
+        assert_eq!(hashes(SOURCE), hashes(&renamed));
+        assert!(winnow("fn main() {}").is_empty());
+    }
+
+    #[test]
+    fn winnow_finds_shared_fragment_in_different_context() {
+        // 相同片段足够长时，无论前后是什么代码，都至少有一个相同的指纹
+        let a = format!("const A: usize = 1;\n{}", SOURCE);
+        let b = format!("{}struct Unrelated {{ x: u8, y: [u16; 4] }}\nimpl Unrelated {{ fn get(&self) -> u8 {{ self.x }} }}\n", SOURCE);
+        let b_hashes: HashSet<u64> = hashes(&b).into_iter().collect();
+        assert!(hashes(&a).iter().any(|hash| b_hashes.contains(hash)));
+
+        let lines: Vec<(usize, usize)> = winnow(&a).into_iter().map(|(_, start, end)| (start, end)).collect();
+        assert!(lines.iter().all(|&(start, end)| 1 <= start && start <= end && end <= 13));
+    }
+
+    #[test]
+    fn match_regions_merges_adjacent_matches_per_file() {
+        let a = Fingerprinted {
+            files: vec!["main.rs".to_string(), "task.rs".to_string()],
+            prints: vec![print(1, 0, (1, 3)), print(2, 0, (4, 6)), print(3, 0, (30, 31)), print(4, 1, (5, 9)), print(9, 1, (1, 2))],
+        };
+        let b = Fingerprinted {
+            files: vec!["lib.rs".to_string()],
+            prints: vec![print(1, 0, (10, 12)), print(2, 0, (13, 16)), print(3, 0, (50, 51)), print(4, 0, (60, 64))],
+        };
+
+        let regions = match_regions(&a, &b);
+        let regions: Vec<(&str, LineRange, &str, LineRange)> = regions
+            .iter()
+            .map(|r| (r.file_a.as_str(), r.lines_a, r.file_b.as_str(), r.lines_b))
+            .collect();
+        // 按片段长度从长到短排列
+        assert_eq!(
+            regions,
+            [
+                ("main.rs", (1, 6), "lib.rs", (10, 16)),
+                ("task.rs", (5, 9), "lib.rs", (60, 64)),
+                ("main.rs", (30, 31), "lib.rs", (50, 51)),
+            ]
+        );
+    }
+
+    #[test]
+    fn analyze_excludes_baseline_and_pairs_copies() {
+        let base = std::env::temp_dir().join(format!("similarity_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let write = |dir: &str, source: &str| {
+            let path = base.join(dir).join("os/src");
+            std::fs::create_dir_all(&path).unwrap();
+            std::fs::write(path.join("syscall.rs"), source).unwrap();
+        };
+        let other = "fn fib(n: u64) -> u64 {\n    if n < 2 { return n; }\n    let mut a = 0;\n    let mut b = 1;\n    for _ in 1..n { let c = a + b; a = b; b = c; }\n    b\n}\n";
+        write("alice", SOURCE);
+        write("bob", &SOURCE.replace("text", "s"));
+        write("carol", other);
+        write("baseline", other);
+
+        let submissions = ["alice", "bob", "carol"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Submission {
+                upload_id: i as i32 + 1,
+                username: name.to_string(),
+                root: base.join(name),
+            })
+            .collect();
+        let report = analyze("ch2", "os/src", Some(&base.join("baseline")), submissions);
+        std::fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(report.fingerprint_counts[2], 0);
+        assert_eq!(report.pairs.len(), 1);
+        let pair = &report.pairs[0];
+        assert_eq!((pair.a, pair.b), (0, 1));
+        assert_eq!(pair.similarity, 1.0);
+        assert_eq!(pair.regions.len(), 1);
+        assert_eq!(pair.regions[0].file_a, "syscall.rs");
+        assert!(report.to_csv().starts_with("user_a,upload_a,user_b,upload_b,similarity,shared_fingerprints,regions\nalice,1,bob,2,1.0000,"));
+    }
+}
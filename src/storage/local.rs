@@ -0,0 +1,125 @@
+use super::{temp_path, ObjectInfo, Storage};
+use async_trait::async_trait;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// 保存在本地目录中的存储，对象键名即相对路径。
+// 对象文件是只读的：上传目录中的文件是它们的硬链接，修改会影响所有引用
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    fn describe(&self) -> String {
+        format!("本地目录 {}", self.root.display())
+    }
+
+    fn local_path(&self, key: &str) -> Option<PathBuf> {
+        Some(self.path_for(key))
+    }
+
+    async fn exists(&self, key: &str) -> io::Result<bool> {
+        match tokio::fs::metadata(self.path_for(key)).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn put_file(&self, key: &str, path: &Path) -> io::Result<()> {
+        let dest = self.path_for(key);
+        let source = path.to_path_buf();
+        tokio::task::spawn_blocking(move || store_readonly(&source, &dest))
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    }
+
+    async fn get_file(&self, key: &str, dest: &Path) -> io::Result<()> {
+        let source = self.path_for(key);
+        let dest = dest.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // 不使用 fs::copy：它会复制只读权限
+            let temp = temp_path(&dest);
+            let copied = std::fs::File::open(&source)
+                .and_then(|mut src| std::fs::File::create(&temp).and_then(|mut out| std::io::copy(&mut src, &mut out)))
+                .and_then(|_| std::fs::rename(&temp, &dest));
+            if copied.is_err() {
+                let _ = std::fs::remove_file(&temp);
+            }
+            copied
+        })
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        match tokio::fs::remove_file(self.path_for(key)).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> io::Result<Vec<ObjectInfo>> {
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut objects = Vec::new();
+            if root.exists() {
+                collect_objects(&root, "", &mut objects)?;
+            }
+            Ok(objects)
+        })
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    }
+}
+
+// 先复制到临时文件并设为只读，再改名为对象文件
+pub(crate) fn store_readonly(source: &Path, dest: &Path) -> io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = temp_path(dest);
+    let stored = std::fs::copy(source, &temp).and_then(|_| {
+        let mut permissions = std::fs::metadata(&temp)?.permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&temp, permissions)?;
+        std::fs::rename(&temp, dest)
+    });
+    if stored.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    stored
+}
+
+fn collect_objects(dir: &Path, prefix: &str, objects: &mut Vec<ObjectInfo>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let key = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_objects(&entry.path(), &format!("{}/", key), objects)?;
+        } else if file_type.is_file() {
+            let metadata = entry.metadata()?;
+            objects.push(ObjectInfo {
+                key,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+    }
+    Ok(())
+}
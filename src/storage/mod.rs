@@ -0,0 +1,78 @@
+mod local;
+mod s3;
+
+use async_trait::async_trait;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+// 区分同时写入的临时文件
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub use local::LocalStorage;
+pub(crate) use local::store_readonly;
+pub use s3::S3Storage;
+
+// 存储中的一个对象
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+// 保存提交文件的存储后端。对象以 / 分隔的键名存取，
+// 多个网页节点和评测节点使用同一个存储时可以共享提交
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // 日志中显示的存储位置
+    fn describe(&self) -> String;
+
+    // 对象在本地文件系统中的路径。本地存储返回路径，上传目录可以直接硬链接到对象上
+    fn local_path(&self, _key: &str) -> Option<PathBuf> {
+        None
+    }
+
+    async fn exists(&self, key: &str) -> io::Result<bool>;
+
+    // 把本地文件保存为对象，已存在时覆盖
+    async fn put_file(&self, key: &str, path: &Path) -> io::Result<()>;
+
+    // 把对象下载到本地文件，dest 不存在的上级目录会被创建。下载的文件总是可写的
+    async fn get_file(&self, key: &str, dest: &Path) -> io::Result<()>;
+
+    // 删除对象，对象不存在时不报错
+    async fn delete(&self, key: &str) -> io::Result<()>;
+
+    // 列出所有对象
+    async fn list(&self) -> io::Result<Vec<ObjectInfo>>;
+}
+
+// 从环境变量读取配置：
+// STORAGE_BACKEND  local 或 s3，默认 local
+// local：BLOB_STORE_DIR 存储根目录，默认 blobs，需要与 uploads 在同一文件系统上才能建立硬链接
+// s3：S3_ENDPOINT（如 http://127.0.0.1:9000）、S3_BUCKET、S3_ACCESS_KEY、S3_SECRET_KEY、
+//     S3_REGION（默认 us-east-1）、S3_PREFIX（对象键名前缀，默认为空）
+pub fn from_env() -> Result<Arc<dyn Storage>, String> {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+    match backend.as_str() {
+        "local" => {
+            let root = std::env::var("BLOB_STORE_DIR").unwrap_or_else(|_| "blobs".to_string());
+            Ok(Arc::new(LocalStorage::new(PathBuf::from(root))))
+        }
+        "s3" => Ok(Arc::new(S3Storage::from_env()?)),
+        other => Err(format!("未知的存储后端: {}", other)),
+    }
+}
+
+// 临时文件名的后缀，多个进程、多个任务同时写入时互不冲突
+pub fn temp_suffix() -> String {
+    format!("{}.{}", std::process::id(), TEMP_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+// 写入时先写到同一目录下的临时文件，完成后再改名，读取者不会看到不完整的文件
+fn temp_path(dest: &Path) -> PathBuf {
+    let name = dest.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    dest.with_file_name(format!(".{}.{}.part", name, temp_suffix()))
+}
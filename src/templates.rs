@@ -1,4 +1,11 @@
-use crate::models::{User, UserRole, UploadRecord, TestResult, TestStatus}; // Add TestResult, TestStatus
+use crate::artifacts::{ArtifactInfo, ArtifactKind};
+use crate::diff::{DiffLine, FileDiff, FileSummary, Hunk, LineTag};
+use crate::lexer::{highlight_line, highlight_source};
+use crate::quota::StorageUsage;
+use crate::retention::{RetentionPolicy, RetentionReport};
+use crate::similarity::{AnalysisState, PairReport, SimilarityReport};
+use crate::tester::{HealthSnapshot, LayoutReport, VendorMirror};
+use crate::models::{User, UserRole, UploadRecord, TestResult, TestStatus, TestCaseResult, CaseVerdict}; // Add TestResult, TestStatus
 use std::fs;
 use std::path::Path;
 
@@ -27,6 +34,14 @@ const TEST_RESULTS_DETAIL_TEMPLATE_PATH: &str = "templates/test_results_detail.h
 const UPLOADS_TABLE_PATH: &str = "templates/uploads_table.html"; // 新增
 const FILES_LIST_TABLE_PATH: &str = "templates/files_list_table.html"; // 新增
 const TEST_RESULTS_LIST_TABLE_PATH: &str = "templates/test_results_list_table.html"; // 新增
+const JUDGE_HEALTH_TEMPLATE_PATH: &str = "templates/judge_health.html";
+const VENDOR_CRATES_TEMPLATE_PATH: &str = "templates/vendor_crates.html";
+const LAYOUT_REPORT_TEMPLATE_PATH: &str = "templates/layout_report.html";
+const SUBMISSION_DIFF_TEMPLATE_PATH: &str = "templates/submission_diff.html";
+const SIMILARITY_TEMPLATE_PATH: &str = "templates/similarity.html";
+const SIMILARITY_PAIR_TEMPLATE_PATH: &str = "templates/similarity_pair.html";
+const FILE_VIEW_TEMPLATE_PATH: &str = "templates/file_view.html";
+const RETENTION_TEMPLATE_PATH: &str = "templates/retention.html";
 
 // 确保模板目录存在
 pub fn ensure_templates_exist() -> std::io::Result<()> {
@@ -99,6 +114,30 @@ pub fn ensure_templates_exist() -> std::io::Result<()> {
     if !Path::new(TEST_RESULTS_DETAIL_TEMPLATE_PATH).exists() {
         fs::write(TEST_RESULTS_DETAIL_TEMPLATE_PATH, include_str!("../templates/test_results_detail.html"))?;
     }
+    if !Path::new(JUDGE_HEALTH_TEMPLATE_PATH).exists() {
+        fs::write(JUDGE_HEALTH_TEMPLATE_PATH, include_str!("../templates/judge_health.html"))?;
+    }
+    if !Path::new(VENDOR_CRATES_TEMPLATE_PATH).exists() {
+        fs::write(VENDOR_CRATES_TEMPLATE_PATH, include_str!("../templates/vendor_crates.html"))?;
+    }
+    if !Path::new(LAYOUT_REPORT_TEMPLATE_PATH).exists() {
+        fs::write(LAYOUT_REPORT_TEMPLATE_PATH, include_str!("../templates/layout_report.html"))?;
+    }
+    if !Path::new(SUBMISSION_DIFF_TEMPLATE_PATH).exists() {
+        fs::write(SUBMISSION_DIFF_TEMPLATE_PATH, include_str!("../templates/submission_diff.html"))?;
+    }
+    if !Path::new(SIMILARITY_TEMPLATE_PATH).exists() {
+        fs::write(SIMILARITY_TEMPLATE_PATH, include_str!("../templates/similarity.html"))?;
+    }
+    if !Path::new(SIMILARITY_PAIR_TEMPLATE_PATH).exists() {
+        fs::write(SIMILARITY_PAIR_TEMPLATE_PATH, include_str!("../templates/similarity_pair.html"))?;
+    }
+    if !Path::new(FILE_VIEW_TEMPLATE_PATH).exists() {
+        fs::write(FILE_VIEW_TEMPLATE_PATH, include_str!("../templates/file_view.html"))?;
+    }
+    if !Path::new(RETENTION_TEMPLATE_PATH).exists() {
+        fs::write(RETENTION_TEMPLATE_PATH, include_str!("../templates/retention.html"))?;
+    }
     // ... add checks for new templates like uploads_table.html and uploads_table_row.html if needed ...
 
     Ok(())
@@ -113,7 +152,7 @@ pub fn login_template() -> String {
 }
 
 // 首页模板
-pub fn index_template(username: &str, role: &UserRole) -> String {
+pub fn index_template(username: &str, role: &UserRole, profiles: &[String], usage: &StorageUsage) -> String {
     let role_text = match role {
         UserRole::Admin => "管理员",
         UserRole::Regular => "普通用户",
@@ -129,12 +168,31 @@ pub fn index_template(username: &str, role: &UserRole) -> String {
         "".to_string()
     };
 
+    // 管理员可以跳过结果缓存
+    let force_rerun_option = if matches!(role, UserRole::Admin) {
+        r#"<div class="form-group">
+                    <label><input type="checkbox" name="force_rerun" value="1"> 强制重新评测（不复用相同提交的结果）</label>
+                </div>"#
+    } else {
+        ""
+    };
+
+    // 题目评测配置下拉选项
+    let profile_options = profiles
+        .iter()
+        .map(|name| format!("<option value=\"{0}\">{0}</option>", html_escape::encode_text(name)))
+        .collect::<Vec<String>>()
+        .join("\n");
+
     read_template(INDEX_TEMPLATE_PATH)
         .map(|template| {
             template
                 .replace("{{username}}", username)
                 .replace("{{role}}", role_text)
-                .replace("{{admin_panel}}", &admin_panel_link)
+                .replace("{{admin_link}}", &admin_panel_link)
+                .replace("{{profile_options}}", &profile_options)
+                .replace("{{force_rerun_option}}", force_rerun_option)
+                .replace("{{storage_usage}}", &usage.describe())
         })
         .unwrap_or_else(|e| {
             tracing::error!("无法读取首页模板文件: {}", e);
@@ -205,9 +263,12 @@ fn build_users_html(users: &[User]) -> Result<String, std::io::Error> {
             let is_admin_selected = if matches!(user.role, UserRole::Admin) { "selected" } else { "" };
             let is_regular_selected = if matches!(user.role, UserRole::Regular) { "selected" } else { "" };
 
+            let quota_value = user.quota_mb.map_or(String::new(), |mb| mb.to_string());
+
             row_template
                 .replace("{{username}}", &user.username)
                 .replace("{{role_text}}", role_text)
+                .replace("{{quota_value}}", &quota_value)
                 .replace("{{is_admin_selected}}", is_admin_selected)
                 .replace("{{is_regular_selected}}", is_regular_selected)
         })
@@ -239,16 +300,54 @@ pub fn uploads_template(user: &User, uploads: &[UploadRecord]) -> String {
                     };
                     let time_display = upload.uploaded_at.format("%Y-%m-%d %H:%M:%S").to_string();
                     let username_cell = if matches!(user.role, UserRole::Admin) {
-                        format!("<td>{}</td>", upload.username.as_deref().unwrap_or("unknown"))
+                        format!("<td>{}</td>", html_escape::encode_text(upload.username.as_deref().unwrap_or("unknown")))
                     } else {
                         "".to_string()
                     };
 
+                    // 该上传最近一次评测的结果
+                    let verdict_cell = match (upload.test_id, upload.test_status.as_deref()) {
+                        (Some(test_id), Some(status)) => format!(
+                            r#"<a href="/test_results/{}" class="status-{}">{}</a> {}"#,
+                            test_id,
+                            status.to_lowercase(),
+                            html_escape::encode_text(status),
+                            html_escape::encode_text(upload.test_profile.as_deref().unwrap_or(""))
+                        ),
+                        _ => "-".to_string(),
+                    };
+                    // 通过检查的提交可以与初始代码或其他提交对比
+                    let diff_link = if upload.project_dir.is_some() {
+                        format!(
+                            r#"<a href="/files/{}/diff/{}" class="view-btn">对比</a>"#,
+                            urlencoding::encode(upload.username.as_deref().unwrap_or(&user.username)),
+                            upload.id
+                        )
+                    } else {
+                        String::new()
+                    };
+                    let action_cell = format!(
+                        r#"{0}
+<form action="/uploads/{1}/rename" method="post" class="inline-form">
+    <input type="text" name="filename" value="{2}" required>
+    <button type="submit" class="small-button">重命名</button>
+</form>
+<form action="/uploads/{1}/delete" method="post" class="inline-form" onsubmit="return confirm('确定要删除上传 #{1} 及其解压出的文件吗？');">
+    <button type="submit" class="small-button danger">删除</button>
+</form>"#,
+                        diff_link,
+                        upload.id,
+                        html_escape::encode_double_quoted_attribute(&upload.filename)
+                    );
+
                     row_template
-                        .replace("{{filename}}", &upload.filename)
-                        .replace("{{size}}", &size_display)
-                        .replace("{{time}}", &time_display)
+                        .replace("{{id}}", &upload.id.to_string())
+                        .replace("{{filename}}", &html_escape::encode_text(&upload.filename))
+                        .replace("{{size_display}}", &size_display)
+                        .replace("{{time_display}}", &time_display)
                         .replace("{{username_cell}}", &username_cell) // Assuming {{username_cell}} in row template
+                        .replace("{{verdict_cell}}", &verdict_cell)
+                        .replace("{{action_cell}}", &action_cell)
                 }).collect::<Vec<String>>().join("\n");
 
                 // 读取表格框架模板
@@ -275,6 +374,7 @@ pub fn uploads_template(user: &User, uploads: &[UploadRecord]) -> String {
             template
                 .replace("{{username}}", &user.username)
                 .replace("{{role}}", if matches!(user.role, UserRole::Admin) { "管理员" } else { "普通用户" })
+                .replace("{{error_message}}", "")
                 .replace("{{uploads_table}}", &uploads_table_html)
         })
         .unwrap_or_else(|e| {
@@ -306,11 +406,18 @@ pub fn alert_redirect_template(message: &str, redirect_url: &str) -> String {
 }
 
 // 新增：文件列表模板
-pub fn files_list_template(target_username: &str, files_content_html: &str) -> String {
+pub fn files_list_template(target_username: &str, current_path: &str, files_content_html: &str) -> String {
+    let archive_url = if current_path.is_empty() {
+        format!("/files/{}/archive", urlencoding::encode(target_username))
+    } else {
+        files_url(target_username, "raw", current_path)
+    };
     read_template(FILES_LIST_TEMPLATE_PATH)
         .map(|template| {
             template
-                .replace("{{target_username}}", target_username)
+                .replace("{{target_username}}", &html_escape::encode_text(target_username))
+                .replace("{{breadcrumbs}}", &breadcrumbs_html(target_username, current_path, true))
+                .replace("{{archive_url}}", &archive_url)
                 .replace("{{files_content}}", files_content_html)
         })
         .unwrap_or_else(|e| {
@@ -324,11 +431,12 @@ pub fn files_list_template(target_username: &str, files_content_html: &str) -> S
 pub fn build_files_list_content_html(
     entries: &[(String, u64, Option<u64>, bool)],
     target_username: &str,
+    current_path: &str,
 ) -> String {
     if entries.is_empty() {
         read_template(FILES_LIST_EMPTY_TEMPLATE_PATH).unwrap_or_else(|e| {
             tracing::warn!("无法读取文件列表空模板: {}", e);
-            "<p>此用户没有上传任何文件</p>".to_string() // Fallback
+            "<p>此目录为空</p>".to_string() // Fallback
         })
     } else {
         match read_template(FILES_LIST_ROW_TEMPLATE_PATH) {
@@ -354,15 +462,34 @@ pub fn build_files_list_content_html(
                         };
 
                         let type_str = if *is_dir { "目录" } else { "文件" };
-                        let download_url = format!("/files/{}/{}", target_username, filename);
+                        let path = if current_path.is_empty() {
+                            filename.clone()
+                        } else {
+                            format!("{}/{}", current_path, filename)
+                        };
+                        let browse_url = files_url(target_username, "browse", &path);
                         let action_cell = if *is_dir {
-                            format!(r#"<a href="{}" class="view-btn">查看目录</a>"#, download_url)
+                            format!(
+                                r#"<a href="{}" class="view-btn">查看目录</a> <a href="{}" class="download-btn">打包下载</a>"#,
+                                browse_url,
+                                files_url(target_username, "raw", &path)
+                            )
                         } else {
-                            format!(r#"<a href="{}" class="download-btn">下载</a>"#, download_url)
+                            format!(
+                                r#"<a href="{}" class="view-btn">查看</a> <a href="{}" class="download-btn">下载</a>"#,
+                                browse_url,
+                                files_url(target_username, "raw", &path)
+                            )
                         };
+                        let name_cell = format!(
+                            r#"<a href="{}">{}{}</a>"#,
+                            browse_url,
+                            html_escape::encode_text(filename),
+                            if *is_dir { "/" } else { "" }
+                        );
 
                         row_template
-                            .replace("{{filename}}", filename)
+                            .replace("{{filename}}", &name_cell)
                             .replace("{{type_str}}", type_str)
                             .replace("{{size_str}}", &size_str)
                             .replace("{{time_str}}", &time_str)
@@ -387,6 +514,88 @@ pub fn build_files_list_content_html(
     }
 }
 
+// 用户目录下某个路径的链接，kind 为 browse（浏览）或 raw（下载，目录打包为 zip），每一段路径分别编码
+pub fn files_url(target_username: &str, kind: &str, path: &str) -> String {
+    let mut url = format!("/files/{}", urlencoding::encode(target_username));
+    if path.is_empty() {
+        return url;
+    }
+    url.push('/');
+    url.push_str(kind);
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        url.push('/');
+        url.push_str(&urlencoding::encode(segment));
+    }
+    url
+}
+
+// 面包屑导航：用户目录 / 子目录 / ...，link_last 为 false 时最后一段不加链接
+fn breadcrumbs_html(target_username: &str, path: &str, link_last: bool) -> String {
+    let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+    let mut crumbs = vec![if segments.is_empty() && !link_last {
+        html_escape::encode_text(target_username).to_string()
+    } else {
+        format!(
+            r#"<a href="{}">{}</a>"#,
+            files_url(target_username, "browse", ""),
+            html_escape::encode_text(target_username)
+        )
+    }];
+    for (i, segment) in segments.iter().enumerate() {
+        if i + 1 == segments.len() && !link_last {
+            crumbs.push(html_escape::encode_text(segment).to_string());
+        } else {
+            crumbs.push(format!(
+                r#"<a href="{}">{}</a>"#,
+                files_url(target_username, "browse", &segments[..=i].join("/")),
+                html_escape::encode_text(segment)
+            ));
+        }
+    }
+    format!(r#"<div class="breadcrumbs">{}</div>"#, crumbs.join(" / "))
+}
+
+// 在线查看文件：content 为 None 时表示二进制或过大的文件
+pub fn file_view_template(target_username: &str, path: &str, size: u64, content: Option<&str>) -> String {
+    let content_html = match content {
+        Some(text) => {
+            let lines = if path.ends_with(".rs") {
+                highlight_source(text)
+            } else {
+                text.lines().map(|line| html_escape::encode_text(line).to_string()).collect()
+            };
+            let rows: String = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    format!(
+                        r##"<tr id="L{0}"><td class="diff-no"><a href="#L{0}">{0}</a></td><td class="diff-code">{1}</td></tr>"##,
+                        i + 1,
+                        line
+                    )
+                })
+                .collect();
+            format!(r#"<table class="diff-table source-view">{}</table>"#, rows)
+        }
+        None => "<p>二进制文件或文件过大，请下载后查看</p>".to_string(),
+    };
+
+    read_template(FILE_VIEW_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{target_username}}", &html_escape::encode_text(target_username))
+                .replace("{{path}}", &html_escape::encode_text(path))
+                .replace("{{breadcrumbs}}", &breadcrumbs_html(target_username, path, false))
+                .replace("{{size}}", &format_size(size))
+                .replace("{{download_url}}", &files_url(target_username, "raw", path))
+                .replace("{{content}}", &content_html)
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取文件查看模板文件: {}", e);
+            "Error loading file view template".to_string()
+        })
+}
+
 // 新增：上传页面模板
 pub fn upload_page_template() -> String {
     read_template(UPLOAD_PAGE_TEMPLATE_PATH).unwrap_or_else(|e| {
@@ -397,15 +606,30 @@ pub fn upload_page_template() -> String {
 }
 
 // 新增：测试结果列表模板
-pub fn test_results_list_template(results_content_html: &str) -> String {
+pub fn test_results_list_template(results_content_html: &str, search_html: &str) -> String {
     read_template(TEST_RESULTS_LIST_TEMPLATE_PATH)
-        .map(|template| template.replace("{{results_content}}", results_content_html))
+        .map(|template| {
+            template
+                .replace("{{search_form}}", search_html)
+                .replace("{{results_content}}", results_content_html)
+        })
         .unwrap_or_else(|e| {
             tracing::error!("无法读取测试结果列表模板文件: {}", e);
             "Error loading test results list template".to_string()
         })
 }
 
+// 管理员按评测环境指纹搜索的表单
+pub fn build_results_search_form(keyword: &str) -> String {
+    format!(
+        r#"<form class="search-form" action="/test_results" method="get">
+            <input type="text" name="env" value="{}" placeholder="按评测环境搜索，如 qemu 版本或测试包哈希">
+            <button type="submit" class="btn">搜索</button>
+        </form>"#,
+        html_escape::encode_double_quoted_attribute(keyword)
+    )
+}
+
 // 新增：构建测试结果列表内容的 HTML
 pub fn build_test_results_content_html(results: &[TestResult]) -> String {
     if results.is_empty() {
@@ -426,7 +650,10 @@ pub fn build_test_results_content_html(results: &[TestResult]) -> String {
                             TestStatus::Failed => "status-failed",
                             TestStatus::Error => "status-error",
                         };
-                        let status_text = format!("{:?}", result.status);
+                        let mut status_text = format!("{:?}", result.status);
+                        if result.cached_from.is_some() {
+                            status_text.push_str(" (缓存)");
+                        }
                         let created_at_str = result.created_at.format("%Y-%m-%d %H:%M:%S").to_string();
 
                         row_template
@@ -455,8 +682,83 @@ pub fn build_test_results_content_html(results: &[TestResult]) -> String {
     }
 }
 
+// 构建用例结果表格的 HTML
+fn build_test_cases_html(cases: &[TestCaseResult]) -> String {
+    if cases.is_empty() {
+        return String::new();
+    }
+
+    let rows_html = cases
+        .iter()
+        .map(|case| {
+            let (verdict_class, verdict_text) = match case.verdict {
+                CaseVerdict::Passed => ("status-passed", "Passed"),
+                CaseVerdict::Failed => ("status-failed", "Failed"),
+                CaseVerdict::TimeLimitExceeded => ("status-error", "TLE"),
+                CaseVerdict::NotRun => ("status-pending", "未运行"),
+            };
+            let exit_code_str = case.exit_code.map_or("-".to_string(), |code| code.to_string());
+            let duration_str = case.duration_ms.map_or("-".to_string(), |ms| format!("{} ms", ms));
+
+            format!(
+                "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td></tr>",
+                html_escape::encode_text(&case.name),
+                verdict_class,
+                verdict_text,
+                exit_code_str,
+                duration_str
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!(
+        "<h2>用例结果</h2><table><thead><tr><th>用例</th><th>结果</th><th>退出码</th><th>耗时</th></tr></thead><tbody>{}</tbody></table>",
+        rows_html
+    )
+}
+
+// 构建评测产物列表的 HTML
+fn build_artifacts_html(test_id: i32, artifacts: &[ArtifactInfo], retention_days: u64) -> String {
+    if artifacts.is_empty() {
+        return String::new();
+    }
+
+    let items_html = artifacts
+        .iter()
+        .map(|artifact| {
+            let file_name = artifact.kind.file_name();
+            format!(
+                r#"<li><a href="/test_results/{}/artifacts/{}" class="download-btn">{}</a> {} ({})</li>"#,
+                test_id,
+                file_name,
+                file_name,
+                artifact.kind.description(),
+                format_size(artifact.size)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let retention_html = if retention_days > 0 {
+        format!("<p>产物保留 {} 天，过期后自动删除（每道题的最好成绩除外）。</p>", retention_days)
+    } else {
+        String::new()
+    };
+
+    format!(
+        r#"<h2>评测产物</h2><ul>{}</ul><p><a href="/test_results/{}/artifacts" class="download-btn">全部打包下载</a></p>{}"#,
+        items_html, test_id, retention_html
+    )
+}
+
 // 新增：测试结果详情模板
-pub fn test_results_detail_template(result: &TestResult) -> String {
+pub fn test_results_detail_template(
+    result: &TestResult,
+    cases: &[TestCaseResult],
+    artifacts: &[ArtifactInfo],
+    retention_days: u64,
+) -> String {
     let status_class = match result.status {
         TestStatus::Pending => "status-pending",
         TestStatus::Running => "status-running",
@@ -471,6 +773,31 @@ pub fn test_results_detail_template(result: &TestResult) -> String {
     let error_section_html = result.error.as_ref().map_or(String::new(), |err| {
         format!("<h2>错误</h2><div class=\"error\">{}</div>", err)
     });
+    let cases_section_html = build_test_cases_html(cases);
+    let fingerprint_section_html = result.env_fingerprint.as_ref().map_or(String::new(), |fingerprint| {
+        format!("<h2>评测环境</h2><div class=\"output\">{}</div>", html_escape::encode_text(fingerprint))
+    });
+    // 未通过的评测提供复现包下载
+    let repro_button_html = if artifacts.iter().any(|a| a.kind == ArtifactKind::Repro) {
+        format!(
+            r#"<p><a href="/test_results/{}/artifacts/{}" class="btn">下载复现包</a> 解压后运行 <code>./repro.sh &lt;工程根目录&gt;</code> 即可在本地重现评测过程</p>"#,
+            result.id,
+            ArtifactKind::Repro.file_name()
+        )
+    } else {
+        String::new()
+    };
+    let artifacts_section_html = build_artifacts_html(result.id, artifacts, retention_days);
+    let cached_notice_html = result.cached_from.map_or(String::new(), |source_id| {
+        format!(
+            r#"<p class="tip-box">提交内容与 <a href="/test_results/{0}">#{0}</a> 相同，已直接复用其评测结果和评测产物（缓存）</p>"#,
+            source_id
+        )
+    });
+    let upload_html = result.upload_id.map_or("-".to_string(), |upload_id| format!("#{}", upload_id));
+    let hang_report_section_html = result.hang_report.as_ref().map_or(String::new(), |report| {
+        format!("<h2>卡死报告</h2><div class=\"output\">{}</div>", html_escape::encode_text(report))
+    });
 
     read_template(TEST_RESULTS_DETAIL_TEMPLATE_PATH)
         .map(|template| {
@@ -479,10 +806,18 @@ pub fn test_results_detail_template(result: &TestResult) -> String {
                 .replace("{{username}}", &result.username)
                 .replace("{{status_class}}", status_class)
                 .replace("{{status}}", &status_text)
+                .replace("{{profile}}", result.profile.as_deref().unwrap_or(crate::tester::DEFAULT_PROFILE))
                 .replace("{{created_at}}", &created_at_str)
                 .replace("{{updated_at}}", &updated_at_str)
                 .replace("{{output}}", output_text)
                 .replace("{{error_section}}", &error_section_html)
+                .replace("{{hang_report_section}}", &hang_report_section_html)
+                .replace("{{cases_section}}", &cases_section_html)
+                .replace("{{artifacts_section}}", &artifacts_section_html)
+                .replace("{{repro_button}}", &repro_button_html)
+                .replace("{{fingerprint_section}}", &fingerprint_section_html)
+                .replace("{{cached_notice}}", &cached_notice_html)
+                .replace("{{upload}}", &upload_html)
         })
         .unwrap_or_else(|e| {
             tracing::error!("无法读取测试结果详情模板文件: {}", e);
@@ -490,6 +825,647 @@ pub fn test_results_detail_template(result: &TestResult) -> String {
         })
 }
 
+// 评测机状态页面模板
+pub fn judge_health_template(health: &HealthSnapshot, message: Option<&str>) -> String {
+    let format_time = |time: Option<chrono::DateTime<chrono::Utc>>| {
+        time.map_or("-".to_string(), |t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+    };
+    let status_html = |ok: bool| {
+        if ok {
+            r#"<span class="status-passed">正常</span>"#
+        } else {
+            r#"<span class="status-failed">异常</span>"#
+        }
+    };
+
+    let check_rows: String = health
+        .checks
+        .iter()
+        .map(|check| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                check.tool.description(),
+                status_html(check.ok),
+                html_escape::encode_text(&check.detail)
+            )
+        })
+        .collect();
+
+    let profile_rows: String = health
+        .profile_checks
+        .iter()
+        .map(|check| {
+            let status = if check.missing.is_empty() {
+                status_html(true).to_string()
+            } else {
+                let missing: Vec<&str> = check.missing.iter().map(|tool| tool.description()).collect();
+                format!("{} 缺少: {}", status_html(false), missing.join("、"))
+            };
+            format!("<tr><td>{}</td><td>{}</td></tr>", html_escape::encode_text(&check.profile), status)
+        })
+        .collect();
+
+    let worker = &health.worker;
+    let running = match worker.running_test {
+        Some(test_id) => format!(
+            "正在评测 <a href=\"/test_results/{0}\">#{0}</a>（开始于 {1}）",
+            test_id,
+            format_time(worker.running_since)
+        ),
+        None => "空闲".to_string(),
+    };
+    let worker_html = format!(
+        "<p><strong>状态:</strong> {}</p><p><strong>排队任务:</strong> {}</p><p><strong>已完成任务:</strong> {}</p><p><strong>最近心跳:</strong> {}</p>",
+        running,
+        health.queue_len,
+        worker.processed,
+        format_time(worker.last_heartbeat)
+    );
+
+    let canary_html = match &health.canary {
+        Some(canary) => {
+            let status = match canary.status {
+                TestStatus::Pending | TestStatus::Running => r#"<span class="status-running">进行中</span>"#,
+                TestStatus::Passed => status_html(true),
+                TestStatus::Failed | TestStatus::Error => status_html(false),
+            };
+            format!(
+                "<p><strong>最近一次:</strong> <a href=\"/test_results/{0}\">#{0}</a> {1}</p><p><strong>完成时间:</strong> {2}</p>",
+                canary.test_id,
+                status,
+                format_time(canary.finished_at)
+            )
+        }
+        None => "<p>尚未运行金丝雀评测</p>".to_string(),
+    };
+
+    let message_html = message.map_or(String::new(), |msg| {
+        format!("<div class=\"success-message\">{}</div>", html_escape::encode_text(msg))
+    });
+
+    read_template(JUDGE_HEALTH_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{message}}", &message_html)
+                .replace("{{checked_at}}", &format_time(health.checked_at))
+                .replace("{{check_rows}}", &check_rows)
+                .replace("{{profile_rows}}", &profile_rows)
+                .replace("{{worker_status}}", &worker_html)
+                .replace("{{canary_status}}", &canary_html)
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取评测机状态模板文件: {}", e);
+            "Error loading judge health template".to_string()
+        })
+}
+
+// 依赖镜像页面模板
+pub fn vendor_crates_template(vendor: &VendorMirror) -> String {
+    let crates_html = if vendor.is_enabled() {
+        let rows: String = vendor
+            .crates()
+            .iter()
+            .map(|krate| {
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    html_escape::encode_text(&krate.name),
+                    html_escape::encode_text(&krate.version),
+                    krate.source.description()
+                )
+            })
+            .collect();
+        format!(
+            "<table><thead><tr><th>名称</th><th>版本</th><th>来源</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        )
+    } else {
+        r#"<div class="error-message">依赖镜像未配置或为空，评测编译时将直接访问网络</div>"#.to_string()
+    };
+
+    read_template(VENDOR_CRATES_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{vendor_dir}}", &html_escape::encode_text(&vendor.dir().display().to_string()))
+                .replace("{{crates_content}}", &crates_html)
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取依赖镜像模板文件: {}", e);
+            "Error loading vendor crates template".to_string()
+        })
+}
+
+// 提交目录结构检查未通过的报告页面
+pub fn layout_report_template(filename: &str, profile: &str, report: &LayoutReport) -> String {
+    let problems: Vec<String> = report
+        .problems
+        .iter()
+        .map(|problem| format!("- {}", problem.replace('\n', "\n    ")))
+        .collect();
+
+    read_template(LAYOUT_REPORT_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{filename}}", &html_escape::encode_text(filename))
+                .replace("{{profile}}", &html_escape::encode_text(profile))
+                .replace("{{problems}}", &html_escape::encode_text(&problems.join("\n")))
+                .replace("{{tree}}", &html_escape::encode_text(&report.tree))
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取提交检查报告模板文件: {}", e);
+            "Error loading layout report template".to_string()
+        })
+}
+
+// 提交对比页面：文件级摘要，以及选中文件的逐行差异
+pub fn submission_diff_template(
+    upload: &UploadRecord,
+    others: &[UploadRecord],
+    against: &str,
+    split: bool,
+    summaries: &[FileSummary],
+    file_diff: Option<(&str, &FileDiff)>,
+) -> String {
+    let username = upload.username.as_deref().unwrap_or("unknown");
+    let mode = if split { "split" } else { "unified" };
+
+    let option = |value: &str, label: &str, selected: bool| {
+        format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            html_escape::encode_double_quoted_attribute(value),
+            if selected { " selected" } else { "" },
+            html_escape::encode_text(label)
+        )
+    };
+    let mut against_options = vec![option("baseline", "本章初始代码", against == "baseline")];
+    for other in others {
+        let label = format!(
+            "上传 #{} {} ({})",
+            other.id,
+            other.filename,
+            other.uploaded_at.format("%Y-%m-%d %H:%M:%S")
+        );
+        against_options.push(option(&other.id.to_string(), &label, against == other.id.to_string()));
+    }
+    let mode_options = [option("unified", "合并视图", !split), option("split", "并排视图", split)].join("");
+
+    let base_url = format!(
+        "/files/{}/diff/{}?against={}&mode={}",
+        urlencoding::encode(username),
+        upload.id,
+        urlencoding::encode(against),
+        mode
+    );
+    let summary_html = if summaries.is_empty() {
+        "<p>两次提交的内容相同</p>".to_string()
+    } else {
+        let rows: String = summaries
+            .iter()
+            .map(|summary| {
+                let stats = if summary.too_large {
+                    "文件过大".to_string()
+                } else if summary.binary {
+                    "二进制文件".to_string()
+                } else {
+                    format!(
+                        r#"<span class="diff-add">+{}</span> <span class="diff-del">-{}</span>"#,
+                        summary.additions, summary.deletions
+                    )
+                };
+                format!(
+                    r#"<tr><td><a href="{}&file={}">{}</a></td><td>{}</td><td>{}</td></tr>"#,
+                    base_url,
+                    urlencoding::encode(&summary.path),
+                    html_escape::encode_text(&summary.path),
+                    summary.change.description(),
+                    stats
+                )
+            })
+            .collect();
+        let (additions, deletions) = summaries
+            .iter()
+            .fold((0, 0), |(a, d), summary| (a + summary.additions, d + summary.deletions));
+        format!(
+            r#"<p>共 {} 个文件，<span class="diff-add">+{}</span> <span class="diff-del">-{}</span></p>
+<table class="file-table"><thead><tr><th>文件</th><th>变更</th><th>行数</th></tr></thead><tbody>{}</tbody></table>"#,
+            summaries.len(),
+            additions,
+            deletions,
+            rows
+        )
+    };
+
+    let file_diff_html = match file_diff {
+        Some((path, diff)) => {
+            let body = match diff {
+                FileDiff::Binary => "<p>二进制文件，不显示差异</p>".to_string(),
+                FileDiff::TooLarge => "<p>文件过大，不显示差异</p>".to_string(),
+                FileDiff::Text(hunks) if split => render_split_diff(hunks),
+                FileDiff::Text(hunks) => render_unified_diff(hunks),
+            };
+            format!("<h2>{}</h2>{}", html_escape::encode_text(path), body)
+        }
+        None => String::new(),
+    };
+
+    read_template(SUBMISSION_DIFF_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{username}}", &html_escape::encode_text(username))
+                .replace("{{upload_id}}", &upload.id.to_string())
+                .replace("{{filename}}", &html_escape::encode_text(&upload.filename))
+                .replace("{{against_options}}", &against_options.join("\n"))
+                .replace("{{mode_options}}", &mode_options)
+                .replace("{{summary}}", &summary_html)
+                .replace("{{file_diff}}", &file_diff_html)
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取提交对比模板文件: {}", e);
+            "Error loading submission diff template".to_string()
+        })
+}
+
+fn line_no(no: Option<usize>) -> String {
+    no.map_or(String::new(), |no| no.to_string())
+}
+
+// 合并视图：每行显示新旧行号和 +/- 标记
+fn render_unified_diff(hunks: &[Hunk]) -> String {
+    let mut rows = String::new();
+    for hunk in hunks {
+        rows.push_str(&format!(
+            r#"<tr class="diff-hunk"><td colspan="4">{}</td></tr>"#,
+            html_escape::encode_text(&hunk.header)
+        ));
+        for line in &hunk.lines {
+            let (class, sign) = match line.tag {
+                LineTag::Equal => ("", " "),
+                LineTag::Delete => ("diff-line-del", "-"),
+                LineTag::Insert => ("diff-line-add", "+"),
+            };
+            rows.push_str(&format!(
+                r#"<tr class="{}"><td class="diff-no">{}</td><td class="diff-no">{}</td><td class="diff-sign">{}</td><td class="diff-code">{}</td></tr>"#,
+                class,
+                line_no(line.old_no),
+                line_no(line.new_no),
+                sign,
+                highlight_line(&line.text)
+            ));
+        }
+    }
+    format!(r#"<table class="diff-table">{}</table>"#, rows)
+}
+
+// 并排视图：连续的删除行和新增行逐行配对显示在左右两侧
+fn render_split_diff(hunks: &[Hunk]) -> String {
+    let side = |line: Option<&DiffLine>, no: fn(&DiffLine) -> Option<usize>| match line {
+        Some(line) => {
+            let class = match line.tag {
+                LineTag::Equal => "",
+                LineTag::Delete => "diff-line-del",
+                LineTag::Insert => "diff-line-add",
+            };
+            format!(
+                r#"<td class="diff-no {0}">{1}</td><td class="diff-code {0}">{2}</td>"#,
+                class,
+                line_no(no(line)),
+                highlight_line(&line.text)
+            )
+        }
+        None => r#"<td class="diff-no diff-empty"></td><td class="diff-code diff-empty"></td>"#.to_string(),
+    };
+
+    let mut rows = String::new();
+    for hunk in hunks {
+        rows.push_str(&format!(
+            r#"<tr class="diff-hunk"><td colspan="4">{}</td></tr>"#,
+            html_escape::encode_text(&hunk.header)
+        ));
+        let mut i = 0;
+        while i < hunk.lines.len() {
+            if hunk.lines[i].tag == LineTag::Equal {
+                let line = &hunk.lines[i];
+                rows.push_str(&format!("<tr>{}{}</tr>", side(Some(line), |l| l.old_no), side(Some(line), |l| l.new_no)));
+                i += 1;
+                continue;
+            }
+            let deleted: Vec<&DiffLine> = hunk.lines[i..].iter().take_while(|l| l.tag == LineTag::Delete).collect();
+            i += deleted.len();
+            let inserted: Vec<&DiffLine> = hunk.lines[i..].iter().take_while(|l| l.tag == LineTag::Insert).collect();
+            i += inserted.len();
+            for row in 0..deleted.len().max(inserted.len()) {
+                rows.push_str(&format!(
+                    "<tr>{}{}</tr>",
+                    side(deleted.get(row).copied(), |l| l.old_no),
+                    side(inserted.get(row).copied(), |l| l.new_no)
+                ));
+            }
+        }
+    }
+    format!(r#"<table class="diff-table diff-split">{}</table>"#, rows)
+}
+
+// 代码相似度分析页面：分析状态和按相似度排序的提交对
+pub fn similarity_template(profiles: &[String], profile: &str, analysis: Option<&AnalysisState>) -> String {
+    let profile_options: String = profiles
+        .iter()
+        .map(|name| {
+            format!(
+                r#"<option value="{0}"{1}>{0}</option>"#,
+                html_escape::encode_double_quoted_attribute(name),
+                if name == profile { " selected" } else { "" }
+            )
+        })
+        .collect();
+    let profile_param = urlencoding::encode(profile).to_string();
+
+    let (status_html, report_html) = match analysis {
+        None => ("<p>尚未分析该题目的提交</p>".to_string(), String::new()),
+        Some(AnalysisState::Running { started_at, submissions }) => (
+            format!(
+                r#"<p><span class="status-running">分析中</span> 共 {} 份提交，开始于 {}，请稍后刷新页面</p>"#,
+                submissions,
+                started_at.format("%Y-%m-%d %H:%M:%S")
+            ),
+            String::new(),
+        ),
+        Some(AnalysisState::Failed(reason)) => (
+            format!(
+                r#"<div class="error-message">分析失败: {}</div>"#,
+                html_escape::encode_text(reason)
+            ),
+            String::new(),
+        ),
+        Some(AnalysisState::Done(report)) => (
+            format!(
+                r#"<p>分析完成于 {}，比较了 {} 份提交的 {}，已排除初始代码中的片段。<a href="/admin/similarity/export?profile={}">导出 CSV</a></p>"#,
+                report.finished_at.format("%Y-%m-%d %H:%M:%S"),
+                report.submissions.len(),
+                html_escape::encode_text(&report.source_dir),
+                profile_param
+            ),
+            similarity_pairs_html(report, &profile_param),
+        ),
+    };
+
+    read_template(SIMILARITY_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{profile_options}}", &profile_options)
+                .replace("{{profile}}", &html_escape::encode_double_quoted_attribute(profile))
+                .replace("{{status}}", &status_html)
+                .replace("{{report}}", &report_html)
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取相似度分析模板文件: {}", e);
+            "Error loading similarity template".to_string()
+        })
+}
+
+fn similarity_pairs_html(report: &SimilarityReport, profile_param: &str) -> String {
+    if report.pairs.is_empty() {
+        return "<p>没有发现相同的代码片段</p>".to_string();
+    }
+    let rows: String = report
+        .pairs
+        .iter()
+        .map(|pair| {
+            let a = &report.submissions[pair.a];
+            let b = &report.submissions[pair.b];
+            format!(
+                r#"<tr><td>{:.1}%</td><td>{} (#{})</td><td>{} (#{})</td><td>{} / {} / {}</td><td>{}</td><td><a href="/admin/similarity/pair?profile={}&a={}&b={}">查看</a></td></tr>"#,
+                pair.similarity * 100.0,
+                html_escape::encode_text(&a.username),
+                a.upload_id,
+                html_escape::encode_text(&b.username),
+                b.upload_id,
+                pair.shared,
+                report.fingerprint_counts[pair.a],
+                report.fingerprint_counts[pair.b],
+                pair.regions.len(),
+                profile_param,
+                a.upload_id,
+                b.upload_id
+            )
+        })
+        .collect();
+    format!(
+        r#"<table><thead><tr><th>相似度</th><th>提交 A</th><th>提交 B</th><th>相同指纹 / A / B</th><th>相同片段</th><th>操作</th></tr></thead><tbody>{}</tbody></table>"#,
+        rows
+    )
+}
+
+// 并排显示两份提交中相同的代码片段，sources 与 pair.regions 一一对应
+pub fn similarity_pair_template(report: &SimilarityReport, pair: &PairReport, sources: &[(Vec<String>, Vec<String>)]) -> String {
+    let a = &report.submissions[pair.a];
+    let b = &report.submissions[pair.b];
+    let side = |lines: &[String], first_line: usize| -> String {
+        let rows: String = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                format!(
+                    r#"<tr><td class="diff-no">{}</td><td class="diff-code">{}</td></tr>"#,
+                    first_line + i,
+                    highlight_line(line)
+                )
+            })
+            .collect();
+        format!(r#"<table class="diff-table">{}</table>"#, rows)
+    };
+
+    let regions_html = if pair.regions.is_empty() {
+        "<p>没有可显示的相同片段</p>".to_string()
+    } else {
+        pair.regions
+            .iter()
+            .zip(sources)
+            .map(|(region, (lines_a, lines_b))| {
+                format!(
+                    r#"<div class="similarity-region"><div class="similarity-side"><h3>{} 第 {}-{} 行</h3>{}</div><div class="similarity-side"><h3>{} 第 {}-{} 行</h3>{}</div></div>"#,
+                    html_escape::encode_text(&region.file_a),
+                    region.lines_a.0,
+                    region.lines_a.1,
+                    side(lines_a, region.lines_a.0),
+                    html_escape::encode_text(&region.file_b),
+                    region.lines_b.0,
+                    region.lines_b.1,
+                    side(lines_b, region.lines_b.0)
+                )
+            })
+            .collect()
+    };
+
+    read_template(SIMILARITY_PAIR_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{profile}}", &html_escape::encode_text(&report.profile))
+                .replace("{{profile_param}}", &urlencoding::encode(&report.profile))
+                .replace("{{user_a}}", &html_escape::encode_text(&a.username))
+                .replace("{{upload_a}}", &a.upload_id.to_string())
+                .replace("{{user_b}}", &html_escape::encode_text(&b.username))
+                .replace("{{upload_b}}", &b.upload_id.to_string())
+                .replace("{{similarity}}", &format!("{:.1}%", pair.similarity * 100.0))
+                .replace("{{shared}}", &pair.shared.to_string())
+                .replace("{{regions}}", &regions_html)
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取相似片段模板文件: {}", e);
+            "Error loading similarity pair template".to_string()
+        })
+}
+
+// 存储清理页面：当前规则和最近一次清理（或试运行）的报告
+pub fn retention_template(policy: &RetentionPolicy, report: Option<&RetentionReport>, running: bool) -> String {
+    let keep_latest = if policy.keep_latest > 0 {
+        format!("每个学生每道题保留最近 {} 次上传，最好成绩对应的上传总是保留", policy.keep_latest)
+    } else {
+        "保留所有上传".to_string()
+    };
+    let build_outputs = if policy.purge_build_outputs {
+        "评测结束后删除提交中 os 和 user 的 target 目录"
+    } else {
+        "保留提交中的编译产物"
+    };
+    let artifacts = if policy.artifact_days > 0 {
+        format!("评测产物保留 {} 天，每个学生每道题的最好成绩总是保留", policy.artifact_days)
+    } else {
+        "永久保留评测产物".to_string()
+    };
+    let policy_html = format!(
+        "<ul><li>{}</li><li>{}</li><li>{}</li><li>每 {} 小时自动清理一次{}</li></ul>",
+        keep_latest,
+        build_outputs,
+        artifacts,
+        policy.interval.as_secs() / 3600,
+        if policy.dry_run { "，自动清理只生成报告、不删除文件" } else { "" }
+    );
+
+    let running_html = if running {
+        r#"<p><span class="status-running">清理中</span> 请稍后刷新页面</p>"#
+    } else {
+        ""
+    };
+    let (status_html, report_html) = match report {
+        None => (format!("{}<p>尚未清理过</p>", running_html), String::new()),
+        Some(report) => (
+            format!(
+                "{}<p>{}{}完成于 {}，{}共 {} 个上传、{} 个上传的编译产物、{} 个评测产物目录，{} {}。{}</p>",
+                running_html,
+                if report.scheduled { "定时" } else { "手动" },
+                if report.dry_run { "试运行" } else { "清理" },
+                report.finished_at.format("%Y-%m-%d %H:%M:%S"),
+                if report.dry_run { "将要删除" } else { "删除了" },
+                report.uploads.len(),
+                report.build_outputs.len(),
+                report.artifacts.len(),
+                if report.dry_run { "预计释放" } else { "释放" },
+                format_size(report.total_size()),
+                if report.kept_best > 0 {
+                    format!("另有 {} 个过期的评测产物目录是最好成绩，已保留。", report.kept_best)
+                } else {
+                    String::new()
+                }
+            ),
+            retention_report_html(report),
+        ),
+    };
+
+    read_template(RETENTION_TEMPLATE_PATH)
+        .map(|template| {
+            template
+                .replace("{{policy}}", &policy_html)
+                .replace("{{status}}", &status_html)
+                .replace("{{report}}", &report_html)
+        })
+        .unwrap_or_else(|e| {
+            tracing::error!("无法读取存储清理模板文件: {}", e);
+            "Error loading retention template".to_string()
+        })
+}
+
+fn retention_report_html(report: &RetentionReport) -> String {
+    let mut html = String::new();
+    if !report.errors.is_empty() {
+        let items: String = report
+            .errors
+            .iter()
+            .map(|e| format!("<li>{}</li>", html_escape::encode_text(e)))
+            .collect();
+        html.push_str(&format!(r#"<div class="error-message"><ul>{}</ul></div>"#, items));
+    }
+    if !report.uploads.is_empty() {
+        let rows: String = report
+            .uploads
+            .iter()
+            .map(|upload| {
+                format!(
+                    "<tr><td>#{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    upload.id,
+                    html_escape::encode_text(upload.username.as_deref().unwrap_or("-")),
+                    html_escape::encode_text(upload.test_profile.as_deref().unwrap_or("-")),
+                    html_escape::encode_text(&upload.filename),
+                    upload.uploaded_at.format("%Y-%m-%d %H:%M:%S"),
+                    format_size(upload.file_size.max(0) as u64)
+                )
+            })
+            .collect();
+        html.push_str(&format!(
+            "<h3>旧的上传</h3><table><thead><tr><th>上传</th><th>用户</th><th>题目</th><th>文件名</th><th>上传时间</th><th>大小</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        ));
+    }
+    if !report.build_outputs.is_empty() {
+        let rows: String = report
+            .build_outputs
+            .iter()
+            .map(|outputs| {
+                let dirs: Vec<String> = outputs.dirs.iter().map(|dir| html_escape::encode_text(dir).into_owned()).collect();
+                format!(
+                    "<tr><td>#{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    outputs.upload_id,
+                    html_escape::encode_text(&outputs.username),
+                    dirs.join("<br>"),
+                    outputs.files,
+                    format_size(outputs.size)
+                )
+            })
+            .collect();
+        html.push_str(&format!(
+            "<h3>编译产物</h3><table><thead><tr><th>上传</th><th>用户</th><th>目录</th><th>文件数</th><th>大小</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        ));
+    }
+    if !report.artifacts.is_empty() {
+        let rows: String = report
+            .artifacts
+            .iter()
+            .map(|(test_id, size)| {
+                format!(
+                    r#"<tr><td><a href="/test_results/{0}">#{0}</a></td><td>{1}</td></tr>"#,
+                    test_id,
+                    format_size(*size)
+                )
+            })
+            .collect();
+        html.push_str(&format!(
+            "<h3>过期的评测产物</h3><table><thead><tr><th>评测</th><th>大小</th></tr></thead><tbody>{}</tbody></table>",
+            rows
+        ));
+    }
+    html
+}
+
+// 辅助函数：格式化文件大小
+fn format_size(size: u64) -> String {
+    if size < 1024 {
+        format!("{} B", size)
+    } else if size < 1024 * 1024 {
+        format!("{:.2} KB", size as f64 / 1024.0)
+    } else {
+        format!("{:.2} MB", size as f64 / (1024.0 * 1024.0))
+    }
+}
+
 // 辅助函数：读取模板文件内容
 fn read_template(path: &str) -> Result<String, std::io::Error> {
     fs::read_to_string(path)
@@ -508,4 +1484,80 @@ fn render_message_template(message_class: &str, message_text: &str) -> String {
             // Fallback to simple div
             format!("<div class=\"{}\">{}</div>", message_class, message_text)
         })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(id: i32, test: Option<(i32, &str)>) -> UploadRecord {
+        UploadRecord {
+            id,
+            filename: "os.zip".to_string(),
+            file_path: format!("uploads/alice/{}/os.zip", id),
+            file_size: 2048,
+            uploaded_at: chrono::Utc::now(),
+            username: Some("alice".to_string()),
+            project_dir: None,
+            test_id: test.map(|(test_id, _)| test_id),
+            test_status: test.map(|(_, status)| status.to_string()),
+            test_profile: test.map(|_| "ch3".to_string()),
+        }
+    }
+
+    #[test]
+    fn artifact_list_links_each_kind() {
+        let artifacts = [
+            ArtifactInfo { kind: ArtifactKind::Kernel, size: 4096 },
+            ArtifactInfo { kind: ArtifactKind::Transcript, size: 10 },
+        ];
+        let html = build_artifacts_html(5, &artifacts, 14);
+
+        assert!(html.contains(r#"<a href="/test_results/5/artifacts/os.elf" class="download-btn">os.elf</a> 内核ELF (4.00 KB)"#));
+        assert!(html.contains("/test_results/5/artifacts/transcript.txt"));
+        assert!(html.contains("产物保留 14 天"));
+        assert!(build_artifacts_html(5, &[], 14).is_empty());
+    }
+
+    #[test]
+    fn cached_result_links_its_source_and_lists_copied_artifacts() {
+        let now = chrono::Utc::now();
+        let result = TestResult {
+            id: 9,
+            user_id: 1,
+            username: "alice".to_string(),
+            status: TestStatus::Passed,
+            profile: Some("ch3".to_string()),
+            output: Some("[ok] ch3_sleep".to_string()),
+            error: None,
+            hang_report: None,
+            env_fingerprint: None,
+            cached_from: Some(4),
+            upload_id: Some(7),
+            created_at: now,
+            updated_at: now,
+        };
+        let artifacts = [ArtifactInfo { kind: ArtifactKind::Transcript, size: 14 }];
+        let html = test_results_detail_template(&result, &[], &artifacts, 14);
+
+        assert!(html.contains(r#"<a href="/test_results/4">#4</a>"#));
+        assert!(html.contains("/test_results/9/artifacts/transcript.txt"));
+    }
+
+    #[test]
+    fn uploads_list_shows_verdict_of_latest_test() {
+        let user = User {
+            id: 1,
+            username: "alice".to_string(),
+            password: String::new(),
+            role: UserRole::Regular,
+            quota_mb: None,
+        };
+        let html = uploads_template(&user, &[upload(7, Some((42, "Passed"))), upload(8, None)]);
+
+        assert!(html.contains(r#"<a href="/test_results/42" class="status-passed">Passed</a> ch3"#));
+        assert!(html.contains("/uploads/7/delete"));
+        assert!(html.contains("/uploads/8/rename"));
+        assert!(!html.contains("{{"));
+    }
+}
@@ -0,0 +1,388 @@
+use super::copy_dir_recursively;
+use super::vendor::VendorMirror;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+// 需要预先下载依赖的crate（相对于学生工程根目录）
+const FETCH_CRATES: [&str; 2] = ["os", "easy-fs-fuse"];
+
+// 共享的cargo缓存中可以链接给评测使用的部分（不含解压后的源码）
+const CARGO_HOME_SHARED_DIRS: [&str; 3] = ["registry/index", "registry/cache", "git/db"];
+
+// 去掉编译产物中的绝对路径（调试信息和 panic 位置中的工程目录、cargo目录），
+// 同一份测试包在评测机和学生工程的不同目录下编译出的测试程序才会逐字节相同，fs.img 的校验依赖这一点。
+// trim-paths 目前是 nightly 功能，评测使用的工具链是 nightly
+pub const TRIM_PATHS_ENV: [(&str, &str); 3] = [
+    ("CARGO_UNSTABLE_TRIM_PATHS", "true"),
+    ("CARGO_PROFILE_RELEASE_TRIM_PATHS", "all"),
+    ("CARGO_PROFILE_DEV_TRIM_PATHS", "all"),
+];
+
+// 评测之间共享的构建缓存，只用于加快编译
+//
+// 共享缓存由评测机写入：依赖通过 cargo fetch 下载（不会执行学生代码），
+// user crate 由评测机用原始测试包预先编译，评测时复制一份私有副本使用。
+// 但学生的 build.rs 和内核与评测机以同一用户运行、没有沙箱，仍然可以直接修改缓存目录，
+// 所以缓存中的内容不可信，只能用来加快编译；需要可信的测试程序时见 trusted_user_build。
+pub struct BuildCache {
+    root: PathBuf,
+    enabled: bool,
+    rustc_wrapper: Option<String>,
+    // 预编译 user crate 时避免重复编译同一份测试包
+    warm_lock: Mutex<()>,
+    // 测试包目录的内容哈希。与环境指纹一样，评测机运行期间测试包视为不变，每个测试包只计算一次
+    bundle_hashes: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl BuildCache {
+    // 从环境变量读取配置：
+    // JUDGE_CACHE_DIR      缓存根目录，默认 judge_cache
+    // JUDGE_BUILD_CACHE    设为 0 时关闭构建缓存
+    // JUDGE_RUSTC_WRAPPER  可选的编译缓存程序（如 sccache），作为 RUSTC_WRAPPER 传给cargo
+    pub fn from_env() -> Self {
+        Self {
+            root: PathBuf::from(std::env::var("JUDGE_CACHE_DIR").unwrap_or_else(|_| "judge_cache".to_string())),
+            enabled: std::env::var("JUDGE_BUILD_CACHE").map_or(true, |v| v != "0"),
+            rustc_wrapper: std::env::var("JUDGE_RUSTC_WRAPPER").ok().filter(|v| !v.is_empty()),
+            warm_lock: Mutex::new(()),
+            bundle_hashes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // 每个工具链使用独立的cargo目录
+    fn shared_cargo_home(&self, toolchain: &str) -> PathBuf {
+        self.root.join("cargo").join(toolchain)
+    }
+
+    // 为一次评测准备构建环境，返回编译时需要设置的环境变量。
+    // run_dir 是本次评测的私有目录，评测结束后由调用者删除
+    pub async fn prepare(
+        &self,
+        work_dir: &Path,
+        run_dir: &Path,
+        toolchain: &str,
+        bundle_dir: &Path,
+        make_args: &[String],
+        vendor: &VendorMirror,
+    ) -> Vec<(String, String)> {
+        let envs = self.cargo_env(work_dir, run_dir, toolchain, vendor).await;
+        if !self.enabled {
+            return envs;
+        }
+
+        // 用预先编译好的 user crate 替换学生工程中的测试包
+        let shared_home = self.shared_cargo_home(toolchain);
+        let bundle_hash = self.bundle_hash(bundle_dir).await;
+        match self.warm_user_build(bundle_dir, &bundle_hash, &shared_home, toolchain, make_args).await {
+            Ok(warm_dir) => {
+                let target = work_dir.to_path_buf();
+                let result = tokio::task::spawn_blocking(move || {
+                    copy_dir_recursively(&warm_dir.to_string_lossy(), &target)?;
+                    // 复制会更新修改时间，再复制一次 target 使编译产物比源码新，cargo 才会认为无需重新编译
+                    let user_dir = target.join(warm_dir.file_name().unwrap_or_default());
+                    copy_dir_recursively(&warm_dir.join("target").to_string_lossy(), &user_dir)
+                })
+                .await;
+                match result {
+                    Ok(Ok(())) => tracing::debug!("已使用预编译的测试包: {}", work_dir.display()),
+                    Ok(Err(e)) => tracing::warn!("无法复制预编译的测试包: {}", e),
+                    Err(e) => tracing::warn!("复制预编译测试包的任务异常退出: {}", e),
+                }
+            }
+            Err(e) => tracing::warn!("无法预编译测试包，将在评测中完整编译: {}", e),
+        }
+
+        envs
+    }
+
+    async fn bundle_hash(&self, bundle_dir: &Path) -> String {
+        let mut hashes = self.bundle_hashes.lock().await;
+        if let Some(hash) = hashes.get(bundle_dir) {
+            return hash.clone();
+        }
+        let dir = bundle_dir.to_path_buf();
+        let hash = tokio::task::spawn_blocking(move || crate::digest::hash_dir(&dir, &["target"]))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r.map_err(|e| e.to_string()));
+        match hash {
+            Ok(hash) => {
+                hashes.insert(bundle_dir.to_path_buf(), hash.clone());
+                hash
+            }
+            Err(e) => {
+                tracing::warn!("无法计算测试包的哈希: {}", e);
+                String::new()
+            }
+        }
+    }
+
+    // 准备本次评测使用的cargo目录，返回需要设置的环境变量。
+    // 有依赖镜像时只从镜像读取依赖；否则用共享的cargo目录下载依赖，再以硬链接建立私有的cargo目录给本次评测使用
+    pub async fn cargo_env(
+        &self,
+        work_dir: &Path,
+        run_dir: &Path,
+        toolchain: &str,
+        vendor: &VendorMirror,
+    ) -> Vec<(String, String)> {
+        let mut envs: Vec<(String, String)> =
+            TRIM_PATHS_ENV.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        if let Some(wrapper) = self.rustc_wrapper.as_ref().filter(|_| self.enabled) {
+            envs.push(("RUSTC_WRAPPER".to_string(), wrapper.clone()));
+        }
+
+        let shared_home = self.shared_cargo_home(toolchain);
+        let private_home = run_dir.join("cargo-home");
+        let seeded = if vendor.is_enabled() {
+            write_vendor_config(&shared_home, vendor)
+                .await
+                .and(write_vendor_config(&private_home, vendor).await)
+                .map(|_| true)
+        } else if self.enabled {
+            let fetched = self.fetch_dependencies(work_dir, &shared_home, toolchain).await;
+            seed_cargo_home(&shared_home, &private_home).await.map(|_| fetched)
+        } else {
+            return envs;
+        };
+        match seeded {
+            Ok(offline) => {
+                envs.push(("CARGO_HOME".to_string(), private_home.to_string_lossy().to_string()));
+                if offline {
+                    // 依赖已全部下载，编译时不再访问网络
+                    envs.push(("CARGO_NET_OFFLINE".to_string(), "true".to_string()));
+                }
+            }
+            Err(e) => tracing::warn!("无法准备私有cargo目录，本次评测不使用依赖缓存: {}", e),
+        }
+
+        envs
+    }
+
+    // 使用共享的cargo目录下载学生工程的依赖。
+    // 在缓存目录下执行，避免读取学生工程中的 .cargo/config
+    async fn fetch_dependencies(&self, work_dir: &Path, shared_home: &Path, toolchain: &str) -> bool {
+        if let Err(e) = tokio::fs::create_dir_all(shared_home).await {
+            tracing::warn!("无法创建共享cargo目录: {}", e);
+            return false;
+        }
+
+        let mut all_ok = true;
+        for name in FETCH_CRATES {
+            let manifest = work_dir.join(name).join("Cargo.toml");
+            if !manifest.exists() {
+                continue;
+            }
+            let manifest = match manifest.canonicalize() {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+            let output = Command::new("cargo")
+                .arg("fetch")
+                .arg("--manifest-path")
+                .arg(&manifest)
+                .env("RUSTUP_TOOLCHAIN", toolchain)
+                .env("CARGO_HOME", absolute(shared_home))
+                .current_dir(&self.root)
+                .stdin(Stdio::null())
+                .output()
+                .await;
+            match output {
+                Ok(output) if output.status.success() => {}
+                Ok(output) => {
+                    tracing::warn!(
+                        "下载 {} 的依赖失败: {}",
+                        name,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                    all_ok = false;
+                }
+                Err(e) => {
+                    tracing::warn!("无法运行 cargo fetch: {}", e);
+                    all_ok = false;
+                }
+            }
+        }
+        all_ok
+    }
+
+    // 评测机在 out_dir 中用原始测试包重新编译的 user 目录，其中的测试程序不经过学生代码。
+    // 不使用共享缓存中预编译的 user crate，也不使用评测时复制的cargo目录：这些都可能被之前的评测修改过。
+    // 必须在本次评测运行学生代码之前调用；同时进行的其他评测与评测机是同一用户，仍能写入 out_dir，
+    // 完全隔离需要以单独的用户运行评测。out_dir 由调用者删除
+    pub async fn trusted_user_build(
+        &self,
+        bundle_dir: &Path,
+        toolchain: &str,
+        make_args: &[String],
+        vendor: &VendorMirror,
+        out_dir: &Path,
+    ) -> Result<PathBuf, String> {
+        let _ = tokio::fs::remove_dir_all(out_dir).await;
+        let cargo_home = out_dir.join("cargo-home");
+        if vendor.is_enabled() {
+            write_vendor_config(&cargo_home, vendor).await?;
+        } else {
+            // 只链接下载的压缩包和索引，cargo 解压时会按 Cargo.lock 校验压缩包
+            seed_cargo_home(&self.shared_cargo_home(toolchain), &cargo_home).await?;
+        }
+        copy_bundle(bundle_dir, out_dir).await?;
+        let user_dir = out_dir.join(bundle_dir.file_name().unwrap_or_default());
+        make_user(&user_dir, &cargo_home, toolchain, make_args).await?;
+        Ok(user_dir)
+    }
+
+    // 获取与测试包版本、工具链和编译参数对应的预编译 user 目录，不存在时先编译
+    async fn warm_user_build(
+        &self,
+        bundle_dir: &Path,
+        bundle_hash: &str,
+        shared_home: &Path,
+        toolchain: &str,
+        make_args: &[String],
+    ) -> Result<PathBuf, String> {
+        let key = format!("{}-{}-{}", toolchain, &bundle_hash[..bundle_hash.len().min(16)], make_args.join("_"));
+        let entry = self.root.join("user").join(&key);
+        let warm_dir = entry.join(bundle_dir.file_name().unwrap_or_default());
+
+        let _guard = self.warm_lock.lock().await;
+        if warm_dir.exists() {
+            return Ok(warm_dir);
+        }
+
+        // 在临时目录中编译，成功后再移动到最终位置，避免留下编译了一半的缓存
+        let staging = self.root.join("user").join(format!("{}.tmp", key));
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        tokio::fs::create_dir_all(&staging).await.map_err(|e| e.to_string())?;
+        copy_bundle(bundle_dir, &staging).await?;
+        let staged_user = staging.join(bundle_dir.file_name().unwrap_or_default());
+        if let Err(e) = make_user(&staged_user, shared_home, toolchain, make_args).await {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            return Err(e);
+        }
+
+        tokio::fs::rename(&staging, &entry).await.map_err(|e| e.to_string())?;
+        tracing::info!("已预编译测试包: {}", key);
+        Ok(warm_dir)
+    }
+}
+
+// 把测试包复制到 dst 目录下
+async fn copy_bundle(bundle_dir: &Path, dst: &Path) -> Result<(), String> {
+    tokio::fs::create_dir_all(dst).await.map_err(|e| e.to_string())?;
+    let src = bundle_dir.to_path_buf();
+    let dst = dst.to_path_buf();
+    tokio::task::spawn_blocking(move || copy_dir_recursively(&src.to_string_lossy(), &dst))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("无法复制测试包: {}", e))
+}
+
+// 在 user 目录中编译测试程序
+async fn make_user(user_dir: &Path, cargo_home: &Path, toolchain: &str, make_args: &[String]) -> Result<(), String> {
+    let output = Command::new("make")
+        .args(make_args)
+        .envs(TRIM_PATHS_ENV)
+        .env("RUSTUP_TOOLCHAIN", toolchain)
+        .env("CARGO_HOME", absolute(cargo_home))
+        .current_dir(user_dir)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("无法运行make: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("编译测试包失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// 把共享cargo目录中已下载的内容链接到私有目录，解压和编译都在私有目录中进行
+async fn seed_cargo_home(shared_home: &Path, private_home: &Path) -> Result<(), String> {
+    tokio::fs::create_dir_all(private_home).await.map_err(|e| e.to_string())?;
+    for dir in CARGO_HOME_SHARED_DIRS {
+        let src = shared_home.join(dir);
+        if !src.exists() {
+            continue;
+        }
+        let dst = private_home.join(dir);
+        tokio::task::spawn_blocking(move || link_tree(&src, &dst, false))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("无法链接 {}: {}", dir, e))?;
+    }
+    Ok(())
+}
+
+// 以硬链接的形式在 dst 下重建 src 目录树，不在同一文件系统时复制。
+// 下载的压缩包和 git 对象写入后不再修改，可以共享同一份文件；
+// 索引 .cache 目录中的文件会被cargo原地改写，总是复制
+fn link_tree(src: &Path, dst: &Path, copy: bool) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            link_tree(&from, &to, copy || entry.file_name() == ".cache")?;
+        } else if file_type.is_file() && (copy || std::fs::hard_link(&from, &to).is_err()) {
+            std::fs::copy(&from, &to)?;
+        }
+    }
+    Ok(())
+}
+
+// 在cargo目录中写入使用依赖镜像的配置
+async fn write_vendor_config(cargo_home: &Path, vendor: &VendorMirror) -> Result<(), String> {
+    tokio::fs::create_dir_all(cargo_home).await.map_err(|e| e.to_string())?;
+    tokio::fs::write(cargo_home.join("config.toml"), vendor.cargo_config())
+        .await
+        .map_err(|e| format!("无法写入cargo配置: {}", e))
+}
+
+// cargo 在其他目录下执行时需要绝对路径
+pub fn absolute(path: &Path) -> PathBuf {
+    std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    #[tokio::test]
+    async fn seeds_private_cargo_home_with_links() {
+        let base = std::env::temp_dir().join(format!("cargo_home_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let (shared, private) = (base.join("shared"), base.join("private"));
+        let files = [
+            "registry/cache/index.crates.io-6f17d22bba15001f/log-0.4.14.crate",
+            "registry/index/index.crates.io-6f17d22bba15001f/.cache/lo/g/log",
+            "git/db/riscv-0123456789abcdef/objects/pack/pack-1.pack",
+            "registry/src/index.crates.io-6f17d22bba15001f/log-0.4.14/src/lib.rs",
+        ];
+        for file in files {
+            let path = shared.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+
+        seed_cargo_home(&shared, &private).await.unwrap();
+
+        let same_file = |file: &str| {
+            let ino = |root: &Path| std::fs::metadata(root.join(file)).unwrap().ino();
+            ino(&shared) == ino(&private)
+        };
+        assert!(same_file(files[0]));
+        assert!(same_file(files[2]));
+        // 索引缓存会被原地改写，私有目录中是独立的副本
+        assert!(!same_file(files[1]));
+        assert_eq!(std::fs::read_to_string(private.join(files[1])).unwrap(), files[1]);
+        // 解压后的源码不共享
+        assert!(!private.join("registry/src").exists());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+use super::copy_dir_recursively;
+use crate::models::{CaseVerdict, TestCaseResult};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::Instant;
+
+// 评测机自带的 easy-fs 测试集，不使用学生提交的任何测试代码
+pub const SUITE_DIR: &str = "judge_tests/easy-fs";
+const CARGO_TEMPLATE: &str = "Cargo.toml.template";
+
+// 学生工程中 easy-fs crate 的位置
+pub const EASY_FS_DIR: &str = "easy-fs";
+
+// 一次测试集运行的结果
+pub struct SuiteReport {
+    pub build_ok: bool,
+    pub timed_out: bool,
+    pub output: String,
+    pub cases: Vec<TestCaseResult>,
+}
+
+// 在宿主机上用 cargo test 运行测试集，每个 tests/*.rs 是一个独立的测试程序
+pub async fn run_suite(
+    easy_fs_dir: &Path,
+    run_dir: &Path,
+    toolchain: &str,
+    envs: Vec<(String, String)>,
+    deadline: Instant,
+) -> Result<SuiteReport, String> {
+    let harness_dir = prepare_harness(easy_fs_dir, run_dir).await?;
+    let cargo = |args: &[&str]| {
+        let mut cmd = Command::new("cargo");
+        cmd.args(args)
+            .envs(envs.iter().cloned())
+            .env("RUSTUP_TOOLCHAIN", toolchain)
+            .env("EASYFS_JUDGE_IMAGE_DIR", run_dir)
+            .current_dir(&harness_dir)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        cmd
+    };
+
+    let mut report = SuiteReport {
+        build_ok: false,
+        timed_out: false,
+        output: String::new(),
+        cases: Vec::new(),
+    };
+
+    // 先编译所有测试程序，编译失败时不再运行
+    let build = match output_before(cargo(&["test", "--no-run"]), deadline).await {
+        Ok(Some(output)) => output,
+        Ok(None) => {
+            report.timed_out = true;
+            return Ok(report);
+        }
+        Err(e) => return Err(format!("无法运行cargo: {}", e)),
+    };
+    report.output.push_str(&String::from_utf8_lossy(&build.stderr));
+    if !build.status.success() {
+        return Ok(report);
+    }
+    report.build_ok = true;
+
+    for suite in suite_names()? {
+        report.output.push_str(&format!("\n== {} ==\n", suite));
+        let run = output_before(cargo(&["test", "--test", &suite, "--", "--test-threads=1"]), deadline).await;
+        let output = match run {
+            Ok(Some(output)) => output,
+            Err(e) => return Err(format!("无法运行cargo: {}", e)),
+            Ok(None) => {
+                report.timed_out = true;
+                report.cases.push(TestCaseResult {
+                    name: suite,
+                    verdict: CaseVerdict::TimeLimitExceeded,
+                    exit_code: None,
+                    duration_ms: None,
+                });
+                break;
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        report.output.push_str(&stdout);
+        report.output.push_str(&String::from_utf8_lossy(&output.stderr));
+        report.cases.extend(parse_test_output(&suite, &stdout));
+    }
+
+    Ok(report)
+}
+
+// 在单独的进程组中运行命令并收集输出，超时返回 None。
+// cargo 会启动 rustc、build.rs 和测试程序，超时时结束整个进程组，只结束 cargo 会留下仍在运行的测试程序
+async fn output_before(mut cmd: Command, deadline: Instant) -> std::io::Result<Option<std::process::Output>> {
+    let child = cmd
+        .process_group(0)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pgid = child.id();
+    match tokio::time::timeout_at(deadline, child.wait_with_output()).await {
+        Ok(output) => output.map(Some),
+        Err(_) => {
+            if let Some(pgid) = pgid {
+                kill_process_group(pgid).await;
+            }
+            Ok(None)
+        }
+    }
+}
+
+// 结束整个进程组
+async fn kill_process_group(pgid: u32) {
+    let status = Command::new("kill")
+        .arg("-KILL")
+        .arg("--")
+        .arg(format!("-{}", pgid))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+    if !matches!(status, Ok(status) if status.success()) {
+        tracing::warn!("无法结束进程组 {}", pgid);
+    }
+}
+
+// 复制测试集并生成指向学生 easy-fs 的 Cargo.toml
+async fn prepare_harness(easy_fs_dir: &Path, run_dir: &Path) -> Result<PathBuf, String> {
+    let easy_fs_dir = easy_fs_dir
+        .canonicalize()
+        .map_err(|e| format!("无法访问 easy-fs 目录: {}", e))?;
+    tokio::fs::create_dir_all(run_dir).await.map_err(|e| e.to_string())?;
+
+    let dst = run_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || copy_dir_recursively(SUITE_DIR, &dst))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("无法复制测试集: {}", e))?;
+
+    let harness_dir = run_dir.join(Path::new(SUITE_DIR).file_name().unwrap_or_default());
+    let template = tokio::fs::read_to_string(harness_dir.join(CARGO_TEMPLATE))
+        .await
+        .map_err(|e| format!("无法读取测试集的 {}: {}", CARGO_TEMPLATE, e))?;
+    let manifest = template.replace("{{easy_fs_path}}", &easy_fs_dir.to_string_lossy().replace('\\', "/"));
+    tokio::fs::write(harness_dir.join("Cargo.toml"), manifest)
+        .await
+        .map_err(|e| format!("无法生成测试集的 Cargo.toml: {}", e))?;
+
+    Ok(harness_dir)
+}
+
+// tests 目录下的每个文件对应一个测试程序
+fn suite_names() -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(Path::new(SUITE_DIR).join("tests")).map_err(|e| format!("无法读取测试集: {}", e))?;
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+        .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+// 解析 libtest 的输出，形如 "test write_then_read ... ok"
+fn parse_test_output(suite: &str, stdout: &str) -> Vec<TestCaseResult> {
+    let mut cases = Vec::new();
+    for line in stdout.lines() {
+        let rest = match line.strip_prefix("test ") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        let (name, result) = match rest.split_once(" ... ") {
+            Some((name, result)) => (name, result.trim()),
+            // 测试程序崩溃时最后一行只有用例名
+            None => match rest.strip_suffix(" ...") {
+                Some(name) => (name, ""),
+                None => continue,
+            },
+        };
+        let verdict = match result {
+            "ok" => CaseVerdict::Passed,
+            "ignored" => continue,
+            _ => CaseVerdict::Failed,
+        };
+        cases.push(TestCaseResult {
+            name: format!("{}::{}", suite, name),
+            verdict,
+            exit_code: None,
+            duration_ms: None,
+        });
+    }
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // 进程已退出（不存在或只剩僵尸进程）
+    fn is_gone(pid: &str) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => stat.rsplit(") ").next().map_or(false, |rest| rest.starts_with('Z')),
+            Err(_) => true,
+        }
+    }
+
+    #[tokio::test]
+    async fn timeout_kills_the_whole_process_group() {
+        let pid_file = std::env::temp_dir().join(format!("easyfs_group_test_{}", std::process::id()));
+        let _ = std::fs::remove_file(&pid_file);
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}; wait", pid_file.display()))
+            .kill_on_drop(true);
+
+        let deadline = Instant::now() + Duration::from_millis(500);
+        assert!(output_before(cmd, deadline).await.unwrap().is_none());
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let pid = pid.trim();
+        for _ in 0..50 {
+            if is_gone(pid) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(is_gone(pid), "sleep {} 仍在运行", pid);
+        std::fs::remove_file(&pid_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn collects_output_before_the_deadline() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("echo ok; echo err >&2");
+        let output = output_before(cmd, Instant::now() + Duration::from_secs(10)).await.unwrap().unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"ok\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// easy-fs 的磁盘布局，与学生工程 easy-fs/src/layout.rs 保持一致
+const BLOCK_SZ: usize = 512;
+const EFS_MAGIC: u32 = 0x3b80_0001;
+const INODE_SIZE: usize = 128;
+const INODES_PER_BLOCK: u32 = (BLOCK_SZ / INODE_SIZE) as u32;
+const INODE_DIRECT_COUNT: usize = 28;
+const INODE_INDIRECT1_COUNT: usize = BLOCK_SZ / 4;
+const DIRENT_SZ: usize = 32;
+const NAME_LENGTH_LIMIT: usize = 27;
+const BLOCK_BITS: u32 = (BLOCK_SZ * 8) as u32;
+const ROOT_INODE: u32 = 0;
+// 一个inode最多能索引的数据块数
+const INODE_MAX_BLOCKS: usize = INODE_DIRECT_COUNT + INODE_INDIRECT1_COUNT + INODE_INDIRECT1_COUNT * INODE_INDIRECT1_COUNT;
+
+#[derive(Debug, Clone)]
+struct SuperBlock {
+    total_blocks: u32,
+    inode_bitmap_blocks: u32,
+    inode_area_blocks: u32,
+    data_bitmap_blocks: u32,
+    data_area_blocks: u32,
+}
+
+impl SuperBlock {
+    fn inode_area_start(&self) -> u32 {
+        1 + self.inode_bitmap_blocks
+    }
+
+    fn data_bitmap_start(&self) -> u32 {
+        self.inode_area_start() + self.inode_area_blocks
+    }
+
+    fn data_area_start(&self) -> u32 {
+        self.data_bitmap_start() + self.data_bitmap_blocks
+    }
+}
+
+// 磁盘上的inode
+#[derive(Debug, Clone)]
+pub struct DiskInode {
+    pub size: u32,
+    direct: [u32; INODE_DIRECT_COUNT],
+    indirect1: u32,
+    indirect2: u32,
+    pub is_dir: bool,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+// 只读的 easy-fs 镜像解析器，评测结束后用于检查文件系统的状态
+pub struct EfsImage {
+    data: Vec<u8>,
+    sb: SuperBlock,
+}
+
+impl EfsImage {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+        Self::from_bytes(data)
+    }
+
+    // 超级块中的数字来自学生代码，校验通过之后各区域的起始块号都不会溢出
+    fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        if data.len() < BLOCK_SZ {
+            return Err("镜像太小，无法读取超级块".to_string());
+        }
+        if read_u32(&data, 0) != EFS_MAGIC {
+            return Err(format!("超级块的魔数不正确: {:#x}", read_u32(&data, 0)));
+        }
+        let sb = SuperBlock {
+            total_blocks: read_u32(&data, 4),
+            inode_bitmap_blocks: read_u32(&data, 8),
+            inode_area_blocks: read_u32(&data, 12),
+            data_bitmap_blocks: read_u32(&data, 16),
+            data_area_blocks: read_u32(&data, 20),
+        };
+        let used_blocks = 1
+            + sb.inode_bitmap_blocks as u64
+            + sb.inode_area_blocks as u64
+            + sb.data_bitmap_blocks as u64
+            + sb.data_area_blocks as u64;
+        if used_blocks > sb.total_blocks as u64 {
+            return Err("超级块中各区域的大小之和超过了总块数".to_string());
+        }
+        if sb.total_blocks as u64 * BLOCK_SZ as u64 > data.len() as u64 {
+            return Err(format!("超级块记录了 {} 个块，但镜像只有 {} 字节", sb.total_blocks, data.len()));
+        }
+        Ok(Self { data, sb })
+    }
+
+    fn block(&self, block_id: u32) -> Result<&[u8], String> {
+        if block_id >= self.sb.total_blocks {
+            return Err(format!("块号 {} 超出范围", block_id));
+        }
+        let start = block_id as usize * BLOCK_SZ;
+        Ok(&self.data[start..start + BLOCK_SZ])
+    }
+
+    fn inode(&self, inode_id: u32) -> Result<DiskInode, String> {
+        if inode_id >= self.sb.inode_area_blocks.saturating_mul(INODES_PER_BLOCK) {
+            return Err(format!("inode {} 超出范围", inode_id));
+        }
+        let block = self.block(self.sb.inode_area_start() + inode_id / INODES_PER_BLOCK)?;
+        let raw = &block[(inode_id % INODES_PER_BLOCK) as usize * INODE_SIZE..][..INODE_SIZE];
+
+        let mut direct = [0u32; INODE_DIRECT_COUNT];
+        for (i, slot) in direct.iter_mut().enumerate() {
+            *slot = read_u32(raw, 4 + i * 4);
+        }
+        let type_offset = 4 + INODE_DIRECT_COUNT * 4 + 8;
+        Ok(DiskInode {
+            size: read_u32(raw, 0),
+            direct,
+            indirect1: read_u32(raw, 4 + INODE_DIRECT_COUNT * 4),
+            indirect2: read_u32(raw, 8 + INODE_DIRECT_COUNT * 4),
+            is_dir: raw[type_offset] == 1,
+        })
+    }
+
+    // 读取索引块中的前 count 个块号
+    fn index_entries(&self, block_id: u32, count: usize) -> Result<Vec<u32>, String> {
+        let block = self.block(block_id)?;
+        Ok((0..count.min(INODE_INDIRECT1_COUNT)).map(|i| read_u32(block, i * 4)).collect())
+    }
+
+    // 返回 (按顺序排列的数据块, 索引块)
+    fn inode_blocks(&self, inode: &DiskInode) -> Result<(Vec<u32>, Vec<u32>), String> {
+        let total = (inode.size as usize).div_ceil(BLOCK_SZ);
+        let mut data = Vec::with_capacity(total.min(INODE_MAX_BLOCKS));
+        let mut index = Vec::new();
+
+        data.extend_from_slice(&inode.direct[..total.min(INODE_DIRECT_COUNT)]);
+        if total > INODE_DIRECT_COUNT {
+            index.push(inode.indirect1);
+            data.extend(self.index_entries(inode.indirect1, total - INODE_DIRECT_COUNT)?);
+        }
+        if total > INODE_DIRECT_COUNT + INODE_INDIRECT1_COUNT {
+            let mut remaining = total - INODE_DIRECT_COUNT - INODE_INDIRECT1_COUNT;
+            index.push(inode.indirect2);
+            let firsts = self.index_entries(inode.indirect2, remaining.div_ceil(INODE_INDIRECT1_COUNT))?;
+            for first in firsts {
+                index.push(first);
+                let entries = self.index_entries(first, remaining)?;
+                remaining -= entries.len();
+                data.extend(entries);
+            }
+        }
+        Ok((data, index))
+    }
+
+    pub fn read_inode_data(&self, inode: &DiskInode) -> Result<Vec<u8>, String> {
+        // inode 中的大小可能远大于实际能索引的块，只按解析出的块数分配
+        let (blocks, _) = self.inode_blocks(inode)?;
+        let capacity = blocks
+            .len()
+            .checked_mul(BLOCK_SZ)
+            .ok_or_else(|| format!("文件大小 {} 超出范围", inode.size))?;
+        let mut content = Vec::with_capacity(capacity);
+        for block_id in blocks {
+            content.extend_from_slice(self.block(block_id)?);
+        }
+        content.truncate(inode.size as usize);
+        Ok(content)
+    }
+
+    fn dir_entries(&self, inode: &DiskInode) -> Result<Vec<(String, u32)>, String> {
+        let content = self.read_inode_data(inode)?;
+        Ok(content
+            .chunks_exact(DIRENT_SZ)
+            .map(|entry| {
+                let name_len = entry[..=NAME_LENGTH_LIMIT].iter().position(|&b| b == 0).unwrap_or(NAME_LENGTH_LIMIT);
+                (
+                    String::from_utf8_lossy(&entry[..name_len]).to_string(),
+                    read_u32(entry, NAME_LENGTH_LIMIT + 1),
+                )
+            })
+            .collect())
+    }
+
+    // 从根目录开始按路径查找文件
+    pub fn lookup(&self, path: &str) -> Result<Option<DiskInode>, String> {
+        let mut inode = self.inode(ROOT_INODE)?;
+        for name in path.split('/').filter(|s| !s.is_empty()) {
+            if !inode.is_dir {
+                return Ok(None);
+            }
+            match self.dir_entries(&inode)?.into_iter().find(|(entry, _)| entry == name) {
+                Some((_, inode_id)) => inode = self.inode(inode_id)?,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(inode))
+    }
+
+    fn bit_set(&self, bitmap_start: u32, bit: u32) -> Result<bool, String> {
+        let block = self.block(bitmap_start + bit / BLOCK_BITS)?;
+        let inner = (bit % BLOCK_BITS) as usize;
+        let word = u64::from_le_bytes(block[inner / 64 * 8..][..8].try_into().unwrap());
+        Ok(word & (1u64 << (inner % 64)) != 0)
+    }
+
+    fn count_bits(&self, bitmap_start: u32, limit: u32) -> Result<u32, String> {
+        let mut count = 0;
+        for bit in 0..limit {
+            if self.bit_set(bitmap_start, bit)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    // 检查inode、数据块和位图是否一致，返回检查摘要或发现的问题
+    pub fn check_consistency(&self) -> Result<String, String> {
+        let mut problems = Vec::new();
+        let mut visited = HashSet::new();
+        let mut block_owner: HashMap<u32, u32> = HashMap::new();
+        let mut pending = vec![(ROOT_INODE, "/".to_string())];
+        let data_start = self.sb.data_area_start();
+        let data_end = data_start + self.sb.data_area_blocks;
+
+        while let Some((inode_id, path)) = pending.pop() {
+            // 硬链接会使同一个inode出现多次
+            if !visited.insert(inode_id) {
+                continue;
+            }
+            if !self.bit_set(1, inode_id)? {
+                problems.push(format!("{} 使用的 inode {} 在inode位图中未分配", path, inode_id));
+            }
+            let inode = self.inode(inode_id)?;
+            let (data, index) = match self.inode_blocks(&inode) {
+                Ok(blocks) => blocks,
+                Err(e) => {
+                    problems.push(format!("{} 的块索引无效: {}", path, e));
+                    continue;
+                }
+            };
+
+            for block_id in data.iter().chain(index.iter()).copied() {
+                if !(data_start..data_end).contains(&block_id) {
+                    problems.push(format!("{} 引用了数据区之外的块 {}", path, block_id));
+                    continue;
+                }
+                if !self.bit_set(self.sb.data_bitmap_start(), block_id - data_start)? {
+                    problems.push(format!("{} 使用的块 {} 在数据位图中未分配", path, block_id));
+                }
+                if let Some(owner) = block_owner.insert(block_id, inode_id) {
+                    if owner != inode_id {
+                        problems.push(format!("块 {} 同时被 inode {} 和 {} 使用", block_id, owner, inode_id));
+                    }
+                }
+            }
+
+            if inode.is_dir {
+                if inode.size as usize % DIRENT_SZ != 0 {
+                    problems.push(format!("目录 {} 的大小 {} 不是目录项大小的整数倍", path, inode.size));
+                }
+                for (name, child) in self.dir_entries(&inode)? {
+                    let child_path = format!("{}{}{}", path, if path.ends_with('/') { "" } else { "/" }, name);
+                    pending.push((child, child_path));
+                }
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(problems.join("\n"));
+        }
+
+        // easy-fs 不回收inode，删除文件后位图中留下未使用的项是正常的，只作提示
+        let allocated_inodes = self.count_bits(1, self.sb.inode_area_blocks.saturating_mul(INODES_PER_BLOCK))?;
+        let allocated_blocks = self.count_bits(self.sb.data_bitmap_start(), self.sb.data_area_blocks)?;
+        Ok(format!(
+            "可达 inode {} 个（位图中已分配 {} 个），使用数据块 {} 个（位图中已分配 {} 个）",
+            visited.len(),
+            allocated_inodes,
+            block_owner.len(),
+            allocated_blocks
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA_START: u32 = 4;
+    const INDEX_BLOCK: u32 = 6;
+    const ZERO_BLOCK: u32 = 7;
+
+    // 8 个块：超级块、inode位图、inode区、数据位图各一块，数据区 4 块。
+    // 根目录在块 4 中有一个目录项 hello 指向 inode 1，inode 1 的内容在块 5；
+    // 块 6 中的块号都是 7，块 7 全为 0
+    fn image(file_size: u32, indirect1: u32, indirect2: u32) -> Vec<u8> {
+        let mut data = vec![0u8; 8 * BLOCK_SZ];
+        let put = |data: &mut Vec<u8>, offset: usize, value: u32| data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        for (i, value) in [EFS_MAGIC, 8, 1, 1, 1, 4].into_iter().enumerate() {
+            put(&mut data, i * 4, value);
+        }
+        data[BLOCK_SZ] = 0b11;
+        data[3 * BLOCK_SZ] = 0b1111;
+
+        let inode = |data: &mut Vec<u8>, id: usize, size: u32, first: u32, is_dir: bool| {
+            let base = 2 * BLOCK_SZ + id * INODE_SIZE;
+            put(data, base, size);
+            put(data, base + 4, first);
+            put(data, base + 4 + INODE_DIRECT_COUNT * 4, indirect1);
+            put(data, base + 8 + INODE_DIRECT_COUNT * 4, indirect2);
+            data[base + 4 + INODE_DIRECT_COUNT * 4 + 8] = is_dir as u8;
+        };
+        inode(&mut data, 0, DIRENT_SZ as u32, DATA_START, true);
+        inode(&mut data, 1, file_size, DATA_START + 1, false);
+
+        let dirent = DATA_START as usize * BLOCK_SZ;
+        data[dirent..dirent + 5].copy_from_slice(b"hello");
+        put(&mut data, dirent + NAME_LENGTH_LIMIT + 1, 1);
+        let content = (DATA_START as usize + 1) * BLOCK_SZ;
+        data[content..content + 6].copy_from_slice(b"world\n");
+        for i in 0..INODE_INDIRECT1_COUNT {
+            put(&mut data, INDEX_BLOCK as usize * BLOCK_SZ + i * 4, ZERO_BLOCK);
+        }
+        data
+    }
+
+    #[test]
+    fn reads_file_through_root_directory() {
+        let image = EfsImage::from_bytes(image(6, 0, 0)).unwrap();
+        let inode = image.lookup("/hello").unwrap().unwrap();
+        assert_eq!(image.read_inode_data(&inode).unwrap(), b"world\n");
+        assert!(image.lookup("/missing").unwrap().is_none());
+        assert!(image.check_consistency().is_ok());
+    }
+
+    #[test]
+    fn huge_inode_size_reads_only_indexed_blocks() {
+        // 间接块最终都指向块 0，能索引的块数远小于 u32::MAX 字节；按大小分配会申请 4GB 内存
+        let image = EfsImage::from_bytes(image(u32::MAX, ZERO_BLOCK, INDEX_BLOCK)).unwrap();
+        let inode = image.lookup("/hello").unwrap().unwrap();
+        let content = image.read_inode_data(&inode).unwrap();
+        assert_eq!(content.len(), INODE_MAX_BLOCKS * BLOCK_SZ);
+        assert!(content.capacity() <= INODE_MAX_BLOCKS * BLOCK_SZ);
+    }
+
+    #[test]
+    fn rejects_superblock_with_overflowing_areas() {
+        let mut data = image(6, 0, 0);
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(EfsImage::from_bytes(data).is_err());
+
+        let mut data = image(6, 0, 0);
+        data[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(EfsImage::from_bytes(data).is_err());
+    }
+}
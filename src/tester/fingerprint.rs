@@ -0,0 +1,63 @@
+use std::path::Path;
+use tokio::process::Command;
+
+// 评测环境指纹：用于区分结果变化是来自学生代码还是评测机环境
+#[derive(Debug, Clone)]
+pub struct EnvFingerprint {
+    pub rustc: String,
+    pub cargo: String,
+    pub qemu: String,
+    pub make: String,
+    pub kernel: String,
+    pub host: String,
+    pub bundle_hash: String,
+}
+
+impl EnvFingerprint {
+    // 采集当前评测机的环境信息
+    pub async fn collect(toolchain: &str, bundle_dir: &Path) -> Self {
+        let bundle_dir = bundle_dir.to_path_buf();
+        let bundle_hash = tokio::task::spawn_blocking(move || crate::digest::hash_dir(&bundle_dir, &["target"]))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r.map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| format!("获取失败 ({})", e));
+
+        Self {
+            rustc: command_version("rustc", &["--version"], Some(toolchain)).await,
+            cargo: command_version("cargo", &["--version"], Some(toolchain)).await,
+            qemu: command_version(super::qemu::QEMU_PROGRAM, &["--version"], None).await,
+            make: command_version("make", &["--version"], None).await,
+            kernel: command_version("uname", &["-srm"], None).await,
+            host: command_version("uname", &["-n"], None).await,
+            bundle_hash,
+        }
+    }
+
+    // 以 "名称: 值" 的形式逐行输出，便于展示和搜索
+    pub fn to_text(&self) -> String {
+        format!(
+            "rustc: {}\ncargo: {}\nqemu: {}\nmake: {}\nkernel: {}\nhost: {}\nbundle: {}\n",
+            self.rustc, self.cargo, self.qemu, self.make, self.kernel, self.host, self.bundle_hash
+        )
+    }
+}
+
+// 获取命令输出的第一行作为版本信息
+pub async fn command_version(program: &str, args: &[&str], toolchain: Option<&str>) -> String {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    if let Some(toolchain) = toolchain {
+        cmd.env("RUSTUP_TOOLCHAIN", toolchain);
+    }
+    match cmd.output().await {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_string(),
+        Ok(output) => format!("获取失败 (退出状态 {})", output.status),
+        Err(e) => format!("获取失败 ({})", e),
+    }
+}
@@ -0,0 +1,146 @@
+use super::efs_image::EfsImage;
+use crate::models::{CaseVerdict, TestCaseResult};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+// 测试包中的文件系统断言，每行一条：
+//   exists <路径>              文件存在
+//   absent <路径>              文件不存在
+//   dir <路径>                 目录存在
+//   size <路径> <字节数>        文件大小
+//   content <路径> "<内容>"     文件内容完全相同（支持 \n \t \0 \\ \" 转义）
+//   contains <路径> "<内容>"    文件内容包含
+//   sha256 <路径> <哈希>        文件内容的 sha256
+//   consistent                 inode、数据块和位图一致
+// # 开头的行为注释
+pub const FS_CHECKS_FILE: &str = "fs_checks.txt";
+
+// 每条断言的结果作为一个额外的用例名
+const CASE_PREFIX: &str = "fs: ";
+
+// 评测结束后检查 fs.img 的状态，测试包中没有断言文件时返回 None。
+// 返回的文本会附加到评测输出中，说明每条断言失败的原因
+pub fn run_checks(bundle_dir: &Path, fs_img: &Path) -> Option<(Vec<TestCaseResult>, String)> {
+    let source = std::fs::read_to_string(bundle_dir.join(FS_CHECKS_FILE)).ok()?;
+    let checks: Vec<&str> = source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if checks.is_empty() {
+        return None;
+    }
+
+    let image = EfsImage::open(fs_img);
+    let mut cases = Vec::new();
+    let mut report = String::from("\n== 文件系统检查 ==\n");
+
+    for check in checks {
+        let result = match &image {
+            Ok(image) => run_check(image, check),
+            Err(e) => Err(format!("无法解析 fs.img: {}", e)),
+        };
+        let verdict = match &result {
+            Ok(detail) => {
+                report.push_str(&format!("[通过] {}{}\n", check, detail.as_ref().map_or(String::new(), |d| format!(" ({})", d))));
+                CaseVerdict::Passed
+            }
+            Err(reason) => {
+                report.push_str(&format!("[失败] {}\n    {}\n", check, reason.replace('\n', "\n    ")));
+                CaseVerdict::Failed
+            }
+        };
+        cases.push(TestCaseResult {
+            name: format!("{}{}", CASE_PREFIX, check),
+            verdict,
+            exit_code: None,
+            duration_ms: None,
+        });
+    }
+
+    Some((cases, report))
+}
+
+// 执行一条断言，成功时可以附带说明
+fn run_check(image: &EfsImage, check: &str) -> Result<Option<String>, String> {
+    let (kind, rest) = check.split_once(char::is_whitespace).unwrap_or((check, ""));
+    let rest = rest.trim();
+    let (path, arg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let arg = arg.trim();
+
+    let file = |path: &str| -> Result<Vec<u8>, String> {
+        match image.lookup(path)? {
+            Some(inode) if !inode.is_dir => image.read_inode_data(&inode),
+            Some(_) => Err(format!("{} 是目录", path)),
+            None => Err(format!("{} 不存在", path)),
+        }
+    };
+
+    match kind {
+        "consistent" => image.check_consistency().map(Some),
+        "exists" => file(path).map(|_| None),
+        "absent" => match image.lookup(path)? {
+            Some(_) => Err(format!("{} 不应当存在", path)),
+            None => Ok(None),
+        },
+        "dir" => match image.lookup(path)? {
+            Some(inode) if inode.is_dir => Ok(None),
+            Some(_) => Err(format!("{} 不是目录", path)),
+            None => Err(format!("{} 不存在", path)),
+        },
+        "size" => {
+            let expected: usize = arg.parse().map_err(|_| format!("断言格式错误: {}", check))?;
+            let actual = file(path)?.len();
+            if actual == expected {
+                Ok(None)
+            } else {
+                Err(format!("大小为 {} 字节，期望 {} 字节", actual, expected))
+            }
+        }
+        "content" | "contains" => {
+            let expected = unquote(arg).ok_or_else(|| format!("断言格式错误: {}", check))?;
+            let actual = file(path)?;
+            let ok = if kind == "content" {
+                actual == expected.as_bytes()
+            } else {
+                String::from_utf8_lossy(&actual).contains(&expected)
+            };
+            if ok {
+                Ok(None)
+            } else {
+                Err(format!("实际内容: {:?}", String::from_utf8_lossy(&actual)))
+            }
+        }
+        "sha256" => {
+            let actual = crate::digest::to_hex(&Sha256::digest(file(path)?));
+            if actual.eq_ignore_ascii_case(arg) {
+                Ok(None)
+            } else {
+                Err(format!("sha256 为 {}", actual))
+            }
+        }
+        _ => Err(format!("未知的断言类型: {}", kind)),
+    }
+}
+
+// 解析带引号和转义的字符串
+fn unquote(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => result.push('\n'),
+            't' => result.push('\t'),
+            '0' => result.push('\0'),
+            '\\' => result.push('\\'),
+            '"' => result.push('"'),
+            _ => return None,
+        }
+    }
+    Some(result)
+}
@@ -0,0 +1,276 @@
+use super::profile::ProfileRegistry;
+use super::qemu::QEMU_PROGRAM;
+use crate::models::TestStatus;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+// 内核编译目标
+const KERNEL_TARGET: &str = "riscv64gc-unknown-none-elf";
+
+// 评测依赖的外部工具
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    Make,
+    Toolchain,
+    Target,
+    RustObjcopy,
+    Qemu,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 5] = [Tool::Make, Tool::Toolchain, Tool::Target, Tool::RustObjcopy, Tool::Qemu];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Tool::Make => "make",
+            Tool::Toolchain => "Rust 工具链",
+            Tool::Target => "riscv64gc-unknown-none-elf 目标",
+            Tool::RustObjcopy => "rust-objcopy",
+            Tool::Qemu => "QEMU",
+        }
+    }
+}
+
+// 单项检查的结果
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub tool: Tool,
+    pub ok: bool,
+    pub detail: String,
+}
+
+// 某个题目评测配置的检查结果
+#[derive(Debug, Clone)]
+pub struct ProfileCheck {
+    pub profile: String,
+    pub missing: Vec<Tool>,
+}
+
+// 评测工作器当前的状态
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub running_test: Option<i32>,
+    pub running_since: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_heartbeat: Option<chrono::DateTime<chrono::Utc>>,
+    pub processed: u64,
+}
+
+// 最近一次金丝雀评测
+#[derive(Debug, Clone)]
+pub struct CanaryRun {
+    pub test_id: i32,
+    pub status: TestStatus,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// 评测机健康状态，供管理员页面展示
+pub struct JudgeHealth {
+    checks: Mutex<Vec<CheckResult>>,
+    profile_checks: Mutex<Vec<ProfileCheck>>,
+    checked_at: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+    worker: Mutex<WorkerStatus>,
+    canary: Mutex<Option<CanaryRun>>,
+}
+
+// 健康页面使用的快照
+pub struct HealthSnapshot {
+    pub checks: Vec<CheckResult>,
+    pub profile_checks: Vec<ProfileCheck>,
+    pub checked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub worker: WorkerStatus,
+    pub canary: Option<CanaryRun>,
+    pub queue_len: usize,
+}
+
+impl JudgeHealth {
+    pub fn new() -> Self {
+        Self {
+            checks: Mutex::new(Vec::new()),
+            profile_checks: Mutex::new(Vec::new()),
+            checked_at: Mutex::new(None),
+            worker: Mutex::new(WorkerStatus {
+                running_test: None,
+                running_since: None,
+                last_heartbeat: None,
+                processed: 0,
+            }),
+            canary: Mutex::new(None),
+        }
+    }
+
+    // 检查每个评测配置所需的工具，返回是否全部可用
+    pub async fn run_preflight(&self, profiles: &ProfileRegistry, toolchain: &str) -> bool {
+        let mut checks = Vec::new();
+        for tool in Tool::ALL {
+            let result = check_tool(tool, toolchain).await;
+            if result.ok {
+                tracing::info!("评测环境检查通过: {} ({})", tool.description(), result.detail);
+            } else {
+                tracing::error!("评测环境检查失败: {} ({})", tool.description(), result.detail);
+            }
+            checks.push(result);
+        }
+
+        let profile_checks: Vec<ProfileCheck> = profiles
+            .names()
+            .into_iter()
+            .map(|name| {
+                let missing = profiles
+                    .get(&name)
+                    .required_tools()
+                    .into_iter()
+                    .filter(|tool| checks.iter().any(|c| c.tool == *tool && !c.ok))
+                    .collect();
+                ProfileCheck { profile: name, missing }
+            })
+            .collect();
+        for check in profile_checks.iter().filter(|c| !c.missing.is_empty()) {
+            tracing::error!("评测配置 {} 缺少必需的工具，所有提交都将失败", check.profile);
+        }
+
+        let all_ok = profile_checks.iter().all(|c| c.missing.is_empty());
+        *self.checks.lock().await = checks;
+        *self.profile_checks.lock().await = profile_checks;
+        *self.checked_at.lock().await = Some(chrono::Utc::now());
+        all_ok
+    }
+
+    pub async fn worker_heartbeat(&self) {
+        self.worker.lock().await.last_heartbeat = Some(chrono::Utc::now());
+    }
+
+    pub async fn worker_started(&self, test_id: i32) {
+        let mut worker = self.worker.lock().await;
+        worker.running_test = Some(test_id);
+        worker.running_since = Some(chrono::Utc::now());
+        worker.last_heartbeat = worker.running_since;
+    }
+
+    pub async fn worker_finished(&self) {
+        let mut worker = self.worker.lock().await;
+        worker.running_test = None;
+        worker.running_since = None;
+        worker.processed += 1;
+        worker.last_heartbeat = Some(chrono::Utc::now());
+    }
+
+    pub async fn canary_submitted(&self, test_id: i32) {
+        *self.canary.lock().await = Some(CanaryRun {
+            test_id,
+            status: TestStatus::Pending,
+            finished_at: None,
+        });
+    }
+
+    pub async fn canary_in_flight(&self) -> bool {
+        matches!(
+            self.canary.lock().await.as_ref().map(|run| &run.status),
+            Some(TestStatus::Pending) | Some(TestStatus::Running)
+        )
+    }
+
+    // 如果是金丝雀评测则记录其结果，未通过时发出告警
+    pub async fn record_if_canary(&self, test_id: i32, status: &TestStatus) {
+        let mut canary = self.canary.lock().await;
+        let run = match canary.as_mut() {
+            Some(run) if run.test_id == test_id => run,
+            _ => return,
+        };
+        run.status = status.clone();
+        run.finished_at = Some(chrono::Utc::now());
+
+        if *status == TestStatus::Passed {
+            tracing::info!("金丝雀评测 #{} 通过", test_id);
+        } else {
+            tracing::error!("告警：金丝雀评测 #{} 未通过（{:?}），评测机本身可能已损坏", test_id, status);
+        }
+    }
+
+    pub async fn snapshot(&self, queue_len: usize) -> HealthSnapshot {
+        HealthSnapshot {
+            checks: self.checks.lock().await.clone(),
+            profile_checks: self.profile_checks.lock().await.clone(),
+            checked_at: *self.checked_at.lock().await,
+            worker: self.worker.lock().await.clone(),
+            canary: self.canary.lock().await.clone(),
+            queue_len,
+        }
+    }
+}
+
+// 运行命令，成功时返回输出的第一行
+async fn run_command(program: &str, args: &[&str], toolchain: Option<&str>) -> Result<String, String> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    if let Some(toolchain) = toolchain {
+        cmd.env("RUSTUP_TOOLCHAIN", toolchain);
+    }
+    let output = match tokio::time::timeout(Duration::from_secs(30), cmd.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("无法运行 {}: {}", program, e)),
+        Err(_) => return Err(format!("运行 {} 超时", program)),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} 退出状态 {}: {}", program, output.status, stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or("").trim().to_string())
+}
+
+async fn check_tool(tool: Tool, toolchain: &str) -> CheckResult {
+    let result = match tool {
+        Tool::Make => run_command("make", &["--version"], None).await,
+        Tool::Toolchain => run_command("rustc", &["--version"], Some(toolchain)).await,
+        Tool::Target => {
+            // 目标已安装时其标准库目录中应当存在 rlib 文件
+            match run_command("rustc", &["--print", "target-libdir", "--target", KERNEL_TARGET], Some(toolchain)).await {
+                Ok(libdir) => {
+                    let has_libs = std::fs::read_dir(&libdir)
+                        .map(|entries| entries.flatten().any(|e| e.path().extension().map_or(false, |ext| ext == "rlib")))
+                        .unwrap_or(false);
+                    if has_libs {
+                        Ok(libdir)
+                    } else {
+                        Err(format!("未安装，请运行 rustup target add {} --toolchain {}", KERNEL_TARGET, toolchain))
+                    }
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Tool::RustObjcopy => run_command("rust-objcopy", &["--version"], Some(toolchain)).await,
+        Tool::Qemu => run_command(QEMU_PROGRAM, &["--version"], None).await,
+    };
+
+    match result {
+        Ok(detail) => CheckResult { tool, ok: true, detail },
+        Err(detail) => CheckResult { tool, ok: false, detail },
+    }
+}
+
+// 金丝雀评测的配置
+pub struct CanaryConfig {
+    pub dir: PathBuf,
+    pub username: String,
+    pub interval: Duration,
+}
+
+impl CanaryConfig {
+    // 从环境变量读取配置：
+    // JUDGE_CANARY_DIR            已知可以通过的工程目录，默认 canary
+    // JUDGE_CANARY_USER           金丝雀提交所属的用户，默认 admin
+    // JUDGE_CANARY_INTERVAL_SECS  提交间隔，默认 3600，0 表示不定期提交
+    pub fn from_env() -> Self {
+        Self {
+            dir: PathBuf::from(std::env::var("JUDGE_CANARY_DIR").unwrap_or_else(|_| "canary".to_string())),
+            username: std::env::var("JUDGE_CANARY_USER").unwrap_or_else(|_| "admin".to_string()),
+            interval: Duration::from_secs(
+                std::env::var("JUDGE_CANARY_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(3600),
+            ),
+        }
+    }
+}
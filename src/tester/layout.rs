@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+
+// 查找工程根目录时的最大深度
+const MAX_ROOT_DEPTH: usize = 8;
+
+// 目录树列表中最多显示的条目数
+const MAX_TREE_ENTRIES: usize = 300;
+
+// 压缩工具附带的目录，不属于提交内容
+const IGNORED_DIRS: [&str; 2] = ["__MACOSX", ".git"];
+
+// 编译产物的扩展名，提交中出现时说明学生打包了本地的编译结果
+const BINARY_EXTENSIONS: [&str; 9] = ["bin", "img", "elf", "o", "a", "so", "rlib", "rmeta", "exe"];
+
+// 提交目录的检查结果
+pub struct LayoutReport {
+    pub root: Option<PathBuf>,
+    pub problems: Vec<String>,
+    pub tree: String,
+}
+
+impl LayoutReport {
+    pub fn is_ok(&self) -> bool {
+        self.root.is_some() && self.problems.is_empty()
+    }
+}
+
+// 检查解压后的提交：在任意深度查找工程根目录，确认题目需要的文件都存在，
+// 并拒绝 target 目录和预先编译好的二进制文件。
+// required 中的路径相对于工程根目录，第一项用于识别根目录；
+// skip 中的目录会被测试包替换，不做检查
+pub fn validate(extract_dir: &Path, required: &[String], skip: &str) -> LayoutReport {
+    let mut report = LayoutReport {
+        root: None,
+        problems: Vec::new(),
+        tree: render_tree(extract_dir),
+    };
+    let marker = match required.first() {
+        Some(marker) => marker,
+        None => {
+            report.root = Some(extract_dir.to_path_buf());
+            return report;
+        }
+    };
+
+    let roots = find_roots(extract_dir, marker);
+    let root = match roots.as_slice() {
+        [] => {
+            report.problems.push(format!(
+                "没有找到工程根目录：压缩包中任何一层目录下都没有 {}。请确认压缩的是整个工程目录",
+                marker
+            ));
+            return report;
+        }
+        [root] => root.clone(),
+        _ => {
+            let listed: Vec<String> = roots.iter().map(|root| display_path(extract_dir, root)).collect();
+            report.problems.push(format!(
+                "找到了多个工程根目录，请只提交其中一个：\n{}",
+                listed.join("\n")
+            ));
+            return report;
+        }
+    };
+
+    for path in required {
+        if !root.join(path).exists() {
+            report.problems.push(format!("缺少 {}", path));
+        }
+    }
+
+    let mut stray = Vec::new();
+    find_stray_files(&root, &root, required, skip, &mut stray);
+    for (path, reason) in stray {
+        report.problems.push(format!("{}：{}", path, reason));
+    }
+
+    report.root = Some(root);
+    report
+}
+
+// 按层次查找包含 marker 的目录，只返回最浅一层的结果
+fn find_roots(extract_dir: &Path, marker: &str) -> Vec<PathBuf> {
+    let mut level = vec![extract_dir.to_path_buf()];
+    for _ in 0..=MAX_ROOT_DEPTH {
+        let found: Vec<PathBuf> = level.iter().filter(|dir| dir.join(marker).exists()).cloned().collect();
+        if !found.is_empty() {
+            return found;
+        }
+        level = level.iter().flat_map(|dir| sub_dirs(dir)).collect();
+        if level.is_empty() {
+            break;
+        }
+    }
+    Vec::new()
+}
+
+// 列出子目录，跳过压缩工具附带的目录和符号链接
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().map_or(false, |t| t.is_dir()))
+                .map(|entry| entry.path())
+                .filter(|path| !is_ignored(path))
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn is_ignored(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| IGNORED_DIRS.contains(&name))
+}
+
+// 查找 target 目录和编译产物，题目要求的文件（如 bootloader）除外
+fn find_stray_files(root: &Path, dir: &Path, required: &[String], skip: &str, stray: &mut Vec<(String, &'static str)>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let relative = display_path(root, &path);
+        if is_ignored(&path) || relative == skip || required.contains(&relative) {
+            continue;
+        }
+        if path.is_dir() {
+            if path.file_name().map_or(false, |name| name == "target") {
+                stray.push((format!("{}/", relative), "不要提交编译产物目录，请先运行 cargo clean 或在压缩时排除"));
+            } else {
+                find_stray_files(root, &path, required, skip, stray);
+            }
+        } else if is_binary(&path) {
+            stray.push((relative, "不要提交预先编译好的二进制文件"));
+        }
+    }
+}
+
+// 根据扩展名或 ELF 文件头判断是否为编译产物
+fn is_binary(path: &Path) -> bool {
+    let by_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| BINARY_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    if by_extension {
+        return true;
+    }
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut magic))
+        .map_or(false, |_| magic == *b"\x7fELF")
+}
+
+fn display_path(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+// 以缩进形式列出提交的目录结构，target 目录只显示文件数
+fn render_tree(dir: &Path) -> String {
+    let mut lines = Vec::new();
+    render_dir(dir, 0, &mut lines);
+    if lines.len() > MAX_TREE_ENTRIES {
+        lines.truncate(MAX_TREE_ENTRIES);
+        lines.push("... 目录过大，其余条目未显示".to_string());
+    }
+    lines.join("\n")
+}
+
+fn render_dir(dir: &Path, depth: usize, lines: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        // 目录树过大时不再继续遍历
+        if lines.len() > MAX_TREE_ENTRIES {
+            return;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let indent = "  ".repeat(depth);
+        if !path.is_dir() {
+            lines.push(format!("{}{}", indent, name));
+        } else if name == "target" {
+            lines.push(format!("{}{}/ ({} 个文件)", indent, name, count_files(&path)));
+        } else {
+            lines.push(format!("{}{}/", indent, name));
+            render_dir(&path, depth + 1, lines);
+        }
+    }
+}
+
+fn count_files(dir: &Path) -> usize {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| {
+                    let path = entry.path();
+                    if path.is_dir() {
+                        count_files(&path)
+                    } else {
+                        1
+                    }
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -0,0 +1,897 @@
+mod cache;
+mod easyfs;
+mod efs_image;
+mod fingerprint;
+mod fs_checks;
+mod health;
+mod layout;
+mod profile;
+mod qemu;
+mod repro;
+mod symbolize;
+mod trusted_fs;
+mod vendor;
+mod watchdog;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, OnceCell};
+use tokio::process::Command;
+use std::collections::VecDeque;
+use crate::artifacts::{ArtifactKind, ArtifactStore};
+use crate::blobstore::BlobStore;
+use crate::models::{CaseVerdict, TestCaseResult, TestTask, TestStatus};
+use crate::database::{TestCaseRepo, TestRepo};
+use profile::{Backend, FsImageMode, JudgeProfile, ProfileRegistry};
+use health::{CanaryConfig, JudgeHealth};
+use cache::BuildCache;
+use trusted_fs::FsPacker;
+
+pub use profile::DEFAULT_PROFILE;
+pub use health::HealthSnapshot;
+pub use vendor::VendorMirror;
+pub use layout::LayoutReport;
+use watchdog::Watchdog;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use sqlx::mysql::MySqlPool;
+
+// 编译学生工程使用的工具链
+pub const RUST_TOOLCHAIN: &str = "nightly-2024-04-29";
+
+// 整个评测（编译 + 运行）的超时时间
+const TEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+// 系统预设的测试包目录
+pub const TEST_BUNDLE_DIR: &str = "user";
+
+// 内核在 initproc 退出时打印的信息
+const INITPROC_EXIT_MARKER: &str = "Idle process exit with exit_code ";
+
+pub struct TestQueue {
+    queue: Mutex<VecDeque<TestTask>>,
+    db_pool: Arc<MySqlPool>,
+    profiles: ProfileRegistry,
+    artifacts: Arc<ArtifactStore>,
+    health: JudgeHealth,
+    build_cache: BuildCache,
+    vendor: VendorMirror,
+    fs_packer: FsPacker,
+    scratch_root: PathBuf, // 评测使用的临时目录
+    blobs: Arc<BlobStore>,
+    fingerprint: OnceCell<String>, // 评测工作器启动时采集的环境指纹
+}
+
+// 一次评测的结果
+pub struct RunOutcome {
+    pub status: TestStatus,
+    pub output: String,
+    pub error: Option<String>,
+    pub hang_report: Option<String>,
+    pub cases: Vec<TestCaseResult>,
+}
+
+impl RunOutcome {
+    fn new(status: TestStatus, output: String, error: Option<String>) -> Self {
+        Self {
+            status,
+            output,
+            error,
+            hang_report: None,
+            cases: Vec::new(),
+        }
+    }
+}
+
+impl TestQueue {
+    pub fn new(db_pool: Arc<MySqlPool>, artifacts: Arc<ArtifactStore>, blobs: Arc<BlobStore>) -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+            db_pool,
+            profiles: ProfileRegistry::load(),
+            artifacts,
+            health: JudgeHealth::new(),
+            build_cache: BuildCache::from_env(),
+            vendor: VendorMirror::from_env(),
+            fs_packer: FsPacker::from_env(),
+            scratch_root: PathBuf::from(std::env::var("JUDGE_SCRATCH_DIR").unwrap_or_else(|_| "judge_scratch".to_string())),
+            blobs,
+            fingerprint: OnceCell::new(),
+        }
+    }
+
+    // 启动前检查各评测配置需要的工具是否齐全
+    pub async fn run_preflight(&self) -> bool {
+        self.health.run_preflight(&self.profiles, RUST_TOOLCHAIN).await
+    }
+
+    // 评测机的环境指纹，评测工作器启动之前为 None
+    pub fn env_fingerprint(&self) -> Option<&str> {
+        self.fingerprint.get().map(String::as_str)
+    }
+
+    // 评测机的依赖镜像，其中的crate即允许使用的依赖
+    pub fn vendor(&self) -> &VendorMirror {
+        &self.vendor
+    }
+
+    pub async fn health_snapshot(&self) -> HealthSnapshot {
+        let queue_len = self.queue.lock().await.len();
+        self.health.snapshot(queue_len).await
+    }
+
+    // 提交一次金丝雀评测：使用已知可以通过的内核检验评测机本身是否正常
+    pub async fn submit_canary(&self) -> Result<i32, String> {
+        let config = CanaryConfig::from_env();
+        if !config.dir.join("os").exists() {
+            return Err(format!("金丝雀工程不存在: {}/os", config.dir.display()));
+        }
+        if self.health.canary_in_flight().await {
+            return Err("上一次金丝雀评测尚未完成".to_string());
+        }
+        let user = crate::database::UserRepo::get_user_by_username(&self.db_pool, &config.username)
+            .await
+            .map_err(|e| format!("查询用户失败: {}", e))?
+            .ok_or_else(|| format!("金丝雀提交所属的用户不存在: {}", config.username))?;
+
+        let test_id = TestRepo::create_test(&self.db_pool, user.id, DEFAULT_PROFILE, None)
+            .await
+            .map_err(|e| format!("创建评测记录失败: {}", e))?;
+        self.health.canary_submitted(test_id).await;
+        self.add_task(TestTask {
+            id: test_id,
+            user_id: user.id,
+            username: user.username,
+            work_dir: config.dir.to_string_lossy().to_string(),
+            profile: DEFAULT_PROFILE.to_string(),
+            upload_id: None,
+        })
+        .await;
+        Ok(test_id)
+    }
+
+    // 定期提交金丝雀评测
+    pub async fn run_canary_task(self: Arc<Self>) {
+        let config = CanaryConfig::from_env();
+        if config.interval.is_zero() {
+            return;
+        }
+        if !config.dir.join("os").exists() {
+            tracing::warn!("未配置金丝雀工程 {}，不会定期提交金丝雀评测", config.dir.display());
+            return;
+        }
+        loop {
+            match self.submit_canary().await {
+                Ok(test_id) => tracing::info!("已提交金丝雀评测 #{}", test_id),
+                Err(e) => tracing::error!("告警：无法提交金丝雀评测: {}", e),
+            }
+            tokio::time::sleep(config.interval).await;
+        }
+    }
+
+    // 所有可选的题目评测配置
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.names()
+    }
+
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles.contains(name)
+    }
+
+    // 按题目的要求检查解压后的提交，在加入队列之前发现目录结构的问题
+    pub fn validate_submission(&self, extract_dir: &Path, profile: &str) -> LayoutReport {
+        let requirements = self.profiles.get(profile).layout_requirements();
+        layout::validate(extract_dir, &requirements, TEST_BUNDLE_DIR)
+    }
+
+    // 题目配置的初始代码目录
+    pub fn baseline_dir(&self, profile: &str) -> Option<std::path::PathBuf> {
+        self.profiles.get(profile).baseline.clone().filter(|dir| dir.is_dir())
+    }
+
+    // 题目的相似度分析比较的源码目录
+    pub fn source_dir(&self, profile: &str) -> &'static str {
+        self.profiles.get(profile).source_dir()
+    }
+
+    // 添加任务到队列
+    pub async fn add_task(&self, task: TestTask) {
+        let username = task.username.clone(); // 克隆用户名以备后用
+        let mut queue = self.queue.lock().await;
+        queue.push_back(task);
+        tracing::info!("Added test task for user {} to queue", username);
+    }
+
+    // 启动评测工作器
+    pub async fn start_worker(self: Arc<Self>) {
+        // 启动时采集评测机环境指纹，记录到每一条评测结果上
+        let fingerprint = self
+            .fingerprint
+            .get_or_init(|| async {
+                fingerprint::EnvFingerprint::collect(RUST_TOOLCHAIN, Path::new(TEST_BUNDLE_DIR))
+                    .await
+                    .to_text()
+            })
+            .await
+            .clone();
+        tracing::info!("Test worker started, environment fingerprint:\n{}", fingerprint);
+        // 队列不会跨重启保存，上次运行残留的临时目录都可以删除
+        if let Err(e) = tokio::fs::remove_dir_all(&self.scratch_root).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("清理评测临时目录失败: {}", e);
+            }
+        }
+        loop {
+            // 尝试获取任务
+            let task = {
+                let mut queue = self.queue.lock().await;
+                queue.pop_front()
+            };
+
+            if let Some(task) = task {
+                tracing::info!("Processing test task for user {}", task.username);
+                self.health.worker_started(task.id).await;
+
+                // 更新状态为运行中
+                if let Err(e) = TestRepo::update_test_status(
+                    &self.db_pool,
+                    task.id,
+                    TestStatus::Running,
+                ).await {
+                    tracing::error!("Failed to update test status: {}", e);
+                    self.health.worker_finished().await;
+                    continue;
+                }
+                if let Err(e) = TestRepo::save_fingerprint(&self.db_pool, task.id, &fingerprint).await {
+                    tracing::error!("Failed to save environment fingerprint: {}", e);
+                }
+
+                // 在临时目录中运行测试，结束后删除临时目录
+                let outcome = match self.prepare_scratch(&task).await {
+                    Ok(work_dir) => {
+                        let scratch_task = TestTask {
+                            work_dir: work_dir.to_string_lossy().to_string(),
+                            ..task.clone()
+                        };
+                        let outcome = self.run_test(&scratch_task).await;
+                        self.remove_scratch(task.id).await;
+                        outcome
+                    }
+                    Err(e) => Err(e.into()),
+                };
+                let outcome = match outcome {
+                    Ok(res) => res,
+                    Err(e) => {
+                        // 评测本身出错时也要记录结果，否则状态会一直停留在运行中
+                        tracing::error!("测试执行错误: {}", e);
+                        if let Err(e) = TestRepo::update_test_result(
+                            &self.db_pool,
+                            task.id,
+                            TestStatus::Error,
+                            None,
+                            Some(format!("评测出错: {}", e)),
+                        ).await {
+                            tracing::error!("Failed to save test result: {}", e);
+                        }
+                        self.health.record_if_canary(task.id, &TestStatus::Error).await;
+                        self.health.worker_finished().await;
+                        continue;
+                    }
+                };
+
+                // 更新测试结果
+                if let Err(e) = TestRepo::update_test_result(
+                    &self.db_pool,
+                    task.id,
+                    outcome.status.clone(),
+                    Some(outcome.output),
+                    outcome.error,
+                ).await {
+                    tracing::error!("Failed to save test result: {}", e);
+                }
+
+                // 未通过时生成复现包，方便学生在本地重现评测过程
+                if outcome.status != TestStatus::Passed {
+                    self.save_repro_bundle(&task, &fingerprint).await;
+                }
+
+                // 保存各用例的结果
+                if let Err(e) = TestCaseRepo::save_cases(&self.db_pool, task.id, &outcome.cases).await {
+                    tracing::error!("Failed to save test cases: {}", e);
+                }
+
+                // 保存卡死报告
+                if let Some(report) = outcome.hang_report {
+                    if let Err(e) = TestRepo::save_hang_report(&self.db_pool, task.id, &report).await {
+                        tracing::error!("Failed to save hang report: {}", e);
+                    }
+                }
+
+                self.health.record_if_canary(task.id, &outcome.status).await;
+                self.health.worker_finished().await;
+                tracing::info!("Test for user {} completed with status: {:?}",
+                              task.username, outcome.status);
+            } else {
+                // 如果队列为空，等待一段时间
+                self.health.worker_heartbeat().await;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    // 把提交取到临时目录并注入当前的测试包，返回临时目录中的工程根目录。
+    // 上传的提交从存储中取出，评测节点不需要访问网页节点的 uploads 目录；
+    // 没有存入存储的提交（如金丝雀工程）从本地目录复制
+    async fn prepare_scratch(&self, task: &TestTask) -> Result<PathBuf, String> {
+        let source = PathBuf::from(&task.work_dir);
+        let scratch = self.scratch_root.join(task.id.to_string());
+        let work_dir = scratch.join(source.file_name().unwrap_or_else(|| std::ffi::OsStr::new("project")));
+        if let Err(e) = tokio::fs::remove_dir_all(&scratch).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(format!("无法清理评测目录: {}", e));
+            }
+        }
+
+        // 工程目录在上传文件表中的路径相对用户目录
+        let user_dir = PathBuf::from(format!("uploads/{}", task.username));
+        let prefix = source
+            .strip_prefix(&user_dir)
+            .ok()
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"));
+        let fetched = match (task.upload_id, prefix) {
+            (Some(upload_id), Some(prefix)) => self.blobs.checkout(&self.db_pool, upload_id, &prefix, &work_dir).await?,
+            _ => 0,
+        };
+
+        let copy_from = if fetched == 0 { Some(source) } else { None };
+        let target = work_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Some(source) = copy_from {
+                if !source.is_dir() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("工作目录不存在: {}", source.display()),
+                    ));
+                }
+                copy_writable(&source, &target)?;
+            }
+            std::fs::create_dir_all(TEST_BUNDLE_DIR)?;
+            copy_dir_recursively(TEST_BUNDLE_DIR, &target)
+        })
+        .await
+        .map_err(|e| format!("准备评测目录的任务异常退出: {}", e))?
+        .map_err(|e| format!("无法准备评测目录: {}", e))?;
+        Ok(work_dir)
+    }
+
+    async fn remove_scratch(&self, test_id: i32) {
+        let scratch = self.scratch_root.join(test_id.to_string());
+        if let Err(e) = tokio::fs::remove_dir_all(&scratch).await {
+            tracing::warn!("删除评测临时目录 {} 失败: {}", scratch.display(), e);
+        }
+    }
+
+    // 运行测试
+    async fn run_test(&self, task: &TestTask) -> Result<RunOutcome, Box<dyn std::error::Error + Send + Sync>> {
+        let work_dir = std::path::Path::new(&task.work_dir);
+
+        // 检查工作目录是否存在
+        if !work_dir.exists() {
+            return Err(format!("工作目录不存在: {}", task.work_dir).into());
+        }
+
+        let profile = self.profiles.get(&task.profile);
+        tracing::info!("Running test {} with judge profile {}", task.id, profile.name);
+        let deadline = tokio::time::Instant::now() + TEST_TIMEOUT;
+
+        if profile.backend == Backend::EasyFsHost {
+            return self.run_easy_fs_suite(task, work_dir, deadline).await;
+        }
+
+        // 检查OS目录是否存在
+        let os_dir = work_dir.join("os");
+        if !os_dir.exists() {
+            return Err(format!("OS目录不存在: {}/os", task.work_dir).into());
+        }
+
+        // 离线评测时，依赖必须都在评测机的镜像中
+        if self.vendor.is_enabled() {
+            let missing = self.vendor.missing_dependencies(work_dir);
+            if !missing.is_empty() {
+                let message = format!(
+                    "以下依赖不在评测机的依赖镜像中，无法离线编译：\n{}\n\n可用的crate列表请联系管理员查看",
+                    missing.join("\n")
+                );
+                return Ok(RunOutcome::new(TestStatus::Failed, message, Some("依赖不可用".to_string())));
+            }
+        }
+
+        // 使用共享的构建缓存：依赖只下载一次，测试包只编译一次
+        let run_dir = std::env::temp_dir().join(format!("oj-build-{}", task.id));
+        let build_env = self
+            .build_cache
+            .prepare(
+                work_dir,
+                &run_dir,
+                RUST_TOOLCHAIN,
+                Path::new(TEST_BUNDLE_DIR),
+                &profile.make_args(),
+                &self.vendor,
+            )
+            .await;
+
+        // 在学生代码运行之前编译可信的测试程序，之后用来替换或校验 fs.img
+        let trusted_dir = std::env::temp_dir().join(format!("oj-trusted-{}", task.id));
+        let trusted_user = if matches!(profile.fs_image, FsImageMode::Student | FsImageMode::NoFs) {
+            None
+        } else {
+            Some(
+                self.build_cache
+                    .trusted_user_build(
+                        Path::new(TEST_BUNDLE_DIR),
+                        RUST_TOOLCHAIN,
+                        &profile.make_args(),
+                        &self.vendor,
+                        &trusted_dir,
+                    )
+                    .await,
+            )
+        };
+
+        // 编译内核和文件系统镜像
+        let mut build = Command::new("make");
+        build
+            .args(profile.make_args())
+            .envs(build_env)
+            .env("RUSTUP_TOOLCHAIN", RUST_TOOLCHAIN)
+            .current_dir(&os_dir)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+
+        let build_output = tokio::time::timeout_at(deadline, build.output()).await;
+        let _ = tokio::fs::remove_dir_all(&run_dir).await;
+        let build_output = match build_output {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                let _ = tokio::fs::remove_dir_all(&trusted_dir).await;
+                return Err(format!("进程启动失败: {}", e).into());
+            }
+            Err(_) => {
+                let _ = tokio::fs::remove_dir_all(&trusted_dir).await;
+                return Err("编译超时".into());
+            }
+        };
+
+        let build_log = format!(
+            "{}{}",
+            String::from_utf8_lossy(&build_output.stdout),
+            String::from_utf8_lossy(&build_output.stderr)
+        );
+        if let Err(e) = self.artifacts.save_bytes(task.id, ArtifactKind::BuildLog, build_log.as_bytes()).await {
+            tracing::warn!("Failed to save build log for test {}: {}", task.id, e);
+        }
+
+        if !build_output.status.success() {
+            // 没有 Cargo.lock 时无法预先检查，只能根据cargo的报错判断
+            let error = if self.vendor.is_enabled() && vendor::is_dependency_error(&build_log) {
+                "依赖不可用"
+            } else {
+                "编译失败"
+            };
+            let _ = tokio::fs::remove_dir_all(&trusted_dir).await;
+            return Ok(RunOutcome::new(TestStatus::Failed, build_log, Some(error.to_string())));
+        }
+
+        // 学生的打包工具可能替换了测试程序，启动前换成可信的镜像或校验镜像内容
+        let fs_note = self.prepare_fs_image(task.id, profile, &os_dir, trusted_user).await;
+        let _ = tokio::fs::remove_dir_all(&trusted_dir).await;
+        let fs_note = match fs_note {
+            Ok(note) => note,
+            Err(problems) => {
+                let message = format!("fs.img 中的测试程序与评测机的测试包不一致：\n{}", problems);
+                return Ok(RunOutcome::new(TestStatus::Failed, message, Some("fs.img 校验失败".to_string())));
+            }
+        };
+
+        // 根据测试包中的用例列表初始化看门狗
+        let expected_cases = watchdog::load_expected_cases(Path::new(TEST_BUNDLE_DIR));
+        let mut watchdog = Watchdog::new(profile.watchdog.clone(), expected_cases);
+
+        // 启动QEMU，同时开启monitor以便超时时获取CPU状态
+        let monitor_socket = qemu::monitor_socket_path(task.id);
+        let mut child = qemu::spawn_qemu(&os_dir, &monitor_socket, &profile.qemu_args())
+            .map_err(|e| format!("进程启动失败: {}", e))?;
+
+        // 发送测试指令到标准输入（直接启动模式下不需要shell）
+        let stdin_script = profile.stdin_script();
+        let stdin = child.stdin.take();
+        if let Some(mut stdin) = stdin.filter(|_| !stdin_script.is_empty()) {
+            tokio::spawn(async move {
+                stdin.write_all(stdin_script.as_bytes()).await?;
+                stdin.flush().await?;
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+            });
+        }
+
+        // 获取stdout并设置缓冲读取
+        let mut stdout = tokio::io::BufReader::new(child.stdout.take().unwrap());
+        let mut output = fs_note;
+        let mut buffer = [0; 1024];
+        let mut passed = false;
+        let mut hang_report = None;
+        let mut timeout_reason = None;
+        let mut reached_eof = false;
+
+        // 设置超时时间
+        let timeout = tokio::time::sleep_until(deadline);
+        tokio::pin!(timeout);
+
+        loop {
+            tokio::select! {
+                read_result = stdout.read(&mut buffer) => {
+                    match read_result {
+                        Ok(n) if n > 0 => {
+                            let chunk = String::from_utf8_lossy(&buffer[..n]).to_string();
+                            output.push_str(&chunk);
+                            watchdog.feed(&chunk);
+
+                            // 直接启动模式下等待QEMU自行关机，不根据输出内容判断
+                            if !profile.direct_boot && output.contains("Usertests passed!") {
+                                passed = true;
+                            }
+
+                            // 实时更新输出到数据库
+                            if let Err(e) = TestRepo::update_test_result(
+                                &self.db_pool,
+                                task.id,
+                                if passed { TestStatus::Passed } else { TestStatus::Running },
+                                Some(output.clone()),
+                                None,
+                            ).await {
+                                tracing::error!("Failed to update test output: {}", e);
+                            }
+
+                            // 如果测试通过或失败，立即终止qemu进程
+                            if !profile.direct_boot && (passed || output.contains("FAILED")) {
+                                break;
+                            }
+                        },
+                        Ok(_) => {
+                            // EOF
+                            reached_eof = true;
+                            break;
+                        }
+                        Err(e) => return Err(format!("读取输出失败: {}", e).into()),
+                    }
+                }
+                _ = &mut timeout => {
+                    // 终止前先通过monitor保存CPU状态
+                    hang_report = Some(qemu::collect_hang_report(&monitor_socket, "测试执行超时").await);
+                    timeout_reason = Some("测试执行超时".to_string());
+                    break;
+                }
+                _ = tokio::time::sleep_until(watchdog.deadline()) => {
+                    // 用例超时或长时间无输出时提前结束
+                    if let Some(reason) = watchdog.check(tokio::time::Instant::now()) {
+                        tracing::warn!("Watchdog fired for test {}: {}", task.id, reason);
+                        hang_report = Some(qemu::collect_hang_report(&monitor_socket, &reason).await);
+                        timeout_reason = Some(reason);
+                        break;
+                    }
+                }
+            }
+        }
+
+        // QEMU已自行退出时获取其退出状态，否则终止qemu进程
+        let exit_status = if reached_eof {
+            tokio::time::timeout(Duration::from_secs(5), child.wait()).await.ok().and_then(|r| r.ok())
+        } else {
+            None
+        };
+        if exit_status.is_none() {
+            if let Err(e) = child.kill().await {
+                tracing::warn!("Failed to kill qemu process: {}", e);
+            } else {
+                tracing::info!("Successfully terminated qemu process for test {}", task.id);
+            }
+        }
+        let _ = tokio::fs::remove_file(&monitor_socket).await;
+
+        // 直接启动模式：usertests 作为 initproc 退出后内核关机，
+        // 由其退出码和QEMU的退出状态共同决定是否通过
+        if profile.direct_boot && timeout_reason.is_none() {
+            let shutdown_ok = exit_status.map_or(false, |status| status.success());
+            let runner_ok = initproc_exit_code(&output).map_or(true, |code| code == 0);
+            passed = shutdown_ok && runner_ok;
+            tracing::info!(
+                "Test {} finished in direct boot mode: qemu exit status {:?}, initproc exit code {:?}",
+                task.id, exit_status, initproc_exit_code(&output)
+            );
+        }
+
+        // 符号化输出中的地址，并保存本次评测的产物
+        // 超时被打断时输出只有一部分，同样符号化；卡死报告中的pc和栈内容也是内核地址
+        let kernel_elf = os_dir.join(symbolize::KERNEL_ELF_PATH);
+        let mut output = symbolize_output(&kernel_elf, output).await;
+        let hang_report = match hang_report {
+            Some(report) => Some(symbolize_output(&kernel_elf, report).await),
+            None => None,
+        };
+
+        // 用测试包中的断言检查运行结束后 fs.img 的状态，结果作为额外的用例；没有文件系统的题目不检查
+        let fs_img = os_dir.join(qemu::FS_IMG_PATH);
+        let fs_results = if profile.fs_image == FsImageMode::NoFs {
+            None
+        } else {
+            tokio::task::spawn_blocking(move || fs_checks::run_checks(Path::new(TEST_BUNDLE_DIR), &fs_img))
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("文件系统检查任务异常退出: {}", e);
+                    None
+                })
+        };
+        let mut fs_failed = false;
+        let fs_cases = match fs_results {
+            Some((cases, report)) => {
+                output.push_str(&report);
+                fs_failed = cases.iter().any(|case| case.verdict != CaseVerdict::Passed);
+                cases
+            }
+            None => Vec::new(),
+        };
+
+        self.save_run_artifacts(task.id, &os_dir, &output).await;
+
+        // 根据测试输出结果判断状态
+        let (status, error, interrupted_verdict) = match timeout_reason {
+            Some(reason) => (TestStatus::Failed, Some(reason), CaseVerdict::TimeLimitExceeded),
+            None if passed && fs_failed => (TestStatus::Failed, Some("文件系统检查未通过".to_string()), CaseVerdict::Failed),
+            // 正常结束时仍在运行的用例视为失败（例如内核崩溃）
+            None if passed => (TestStatus::Passed, None, CaseVerdict::Failed),
+            None => (TestStatus::Failed, None, CaseVerdict::Failed),
+        };
+        let mut outcome = RunOutcome::new(status, output, error);
+        outcome.hang_report = hang_report;
+        outcome.cases = watchdog.into_results(interrupted_verdict);
+        outcome.cases.extend(fs_cases);
+        Ok(outcome)
+    }
+
+    // 确定内核启动时使用的 fs.img，返回附加在输出开头的说明；镜像中的测试程序被修改时返回不一致之处。
+    // trusted_user 是运行学生代码之前编译的可信 user 目录，直接使用学生镜像或没有文件系统时为 None
+    async fn prepare_fs_image(
+        &self,
+        test_id: i32,
+        profile: &JudgeProfile,
+        os_dir: &Path,
+        trusted_user: Option<Result<PathBuf, String>>,
+    ) -> Result<String, String> {
+        let trusted_user = match trusted_user {
+            None => return Ok(String::new()),
+            Some(Ok(dir)) => dir,
+            Some(Err(e)) => {
+                tracing::warn!("无法编译可信的测试包，评测 {} 不检查 fs.img: {}", test_id, e);
+                return Ok("[评测机] 无法编译可信的测试包，本次未检查 fs.img\n".to_string());
+            }
+        };
+        let bundle_dir = Path::new(TEST_BUNDLE_DIR);
+        let fs_img = os_dir.join(qemu::FS_IMG_PATH);
+
+        if profile.fs_image == FsImageMode::Judge {
+            if self.fs_packer.is_available() {
+                let out_dir = std::env::temp_dir().join(format!("oj-fsimg-{}", test_id));
+                let result = match self.fs_packer.build_image(&trusted_user, bundle_dir, &out_dir, RUST_TOOLCHAIN).await {
+                    Ok(image) => {
+                        if let Some(parent) = fs_img.parent() {
+                            let _ = tokio::fs::create_dir_all(parent).await;
+                        }
+                        tokio::fs::copy(&image, &fs_img).await.map_err(|e| format!("无法替换 fs.img: {}", e))
+                    }
+                    Err(e) => Err(e),
+                };
+                let _ = tokio::fs::remove_dir_all(&out_dir).await;
+                match result {
+                    Ok(_) => return Ok("[评测机] 使用评测机打包的 fs.img\n".to_string()),
+                    Err(e) => tracing::warn!("评测机打包 fs.img 失败，改为校验学生打包的镜像: {}", e),
+                }
+            } else {
+                tracing::warn!("未找到评测机的 easy-fs-fuse，改为校验学生打包的 fs.img");
+            }
+        }
+
+        let result = tokio::task::spawn_blocking(move || {
+            trusted_fs::verify_image(&fs_img, &trusted_user, Path::new(TEST_BUNDLE_DIR))
+        })
+        .await;
+        match result {
+            Ok(Ok(count)) => Ok(format!("[评测机] fs.img 校验通过，{} 个测试程序与测试包一致\n", count)),
+            Ok(Err(problems)) => Err(problems),
+            Err(e) => {
+                tracing::warn!("校验 fs.img 的任务异常退出: {}", e);
+                Ok("[评测机] 校验 fs.img 时出错，本次未检查 fs.img\n".to_string())
+            }
+        }
+    }
+
+    // 在宿主机上用评测机的测试集测试学生的 easy-fs，不需要QEMU
+    async fn run_easy_fs_suite(
+        &self,
+        task: &TestTask,
+        work_dir: &Path,
+        deadline: tokio::time::Instant,
+    ) -> Result<RunOutcome, Box<dyn std::error::Error + Send + Sync>> {
+        let easy_fs_dir = work_dir.join(easyfs::EASY_FS_DIR);
+        if !easy_fs_dir.join("Cargo.toml").exists() {
+            return Ok(RunOutcome::new(
+                TestStatus::Failed,
+                String::new(),
+                Some(format!("未找到 {} 目录", easyfs::EASY_FS_DIR)),
+            ));
+        }
+
+        let run_dir = std::env::temp_dir().join(format!("oj-easyfs-{}", task.id));
+        let envs = self.build_cache.cargo_env(work_dir, &run_dir, RUST_TOOLCHAIN, &self.vendor).await;
+        let result = easyfs::run_suite(&easy_fs_dir, &run_dir, RUST_TOOLCHAIN, envs, deadline).await;
+        let _ = tokio::fs::remove_dir_all(&run_dir).await;
+        let report = result?;
+
+        if let Err(e) = self.artifacts.save_bytes(task.id, ArtifactKind::Transcript, report.output.as_bytes()).await {
+            tracing::warn!("Failed to save transcript for test {}: {}", task.id, e);
+        }
+
+        let passed = report.build_ok
+            && !report.timed_out
+            && !report.cases.is_empty()
+            && report.cases.iter().all(|case| case.verdict == CaseVerdict::Passed);
+        let error = if report.timed_out {
+            Some("测试执行超时".to_string())
+        } else if !report.build_ok && self.vendor.is_enabled() && vendor::is_dependency_error(&report.output) {
+            Some("依赖不可用".to_string())
+        } else if !report.build_ok {
+            Some("编译失败".to_string())
+        } else {
+            None
+        };
+        let status = if passed { TestStatus::Passed } else { TestStatus::Failed };
+
+        let mut outcome = RunOutcome::new(status, report.output, error);
+        outcome.cases = report.cases;
+        Ok(outcome)
+    }
+
+    // 生成并保存复现包
+    async fn save_repro_bundle(&self, task: &TestTask, fingerprint: &str) {
+        let profile = self.profiles.get(&task.profile);
+        // 复现包只描述QEMU评测流程
+        if profile.backend != Backend::Qemu {
+            return;
+        }
+        let info = repro::ReproInfo {
+            test_id: task.id,
+            profile: profile.name.clone(),
+            toolchain: RUST_TOOLCHAIN.to_string(),
+            make_args: profile.make_args(),
+            qemu_args: qemu::qemu_args(&profile.qemu_args()),
+            stdin_script: profile.stdin_script().to_string(),
+            fingerprint: fingerprint.to_string(),
+        };
+
+        match repro::build_bundle(&info, Path::new(TEST_BUNDLE_DIR)).await {
+            Ok(bundle) => {
+                if let Err(e) = self.artifacts.save_bytes(task.id, ArtifactKind::Repro, &bundle).await {
+                    tracing::warn!("Failed to save repro bundle for test {}: {}", task.id, e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to build repro bundle for test {}: {}", task.id, e),
+        }
+    }
+
+    // 保存内核ELF、文件系统镜像和完整输出
+    async fn save_run_artifacts(&self, test_id: i32, os_dir: &Path, transcript: &str) {
+        let files = [
+            (ArtifactKind::Kernel, os_dir.join(symbolize::KERNEL_ELF_PATH)),
+            (ArtifactKind::FsImage, os_dir.join(qemu::FS_IMG_PATH)),
+        ];
+        for (kind, path) in files {
+            if let Err(e) = self.artifacts.save_file(test_id, kind, &path).await {
+                tracing::warn!("Failed to save {} for test {}: {}", kind.description(), test_id, e);
+            }
+        }
+        if let Err(e) = self.artifacts.save_bytes(test_id, ArtifactKind::Transcript, transcript.as_bytes()).await {
+            tracing::warn!("Failed to save transcript for test {}: {}", test_id, e);
+        }
+    }
+}
+
+// 从内核输出中解析 initproc（直接启动模式下即 usertests）的退出码
+fn initproc_exit_code(output: &str) -> Option<i32> {
+    let pos = output.rfind(INITPROC_EXIT_MARKER)?;
+    output[pos + INITPROC_EXIT_MARKER.len()..]
+        .split_whitespace()
+        .next()
+        .and_then(|code| code.parse().ok())
+}
+
+// 用未裁剪的内核ELF对输出中的地址进行符号化
+async fn symbolize_output(kernel_elf: &Path, output: String) -> String {
+    let kernel_elf = kernel_elf.to_path_buf();
+    let transcript = output.clone();
+    match tokio::task::spawn_blocking(move || symbolize::symbolize_transcript(&kernel_elf, &transcript)).await {
+        Ok(Ok(annotated)) => annotated,
+        Ok(Err(e)) => {
+            tracing::warn!("符号化失败: {}", e);
+            output
+        }
+        Err(e) => {
+            tracing::warn!("符号化任务异常退出: {}", e);
+            output
+        }
+    }
+}
+
+// 计算提交内容的哈希，忽略编译产物和会被测试包替换的 user 目录
+pub fn submission_hash(work_dir: &Path) -> std::io::Result<String> {
+    crate::digest::hash_dir(work_dir, &["target", TEST_BUNDLE_DIR])
+}
+
+// 当前测试包的版本
+pub fn bundle_hash() -> std::io::Result<String> {
+    crate::digest::hash_dir(Path::new(TEST_BUNDLE_DIR), &["target"])
+}
+
+// 递归复制目录树，复制出的文件都可以写入（源文件可能是指向存储的只读硬链接）
+fn copy_writable(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let entry_path = entry.path();
+        let dst_file = dst.join(entry.file_name());
+        if entry_path.is_dir() {
+            copy_writable(&entry_path, &dst_file)?;
+        } else {
+            std::fs::copy(&entry_path, &dst_file)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mut permissions = std::fs::metadata(&dst_file)?.permissions();
+                permissions.set_mode(permissions.mode() | 0o200);
+                std::fs::set_permissions(&dst_file, permissions)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// 递归复制目录
+pub fn copy_dir_recursively(src: &str, dst: &std::path::Path) -> std::io::Result<()> {
+    let src_path = std::path::Path::new(src);
+    let dst_path = dst.join(src_path.file_name().unwrap());
+
+    // 如果目标已存在，先删除
+    if dst_path.exists() {
+        std::fs::remove_dir_all(&dst_path)?;
+    }
+
+    // 创建目标目录
+    std::fs::create_dir_all(&dst_path)?;
+
+    // 遍历源目录中的所有条目
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let entry_path = entry.path();
+        let file_name = entry.file_name();
+        let dst_file = dst_path.join(file_name);
+
+        if entry_path.is_dir() {
+            // 递归复制子目录
+            copy_dir_recursively(entry_path.to_str().unwrap(), &dst_path)?;
+        } else {
+            // 复制文件
+            std::fs::copy(&entry_path, &dst_file)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use super::health::Tool;
+use super::watchdog::WatchdogConfig;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// 评测配置所在目录，每个题目一个 <name>.conf 文件
+pub const PROFILES_DIR: &str = "judge_profiles";
+pub const DEFAULT_PROFILE: &str = "default";
+
+// 评测后端
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Qemu,       // 编译内核并在QEMU中运行 usertests
+    EasyFsHost, // 在宿主机上用 cargo test 测试学生的 easy-fs
+}
+
+// 启动内核时使用的 fs.img 来源
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FsImageMode {
+    Student, // 直接使用学生工程打包的镜像（修改了磁盘布局的题目）
+    Verify,  // 使用学生打包的镜像，但先校验其中的测试程序与测试包一致
+    Judge,   // 使用评测机可信的 easy-fs-fuse 重新打包的镜像
+    NoFs,    // 本章没有文件系统，不检查 fs.img，也不运行文件系统断言
+}
+
+// 单个题目的评测配置
+#[derive(Debug, Clone)]
+pub struct JudgeProfile {
+    pub name: String,
+    pub backend: Backend,
+    pub fs_image: FsImageMode,
+    pub deterministic: bool,     // 是否使用 -icount 确定性执行
+    pub icount_shift: u32,       // 每条指令对应 2^shift 纳秒虚拟时间
+    pub rng_seed: Option<u64>,   // 固定的随机数种子
+    pub direct_boot: bool,       // 以 TEST=1 编译，内核直接启动 usertests
+    pub required_paths: Vec<String>, // 提交中还必须包含的文件（相对于工程根目录）
+    pub baseline: Option<PathBuf>, // 本章的初始代码，用于对比学生的修改
+    pub watchdog: WatchdogConfig,
+}
+
+impl JudgeProfile {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            backend: Backend::Qemu,
+            fs_image: FsImageMode::Judge,
+            deterministic: false,
+            icount_shift: 5,
+            rng_seed: None,
+            direct_boot: false,
+            required_paths: Vec::new(),
+            baseline: None,
+            watchdog: WatchdogConfig::from_env(),
+        }
+    }
+
+    // 解析 key = value 格式的配置文件，# 开头为注释
+    fn parse(name: &str, content: &str) -> Result<Self, String> {
+        let mut profile = Self::new(name);
+
+        for (lineno, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("第 {} 行格式错误: {}", lineno + 1, line))?;
+            let invalid = || format!("第 {} 行的值无效: {} = {}", lineno + 1, key, value);
+
+            match key {
+                "backend" => {
+                    profile.backend = match value {
+                        "qemu" => Backend::Qemu,
+                        "easy-fs-host" => Backend::EasyFsHost,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "fs_image" => {
+                    profile.fs_image = match value {
+                        "student" => FsImageMode::Student,
+                        "verify" => FsImageMode::Verify,
+                        "judge" => FsImageMode::Judge,
+                        "none" => FsImageMode::NoFs,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "deterministic" => profile.deterministic = value.parse().map_err(|_| invalid())?,
+                "icount_shift" => profile.icount_shift = value.parse().map_err(|_| invalid())?,
+                "rng_seed" => profile.rng_seed = Some(value.parse().map_err(|_| invalid())?),
+                "direct_boot" => profile.direct_boot = value.parse().map_err(|_| invalid())?,
+                "required_paths" => {
+                    profile.required_paths = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|path| !path.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                }
+                "baseline" => profile.baseline = Some(PathBuf::from(value)),
+                "case_timeout_secs" => {
+                    profile.watchdog.case_timeout = Duration::from_secs(value.parse().map_err(|_| invalid())?)
+                }
+                "idle_timeout_secs" => {
+                    profile.watchdog.idle_timeout = Duration::from_secs(value.parse().map_err(|_| invalid())?)
+                }
+                _ => tracing::warn!("评测配置 {} 中有未知的配置项: {}", name, key),
+            }
+        }
+
+        Ok(profile)
+    }
+
+    // 评测这个题目需要的外部工具
+    pub fn required_tools(&self) -> Vec<Tool> {
+        match self.backend {
+            Backend::Qemu => Tool::ALL.to_vec(),
+            // 只在宿主机上编译运行，不需要交叉编译目标和QEMU
+            Backend::EasyFsHost => vec![Tool::Toolchain],
+        }
+    }
+
+    // 提交中必须包含的文件，第一项用于定位工程根目录
+    pub fn layout_requirements(&self) -> Vec<String> {
+        let mut paths: Vec<String> = match self.backend {
+            Backend::Qemu => vec!["os/Cargo.toml", "os/Makefile", "bootloader/rustsbi-qemu.bin"],
+            Backend::EasyFsHost => vec!["easy-fs/Cargo.toml"],
+        }
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        for path in &self.required_paths {
+            if !paths.contains(path) {
+                paths.push(path.clone());
+            }
+        }
+        paths
+    }
+
+    // 相似度分析比较的源码目录（相对于工程根目录）
+    pub fn source_dir(&self) -> &'static str {
+        match self.backend {
+            Backend::Qemu => "os/src",
+            Backend::EasyFsHost => "easy-fs/src",
+        }
+    }
+
+    // 编译内核和文件系统镜像时传给make的参数
+    pub fn make_args(&self) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
+        if self.direct_boot {
+            // usertests 替换 initproc，内核启动后直接运行测试
+            args.push("TEST=1".to_string());
+        }
+        args
+    }
+
+    // 启动后通过标准输入发送给shell的内容（直接启动模式下不需要shell）
+    pub fn stdin_script(&self) -> &'static str {
+        if self.direct_boot {
+            ""
+        } else {
+            "usertests\n"
+        }
+    }
+
+    // 传给QEMU的额外参数
+    pub fn qemu_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.deterministic {
+            // sleep=off 时虚拟时钟不等待真实时间，结果与宿主机负载无关
+            args.push("-icount".to_string());
+            args.push(format!("shift={},align=off,sleep=off", self.icount_shift));
+        }
+        if let Some(seed) = self.rng_seed {
+            args.push("-seed".to_string());
+            args.push(seed.to_string());
+        }
+        args
+    }
+}
+
+// 所有题目的评测配置
+pub struct ProfileRegistry {
+    profiles: BTreeMap<String, JudgeProfile>,
+}
+
+impl ProfileRegistry {
+    // 从配置目录加载所有评测配置，始终包含默认配置
+    pub fn load() -> Self {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(DEFAULT_PROFILE.to_string(), JudgeProfile::new(DEFAULT_PROFILE));
+
+        match std::fs::read_dir(PROFILES_DIR) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension().and_then(|e| e.to_str()) != Some("conf") {
+                        continue;
+                    }
+                    let name = match path.file_stem().and_then(|s| s.to_str()) {
+                        Some(name) => name.to_string(),
+                        None => continue,
+                    };
+                    match load_profile(&path, &name) {
+                        Ok(profile) => {
+                            tracing::info!("加载评测配置: {}", name);
+                            profiles.insert(name, profile);
+                        }
+                        Err(e) => tracing::error!("无法加载评测配置 {}: {}", path.display(), e),
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("无法读取评测配置目录 {}: {}", PROFILES_DIR, e),
+        }
+
+        Self { profiles }
+    }
+
+    // 获取指定的配置，不存在时返回默认配置
+    pub fn get(&self, name: &str) -> &JudgeProfile {
+        self.profiles
+            .get(name)
+            .or_else(|| self.profiles.get(DEFAULT_PROFILE))
+            .expect("默认评测配置总是存在")
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.profiles.contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+}
+
+fn load_profile(path: &Path, name: &str) -> Result<JudgeProfile, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    JudgeProfile::parse(name, &content)
+}
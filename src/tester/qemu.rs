@@ -0,0 +1,265 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::process::{Child, Command};
+
+// 以下路径均相对于学生工程的os目录，与rCore-Tutorial的os/Makefile保持一致
+const KERNEL_BIN_PATH: &str = "target/riscv64gc-unknown-none-elf/release/os.bin";
+pub const FS_IMG_PATH: &str = "../user/target/riscv64gc-unknown-none-elf/release/fs.img";
+const BOOTLOADER_PATH: &str = "../bootloader/rustsbi-qemu.bin";
+const KERNEL_ENTRY_PA: &str = "0x80200000";
+
+// 与monitor交互时单条命令的最长等待时间
+const MONITOR_TIMEOUT: Duration = Duration::from_secs(5);
+const MONITOR_PROMPT: &str = "(qemu) ";
+
+pub const QEMU_PROGRAM: &str = "qemu-system-riscv64";
+
+// 运行内核所需的QEMU参数（在os目录下执行）
+pub fn qemu_args(extra_args: &[String]) -> Vec<String> {
+    let mut args: Vec<String> = ["-machine", "virt", "-nographic", "-bios", BOOTLOADER_PATH]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    args.push("-device".to_string());
+    args.push(format!("loader,file={},addr={}", KERNEL_BIN_PATH, KERNEL_ENTRY_PA));
+    args.push("-drive".to_string());
+    args.push(format!("file={},if=none,format=raw,id=x0", FS_IMG_PATH));
+    args.push("-device".to_string());
+    args.push("virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0".to_string());
+    args.extend_from_slice(extra_args);
+    args
+}
+
+// 启动QEMU，并为其开启一个HMP monitor socket
+pub fn spawn_qemu(os_dir: &Path, monitor_socket: &Path, extra_args: &[String]) -> std::io::Result<Child> {
+    // 上一次运行残留的socket会导致QEMU无法监听
+    let _ = std::fs::remove_file(monitor_socket);
+
+    Command::new(QEMU_PROGRAM)
+        .args(qemu_args(extra_args))
+        .arg("-monitor")
+        .arg(format!("unix:{},server,nowait", monitor_socket.display()))
+        .current_dir(os_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+// 每个评测任务使用独立的monitor socket
+pub fn monitor_socket_path(test_id: i32) -> PathBuf {
+    std::env::temp_dir().join(format!("oj-qemu-monitor-{}.sock", test_id))
+}
+
+// 简单的HMP monitor客户端
+struct Monitor {
+    stream: UnixStream,
+}
+
+impl Monitor {
+    async fn connect(path: &Path) -> Result<Self, String> {
+        let stream = UnixStream::connect(path)
+            .await
+            .map_err(|e| format!("无法连接QEMU monitor: {}", e))?;
+        let mut monitor = Monitor { stream };
+        // 丢弃欢迎信息，等待第一个提示符
+        monitor.read_until_prompt().await?;
+        Ok(monitor)
+    }
+
+    async fn read_until_prompt(&mut self) -> Result<String, String> {
+        let mut raw = Vec::new();
+        let mut buffer = [0u8; 1024];
+
+        let read = async {
+            loop {
+                let n = self
+                    .stream
+                    .read(&mut buffer)
+                    .await
+                    .map_err(|e| format!("读取monitor输出失败: {}", e))?;
+                if n == 0 {
+                    return Err("QEMU monitor连接已关闭".to_string());
+                }
+                raw.extend_from_slice(&buffer[..n]);
+                if String::from_utf8_lossy(&raw).ends_with(MONITOR_PROMPT) {
+                    return Ok(());
+                }
+            }
+        };
+        tokio::time::timeout(MONITOR_TIMEOUT, read)
+            .await
+            .map_err(|_| "等待QEMU monitor响应超时".to_string())??;
+
+        let text = strip_escape_sequences(&String::from_utf8_lossy(&raw));
+        Ok(text.trim_end_matches(MONITOR_PROMPT).to_string())
+    }
+
+    async fn command(&mut self, cmd: &str) -> Result<String, String> {
+        self.stream
+            .write_all(format!("{}\n", cmd).as_bytes())
+            .await
+            .map_err(|e| format!("发送monitor命令失败: {}", e))?;
+        let response = self.read_until_prompt().await?;
+        // monitor会回显命令本身，去掉第一行
+        Ok(response
+            .split_once('\n')
+            .map(|(_, rest)| rest.to_string())
+            .unwrap_or_default()
+            .replace('\r', ""))
+    }
+}
+
+// 去掉monitor回显中的终端控制序列
+fn strip_escape_sequences(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.peek() == Some(&'[') {
+                chars.next();
+                // CSI序列以 0x40..=0x7e 范围内的字符结束
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+// 从 info registers 的输出中取出某个寄存器的值
+fn parse_register(registers: &str, name: &str) -> Option<u64> {
+    let mut tokens = registers.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == name || token.split('/').any(|alias| alias == name) {
+            return tokens
+                .next()
+                .and_then(|value| u64::from_str_radix(value.trim_start_matches("0x"), 16).ok());
+        }
+    }
+    None
+}
+
+// 从 info registers 的输出中取出当前特权级，较新的QEMU会输出 priv 一项（数字或 U/S/M）
+fn parse_privilege(registers: &str) -> Option<&'static str> {
+    let mut tokens = registers.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == "priv" || token == "priv:" {
+            let value = tokens.next().filter(|value| *value != "=").or_else(|| tokens.next())?;
+            return match value.trim_start_matches("0x") {
+                "0" | "U" => Some("U (用户程序)"),
+                "1" | "S" => Some("S (内核)"),
+                "3" | "M" => Some("M (RustSBI)"),
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+// monitor 没有给出特权级时，根据pc所在的地址范围推测
+fn guess_privilege(pc: u64) -> &'static str {
+    if (0x8000_0000..0x8020_0000).contains(&pc) {
+        "M (RustSBI)"
+    } else if pc >= 0x8020_0000 {
+        "S (内核)"
+    } else {
+        "U (用户程序)"
+    }
+}
+
+// 通过monitor获取CPU状态，生成卡死报告
+pub async fn collect_hang_report(monitor_socket: &Path, reason: &str) -> String {
+    let mut report = format!("原因: {}\n", reason);
+
+    let mut monitor = match Monitor::connect(monitor_socket).await {
+        Ok(monitor) => monitor,
+        Err(e) => {
+            report.push_str(&format!("无法获取CPU状态: {}\n", e));
+            return report;
+        }
+    };
+
+    let registers = match monitor.command("info registers").await {
+        Ok(registers) => registers,
+        Err(e) => {
+            report.push_str(&format!("info registers 失败: {}\n", e));
+            return report;
+        }
+    };
+
+    let pc = parse_register(&registers, "pc");
+    let sp = parse_register(&registers, "sp");
+
+    match (parse_privilege(&registers), pc) {
+        (Some(privilege), _) => report.push_str(&format!("特权级: {}\n", privilege)),
+        (None, Some(pc)) => report.push_str(&format!(
+            "特权级: {}（monitor 未给出 priv，根据pc所在的地址推测，仅供参考）\n",
+            guess_privilege(pc)
+        )),
+        (None, None) => {}
+    }
+    report.push_str("\n== info registers ==\n");
+    report.push_str(&registers);
+
+    if let Some(pc) = pc {
+        match monitor.command(&format!("x /8i {:#x}", pc)).await {
+            Ok(dump) => report.push_str(&format!("\n== pc附近的指令 ==\n{}", dump)),
+            Err(e) => report.push_str(&format!("\n无法读取pc附近的指令: {}\n", e)),
+        }
+    }
+    if let Some(sp) = sp {
+        match monitor.command(&format!("x /16xg {:#x}", sp)).await {
+            Ok(dump) => report.push_str(&format!("\n== 栈内存 ==\n{}", dump)),
+            Err(e) => report.push_str(&format!("\n无法读取栈内存: {}\n", e)),
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTERS: &str = " pc       0000000080203a5c
+ mhartid  0000000000000000
+ mstatus  0000000a000000a0
+ x0/zero  0000000000000000 x1/ra    0000000080200f3e x2/sp    0000000080213f60 x3/gp    0000000000000000
+";
+
+    #[test]
+    fn parses_registers_by_name_and_alias() {
+        assert_eq!(parse_register(REGISTERS, "pc"), Some(0x8020_3a5c));
+        assert_eq!(parse_register(REGISTERS, "sp"), Some(0x8021_3f60));
+        assert_eq!(parse_register(REGISTERS, "x1"), Some(0x8020_0f3e));
+        assert_eq!(parse_register(REGISTERS, "t0"), None);
+    }
+
+    #[test]
+    fn reads_privilege_from_monitor_when_reported() {
+        assert_eq!(parse_privilege(REGISTERS), None);
+        assert_eq!(parse_privilege(&format!(" priv     U\n{}", REGISTERS)), Some("U (用户程序)"));
+        assert_eq!(parse_privilege(" priv = 3\n"), Some("M (RustSBI)"));
+        assert_eq!(parse_privilege(" priv 1\n"), Some("S (内核)"));
+        assert_eq!(parse_privilege(" priv 2\n"), None);
+    }
+
+    #[test]
+    fn guesses_privilege_from_pc() {
+        assert_eq!(guess_privilege(0x8000_0100), "M (RustSBI)");
+        assert_eq!(guess_privilege(0x8020_3a5c), "S (内核)");
+        assert_eq!(guess_privilege(0x1_0000), "U (用户程序)");
+    }
+}
@@ -0,0 +1,172 @@
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+
+// 复现一次评测所需的全部信息
+pub struct ReproInfo {
+    pub test_id: i32,
+    pub profile: String,
+    pub toolchain: String,
+    pub make_args: Vec<String>,
+    pub qemu_args: Vec<String>,
+    pub stdin_script: String,
+    pub fingerprint: String, // 评测机环境指纹
+}
+
+// 给shell参数加上单引号
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+fn repro_script(info: &ReproInfo) -> String {
+    let make_args = info.make_args.iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" ");
+    let qemu_args = info.qemu_args.iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" ");
+    let stdin_cmd = if info.stdin_script.is_empty() {
+        "cat /dev/null".to_string()
+    } else {
+        format!("printf {}", shell_quote(&info.stdin_script.replace('\n', "\\n")))
+    };
+
+    format!(
+        r#"#!/bin/sh
+# 复现评测 #{test_id}（题目: {profile}）
+# 用法: ./repro.sh <工程根目录，即包含 os/ 和 user/ 的目录>
+set -e
+
+BUNDLE_DIR="$(cd "$(dirname "$0")" && pwd)"
+if [ $# -ne 1 ]; then
+    echo "用法: $0 <工程根目录，即包含 os/ 和 user/ 的目录>" >&2
+    exit 1
+fi
+PROJECT_DIR="$1"
+
+# 下面会删除工程中的 user 目录，目录结构不对时拒绝运行，避免误删其他目录
+if [ ! -d "$PROJECT_DIR/os" ] || [ ! -d "$PROJECT_DIR/user" ]; then
+    echo "$PROJECT_DIR 下没有 os/ 和 user/ 目录，请指定正确的工程根目录" >&2
+    exit 1
+fi
+
+# 使用评测时注入的测试包替换工程中的 user 目录
+rm -rf "$PROJECT_DIR/user"
+cp -r "$BUNDLE_DIR/user" "$PROJECT_DIR/user"
+
+cd "$PROJECT_DIR/os"
+export RUSTUP_TOOLCHAIN={toolchain}
+make {make_args}
+
+{stdin_cmd} | qemu-system-riscv64 {qemu_args}
+"#,
+        test_id = info.test_id,
+        profile = info.profile,
+        toolchain = shell_quote(&info.toolchain),
+        make_args = make_args,
+        stdin_cmd = stdin_cmd,
+        qemu_args = qemu_args,
+    )
+}
+
+// 生成复现包：测试包、命令行、标准输入、版本信息和 repro.sh
+pub async fn build_bundle(info: &ReproInfo, bundle_dir: &Path) -> Result<Vec<u8>, String> {
+    let readme = format!(
+        "评测 #{}\n题目: {}\n\n编译命令: RUSTUP_TOOLCHAIN={} make {}\nQEMU命令: qemu-system-riscv64 {}\n\n评测环境:\n{}",
+        info.test_id,
+        info.profile,
+        info.toolchain,
+        info.make_args.join(" "),
+        info.qemu_args.join(" "),
+        info.fingerprint,
+    );
+    let script = repro_script(info);
+    let stdin_script = info.stdin_script.clone();
+    let bundle_dir = bundle_dir.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default();
+
+        add_file(&mut zip, "README.txt", readme.as_bytes(), options)?;
+        add_file(&mut zip, "stdin.txt", stdin_script.as_bytes(), options)?;
+        add_file(&mut zip, "repro.sh", script.as_bytes(), options.unix_permissions(0o755))?;
+
+        add_dir(&mut zip, &bundle_dir, "user", options)?;
+
+        let cursor = zip.finish().map_err(|e| format!("生成复现包失败: {}", e))?;
+        Ok(cursor.into_inner())
+    })
+    .await
+    .map_err(|e| format!("生成复现包的任务异常退出: {}", e))?
+}
+
+fn add_file(
+    zip: &mut zip::ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    data: &[u8],
+    options: FileOptions,
+) -> Result<(), String> {
+    zip.start_file(name, options).map_err(|e| format!("写入 {} 失败: {}", name, e))?;
+    zip.write_all(data).map_err(|e| format!("写入 {} 失败: {}", name, e))
+}
+
+// 递归地把目录加入压缩包，跳过编译产物
+fn add_dir(
+    zip: &mut zip::ZipWriter<Cursor<Vec<u8>>>,
+    dir: &Path,
+    prefix: &str,
+    options: FileOptions,
+) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("无法读取目录 {}: {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            if entry.file_name() == "target" {
+                continue;
+            }
+            zip.add_directory(name.as_str(), options)
+                .map_err(|e| format!("写入 {} 失败: {}", name, e))?;
+            add_dir(zip, &path, &name, options)?;
+        } else {
+            let data = std::fs::read(&path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+            add_file(zip, &name, &data, options)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    fn info() -> ReproInfo {
+        ReproInfo {
+            test_id: 3,
+            profile: "ch5".to_string(),
+            toolchain: "nightly".to_string(),
+            make_args: vec!["build".to_string()],
+            qemu_args: vec!["-machine".to_string(), "virt".to_string()],
+            stdin_script: String::new(),
+            fingerprint: String::new(),
+        }
+    }
+
+    #[test]
+    fn repro_script_refuses_to_run_outside_a_project() {
+        let base = std::env::temp_dir().join(format!("repro_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("user")).unwrap();
+        std::fs::write(base.join("user/keep.txt"), "keep").unwrap();
+        let script = base.join("repro.sh");
+        std::fs::write(&script, repro_script(&info())).unwrap();
+
+        // 没有参数
+        let status = Command::new("sh").arg(&script).current_dir(&base).stderr(Stdio::null()).status().unwrap();
+        assert!(!status.success());
+        // 目录中只有 user/，没有 os/
+        let status = Command::new("sh").arg(&script).arg(&base).stderr(Stdio::null()).status().unwrap();
+        assert!(!status.success());
+
+        assert!(base.join("user/keep.txt").exists());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
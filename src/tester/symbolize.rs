@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// 内核ELF在学生工程中的位置（相对于os目录）
+pub const KERNEL_ELF_PATH: &str = "target/riscv64gc-unknown-none-elf/release/os";
+
+// 只符号化落在内核地址空间附近的地址，避免把普通数字误当成地址
+const MIN_KERNEL_ADDR: u64 = 0x8000_0000;
+
+// 从一行文本中提取所有 0x 开头的十六进制地址
+fn extract_addresses(line: &str) -> Vec<u64> {
+    let bytes = line.as_bytes();
+    let mut addrs = Vec::new();
+    let mut i = 0;
+
+    while i + 2 < bytes.len() {
+        if bytes[i] == b'0' && (bytes[i + 1] == b'x' || bytes[i + 1] == b'X') {
+            let start = i + 2;
+            let mut end = start;
+            while end < bytes.len() && bytes[end].is_ascii_hexdigit() {
+                end += 1;
+            }
+            if end > start && end - start <= 16 {
+                if let Ok(addr) = u64::from_str_radix(&line[start..end], 16) {
+                    if addr >= MIN_KERNEL_ADDR {
+                        addrs.push(addr);
+                    }
+                }
+            }
+            i = end.max(i + 1);
+        } else {
+            i += 1;
+        }
+    }
+
+    addrs
+}
+
+// 查询单个地址对应的函数名和源码位置（包含内联展开的调用链）
+fn lookup(loader: &addr2line::Loader, addr: u64) -> Option<String> {
+    let mut parts = Vec::new();
+
+    if let Ok(mut frames) = loader.find_frames(addr) {
+        while let Ok(Some(frame)) = frames.next() {
+            let function = frame
+                .function
+                .as_ref()
+                .and_then(|f| f.demangle().ok().map(|s| s.into_owned()))
+                .unwrap_or_else(|| "??".to_string());
+            let location = frame
+                .location
+                .as_ref()
+                .map(|loc| format!("{}:{}", loc.file.unwrap_or("??"), loc.line.unwrap_or(0)))
+                .unwrap_or_else(|| "??:0".to_string());
+            parts.push(format!("{} at {}", function, location));
+        }
+    }
+
+    if parts.is_empty() {
+        // 没有调试信息时退回到符号表
+        return loader
+            .find_symbol(addr)
+            .map(|name| addr2line::demangle_auto(name.into(), None).into_owned());
+    }
+
+    Some(parts.join(" (inlined into) "))
+}
+
+// 使用内核ELF对输出中的地址进行符号化，并在对应行之后插入注释
+pub fn symbolize_transcript(elf_path: &Path, transcript: &str) -> Result<String, String> {
+    let loader = addr2line::Loader::new(elf_path)
+        .map_err(|e| format!("无法加载内核ELF {}: {}", elf_path.display(), e))?;
+    Ok(annotate(transcript, |addr| lookup(&loader, addr)))
+}
+
+// 在含有地址的行之后插入 resolve 给出的符号。
+// 超时被打断的输出最后一行可能没有换行，注释前先补上换行
+fn annotate(transcript: &str, mut resolve: impl FnMut(u64) -> Option<String>) -> String {
+    let mut cache: BTreeMap<u64, Option<String>> = BTreeMap::new();
+    let mut annotated = String::with_capacity(transcript.len());
+    let mut resolved = 0;
+
+    for line in transcript.split_inclusive('\n') {
+        annotated.push_str(line);
+
+        let addrs = extract_addresses(line);
+        if addrs.is_empty() {
+            continue;
+        }
+
+        let mut notes = Vec::new();
+        for addr in addrs {
+            let symbol = cache
+                .entry(addr)
+                .or_insert_with(|| resolve(addr))
+                .clone();
+            if let Some(symbol) = symbol {
+                notes.push(format!("    [symbolized] {:#x} => {}\n", addr, symbol));
+            }
+        }
+
+        if !notes.is_empty() {
+            if !line.ends_with('\n') {
+                annotated.push('\n');
+            }
+            resolved += notes.len();
+            for note in notes {
+                annotated.push_str(&note);
+            }
+        }
+    }
+
+    tracing::debug!("符号化完成，共解析 {} 个地址", resolved);
+    annotated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_only_kernel_addresses() {
+        assert_eq!(
+            extract_addresses("[kernel] Panicked at src/trap/mod.rs:60 sepc=0x80201a3c, stval = 0x10"),
+            vec![0x8020_1a3c]
+        );
+        assert_eq!(extract_addresses("ra 0X0000000080200F3E sp 0x0"), vec![0x8020_0f3e]);
+        assert!(extract_addresses("0x").is_empty());
+    }
+
+    #[test]
+    fn annotates_partial_transcript_cut_by_timeout() {
+        let transcript = "[kernel] IllegalInstruction in application, sepc = 0x80201a3c\nsp=0x80213f60";
+        let annotated = annotate(transcript, |addr| (addr == 0x8020_1a3c).then(|| "os::trap::trap_handler".to_string()));
+        assert_eq!(
+            annotated,
+            "[kernel] IllegalInstruction in application, sepc = 0x80201a3c\n\
+             \x20   [symbolized] 0x80201a3c => os::trap::trap_handler\n\
+             sp=0x80213f60"
+        );
+
+        let annotated = annotate("ra=0x80200f3e", |_| Some("os::main".to_string()));
+        assert_eq!(annotated, "ra=0x80200f3e\n    [symbolized] 0x80200f3e => os::main\n");
+    }
+}
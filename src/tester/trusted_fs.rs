@@ -0,0 +1,182 @@
+use super::cache::absolute;
+use super::efs_image::EfsImage;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+
+// 测试程序的编译产物在 user 目录中的位置
+pub const APPS_TARGET_DIR: &str = "target/riscv64gc-unknown-none-elf/release";
+
+// 评测机自己的 easy-fs-fuse（连同它依赖的 easy-fs），不使用学生工程中的打包工具
+pub struct FsPacker {
+    dir: PathBuf,
+}
+
+impl FsPacker {
+    // 从环境变量读取配置：
+    // JUDGE_FS_PACKER_DIR  可信的 easy-fs-fuse 目录，默认 judge_tools/easy-fs-fuse
+    pub fn from_env() -> Self {
+        Self {
+            dir: PathBuf::from(
+                std::env::var("JUDGE_FS_PACKER_DIR").unwrap_or_else(|_| "judge_tools/easy-fs-fuse".to_string()),
+            ),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.dir.join("Cargo.toml").exists()
+    }
+
+    // 用评测机编译的测试程序打包 fs.img，返回生成的镜像路径。
+    // 与 rCore-Tutorial 的 os/Makefile 一样，程序列表取自测试包的 src/bin
+    pub async fn build_image(
+        &self,
+        trusted_user: &Path,
+        bundle_dir: &Path,
+        out_dir: &Path,
+        toolchain: &str,
+    ) -> Result<PathBuf, String> {
+        let apps_dir = out_dir.join("apps");
+        tokio::fs::create_dir_all(&apps_dir).await.map_err(|e| e.to_string())?;
+        for app in app_names(bundle_dir)? {
+            tokio::fs::copy(trusted_user.join(APPS_TARGET_DIR).join(&app), apps_dir.join(&app))
+                .await
+                .map_err(|e| format!("无法复制测试程序 {}: {}", app, e))?;
+        }
+
+        // easy-fs-fuse 直接拼接路径，目录末尾需要有 /
+        let source = absolute(&bundle_dir.join("src/bin"));
+        let target = absolute(&apps_dir);
+        let output = Command::new("cargo")
+            .args(["run", "--release", "--"])
+            .arg("--source")
+            .arg(format!("{}/", source.display()))
+            .arg("--target")
+            .arg(format!("{}/", target.display()))
+            .env("RUSTUP_TOOLCHAIN", toolchain)
+            .current_dir(&self.dir)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| format!("无法运行 easy-fs-fuse: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("打包 fs.img 失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+
+        let image = apps_dir.join("fs.img");
+        if !image.exists() {
+            return Err("easy-fs-fuse 没有生成 fs.img".to_string());
+        }
+        Ok(image)
+    }
+}
+
+// 逐个比较学生打包的 fs.img 中的测试程序与评测机编译的版本，返回校验通过的程序数或所有不一致之处
+pub fn verify_image(fs_img: &Path, trusted_user: &Path, bundle_dir: &Path) -> Result<usize, String> {
+    let image = EfsImage::open(fs_img).map_err(|e| format!("无法解析 fs.img: {}", e))?;
+    let apps = app_names(bundle_dir)?;
+    compare_apps(&apps, &trusted_user.join(APPS_TARGET_DIR), |app| match image.lookup(app)? {
+        Some(inode) if !inode.is_dir => image.read_inode_data(&inode),
+        Some(_) => Err(format!("{} 是目录", app)),
+        None => Err(format!("{} 不存在", app)),
+    })
+}
+
+// 比较 read_actual 读出的测试程序与 trusted_dir 中评测机编译的版本，读取失败的程序也算作不一致。
+// 两边都以 TRIM_PATHS_ENV 编译，产物中不含编译目录，可以直接逐字节比较
+fn compare_apps(
+    apps: &[String],
+    trusted_dir: &Path,
+    read_actual: impl Fn(&str) -> Result<Vec<u8>, String>,
+) -> Result<usize, String> {
+    let mut problems = Vec::new();
+    for app in apps {
+        let expected = std::fs::read(trusted_dir.join(app)).map_err(|e| format!("无法读取评测机编译的 {}: {}", app, e))?;
+        match read_actual(app) {
+            Ok(actual) if actual == expected => {}
+            Ok(actual) => problems.push(format!(
+                "{} 与测试包编译出的程序不一致（{} 字节，期望 {} 字节）",
+                app,
+                actual.len(),
+                expected.len()
+            )),
+            Err(problem) => problems.push(problem),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(apps.len())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+// 测试包中的所有用户程序，即 src/bin 下的文件名
+fn app_names(bundle_dir: &Path) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(bundle_dir.join("src/bin")).map_err(|e| format!("无法读取测试包: {}", e))?;
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+        .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tester::cache::TRIM_PATHS_ENV;
+    use crate::tester::RUST_TOOLCHAIN;
+
+    // 在 dir 下创建一个带调试信息的工程并编译，返回编译产物所在目录
+    fn build_app(dir: &Path) -> PathBuf {
+        std::fs::create_dir_all(dir.join("src/bin")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"user_lib\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n[profile.release]\ndebug = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("src/bin/hello_world.rs"),
+            "fn main() {\n    let args: Vec<String> = std::env::args().collect();\n    println!(\"{}\", args[3]);\n}\n",
+        )
+        .unwrap();
+        let output = std::process::Command::new("cargo")
+            .args(["build", "--release", "--offline"])
+            .envs(TRIM_PATHS_ENV)
+            .env("RUSTUP_TOOLCHAIN", RUST_TOOLCHAIN)
+            .env_remove("CARGO_TARGET_DIR")
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        dir.join("target/release")
+    }
+
+    #[test]
+    #[ignore = "需要安装评测使用的 nightly 工具链"]
+    fn apps_built_in_different_dirs_verify() {
+        let base = std::env::temp_dir().join(format!("oj_trusted_fs_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        // 评测机的可信编译与学生工程中的编译位于不同目录，目录名的长度也不同
+        let trusted = build_app(&base.join("trusted/user"));
+        let student = build_app(&base.join("work/some-student/project/user"));
+
+        let apps = vec!["hello_world".to_string()];
+        let read = |dir: &Path, app: &str| std::fs::read(dir.join(app)).map_err(|e| e.to_string());
+        assert_eq!(compare_apps(&apps, &trusted, |app| read(&student, app)), Ok(1));
+
+        let mut tampered = read(&student, "hello_world").unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(compare_apps(&apps, &trusted, |_| Ok(tampered.clone())).is_err());
+        assert_eq!(
+            compare_apps(&apps, &trusted, |app| Err(format!("{} 不存在", app))),
+            Err("hello_world 不存在".to_string())
+        );
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
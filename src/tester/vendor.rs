@@ -0,0 +1,546 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+// 需要检查依赖的 Cargo.lock（相对于学生工程根目录）
+const LOCK_FILES: [&str; 4] = ["os/Cargo.lock", "easy-fs/Cargo.lock", "easy-fs-fuse/Cargo.lock", "user/Cargo.lock"];
+
+const CRATES_IO_SOURCE: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+// 镜像目录中保存的 cargo vendor 输出（source 配置），git 依赖需要按其中的 source 替换
+pub const SOURCES_FILE: &str = "sources.toml";
+
+// cargo 在离线模式下找不到依赖时的报错
+const OFFLINE_ERROR_PATTERNS: [&str; 4] = [
+    "no matching package named",
+    "failed to select a version",
+    "--offline was specified",
+    "can't checkout from",
+];
+
+// crate 的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CrateSource {
+    CratesIo,
+    Git,
+}
+
+impl CrateSource {
+    pub fn description(&self) -> &'static str {
+        match self {
+            CrateSource::CratesIo => "crates.io",
+            CrateSource::Git => "git",
+        }
+    }
+}
+
+// 镜像中的一个crate
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VendoredCrate {
+    pub name: String,
+    pub version: String,
+    pub source: CrateSource,
+}
+
+// cargo vendor 输出的一个 git 源，如 [source."git+https://github.com/rcore-os/riscv"]
+#[derive(Debug, Clone, PartialEq)]
+struct GitSource {
+    id: String,                     // 与 Cargo.lock 中 source 去掉 #提交 之后的部分相同
+    entries: Vec<(String, String)>, // git 以及 rev、branch 或 tag
+}
+
+// Cargo.lock 中的一个包，source 为 None 的是工程内的路径依赖
+#[derive(Debug, PartialEq)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+// 评测机管理的本地依赖镜像（cargo vendor 生成的目录）
+pub struct VendorMirror {
+    dir: PathBuf,
+    crates: BTreeSet<VendoredCrate>,
+    git_sources: Vec<GitSource>,
+}
+
+impl VendorMirror {
+    // 从环境变量 JUDGE_VENDOR_DIR 读取镜像目录，默认 judge_vendor。
+    // 目录不存在时不启用镜像，编译时照常访问网络
+    pub fn from_env() -> Self {
+        let dir = PathBuf::from(std::env::var("JUDGE_VENDOR_DIR").unwrap_or_else(|_| "judge_vendor".to_string()));
+        let mut crates = BTreeSet::new();
+
+        match std::fs::read_dir(&dir) {
+            Ok(entries) => {
+                for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
+                    let manifest = entry.path().join("Cargo.toml");
+                    match std::fs::read_to_string(&manifest).ok().and_then(|content| parse_package(&content)) {
+                        Some((name, version)) => {
+                            let checksum = std::fs::read_to_string(entry.path().join(".cargo-checksum.json")).unwrap_or_default();
+                            crates.insert(VendoredCrate {
+                                name,
+                                version,
+                                source: checksum_source(&checksum),
+                            });
+                        }
+                        None => tracing::warn!("无法识别镜像中的crate: {}", entry.path().display()),
+                    }
+                }
+                tracing::info!("已加载依赖镜像 {}，共 {} 个crate", dir.display(), crates.len());
+            }
+            Err(e) => tracing::warn!("未找到依赖镜像目录 {}，评测编译将访问网络: {}", dir.display(), e),
+        }
+
+        let git_sources = std::fs::read_to_string(dir.join(SOURCES_FILE))
+            .map(|config| parse_git_sources(&config))
+            .unwrap_or_default();
+        if git_sources.is_empty() && crates.iter().any(|krate| krate.source == CrateSource::Git) {
+            tracing::warn!("依赖镜像中有 git 依赖，但 {} 中没有对应的 source 配置，git 依赖将不可用", SOURCES_FILE);
+        }
+
+        Self { dir, crates, git_sources }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.crates.is_empty()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn crates(&self) -> Vec<VendoredCrate> {
+        self.crates.iter().cloned().collect()
+    }
+
+    // 让cargo只从镜像读取crates.io和已镜像的git仓库上的依赖。
+    // 目录源在编译时会校验 .cargo-checksum.json，被改动的crate会直接报错而不会被悄悄使用
+    pub fn cargo_config(&self) -> String {
+        let dir = std::env::current_dir().map(|cwd| cwd.join(&self.dir)).unwrap_or_else(|_| self.dir.clone());
+        let mut config = String::from("[source.crates-io]\nreplace-with = \"judge-vendor\"\n\n");
+        for git in &self.git_sources {
+            config.push_str(&format!("[source.{}]\n", toml_string(&git.id)));
+            for (key, value) in &git.entries {
+                config.push_str(&format!("{} = {}\n", key, toml_string(value)));
+            }
+            config.push_str("replace-with = \"judge-vendor\"\n\n");
+        }
+        config.push_str(&format!(
+            "[source.judge-vendor]\ndirectory = {}\n\n[net]\noffline = true\n",
+            toml_string(&dir.to_string_lossy())
+        ));
+        config
+    }
+
+    // 检查学生工程 Cargo.lock 中不在镜像里的依赖
+    pub fn missing_dependencies(&self, work_dir: &Path) -> Vec<String> {
+        let mut missing = BTreeSet::new();
+        for lock_file in LOCK_FILES {
+            let content = match std::fs::read_to_string(work_dir.join(lock_file)) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            for package in parse_lock_packages(&content) {
+                let source = match package.source.as_deref() {
+                    // 工程内的路径依赖
+                    None => continue,
+                    Some(CRATES_IO_SOURCE) => Some(CrateSource::CratesIo),
+                    // git 依赖的来源形如 git+<地址>?rev=<版本>#<提交>，镜像配置中替换的是 # 之前的部分
+                    Some(source) if source.starts_with("git+") => {
+                        let id = source.split('#').next().unwrap_or(source);
+                        Some(CrateSource::Git).filter(|_| self.git_sources.iter().any(|git| git.id == id))
+                    }
+                    // 其他registry在离线环境下无法获取
+                    Some(_) => None,
+                };
+                let vendored = source.is_some_and(|source| {
+                    self.crates.contains(&VendoredCrate {
+                        name: package.name.clone(),
+                        version: package.version.clone(),
+                        source,
+                    })
+                });
+                if vendored {
+                    continue;
+                }
+                missing.insert(match package.source.as_deref() {
+                    Some(CRATES_IO_SOURCE) | None => format!("{} {}", package.name, package.version),
+                    Some(source) => format!("{} {} ({})", package.name, package.version, source),
+                });
+            }
+        }
+        missing.into_iter().collect()
+    }
+}
+
+// 判断编译失败是否是因为依赖不可用
+pub fn is_dependency_error(build_log: &str) -> bool {
+    OFFLINE_ERROR_PATTERNS.iter().any(|pattern| build_log.contains(pattern))
+}
+
+// 取出 key = "value" 中的值，值必须是带引号的字符串，引号后的注释会被忽略
+fn parse_string_value(line: &str, key: &str) -> Option<String> {
+    let (k, v) = line.split_once('=')?;
+    if k.trim() != key {
+        return None;
+    }
+    let (value, _) = v.trim().strip_prefix('"')?.split_once('"')?;
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.to_string())
+}
+
+// 写入 TOML 的字符串
+fn toml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// 从 Cargo.toml 的 [package] 段读取名称和版本
+fn parse_package(manifest: &str) -> Option<(String, String)> {
+    let mut in_package = false;
+    let mut name = None;
+    let mut version = None;
+
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        if in_package {
+            name = name.or_else(|| parse_string_value(line, "name"));
+            version = version.or_else(|| parse_string_value(line, "version"));
+        }
+    }
+
+    Some((name?, version?))
+}
+
+// git 依赖没有压缩包，cargo vendor 在 .cargo-checksum.json 中写入 "package": null
+fn checksum_source(checksum: &str) -> CrateSource {
+    let compact: String = checksum.split_whitespace().collect();
+    if compact.contains("\"package\":null") {
+        CrateSource::Git
+    } else {
+        CrateSource::CratesIo
+    }
+}
+
+// 从 cargo vendor 输出的配置中读取 git 源
+fn parse_git_sources(config: &str) -> Vec<GitSource> {
+    let mut sources = Vec::new();
+    let mut current: Option<GitSource> = None;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            sources.extend(current.take());
+            current = line
+                .strip_prefix("[source.\"")
+                .and_then(|rest| rest.strip_suffix("\"]"))
+                .filter(|id| id.starts_with("git+"))
+                .map(|id| GitSource {
+                    id: id.to_string(),
+                    entries: Vec::new(),
+                });
+            continue;
+        }
+        if let Some(source) = current.as_mut() {
+            for key in ["git", "rev", "branch", "tag"] {
+                if let Some(value) = parse_string_value(line, key) {
+                    source.entries.push((key.to_string(), value));
+                }
+            }
+        }
+    }
+    sources.extend(current);
+    sources.retain(|source| source.entries.iter().any(|(key, _)| key == "git"));
+    sources
+}
+
+// 解析 Cargo.lock 中的所有包及其来源
+fn parse_lock_packages(lock: &str) -> Vec<LockedPackage> {
+    let mut packages = Vec::new();
+    for block in lock.split("[[package]]").skip(1) {
+        let mut name = None;
+        let mut version = None;
+        let mut source = None;
+        for line in block.lines().map(str::trim) {
+            if line.starts_with('[') {
+                break;
+            }
+            name = name.or_else(|| parse_string_value(line, "name"));
+            version = version.or_else(|| parse_string_value(line, "version"));
+            source = source.or_else(|| parse_string_value(line, "source"));
+        }
+        if let (Some(name), Some(version)) = (name, version) {
+            packages.push(LockedPackage { name, version, source });
+        }
+    }
+    packages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn krate(name: &str, version: &str, source: CrateSource) -> VendoredCrate {
+        VendoredCrate {
+            name: name.to_string(),
+            version: version.to_string(),
+            source,
+        }
+    }
+
+    fn locked(name: &str, version: &str, source: Option<&str>) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: source.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_lock_packages_with_sources() {
+        let lock = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 3
+
+[[package]]
+name = "bitflags"
+version = "1.3.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bef38d45163c2f1dde094a7dfd33ccf595c92905c8f8f4fdc18d06fb1037718a"
+
+[[package]]
+name = "os"
+version = "0.1.0"
+dependencies = [
+ "bitflags",
+ "riscv",
+]
+
+[[package]]
+name = "riscv"
+version = "0.6.0"
+source = "git+https://github.com/rcore-os/riscv#11d43cf7cccb3b62a3ef4f1b0a3b1b8e0e1d5a5a"
+
+[metadata]
+name = "not-a-package"
+"#;
+        assert_eq!(
+            parse_lock_packages(lock),
+            vec![
+                locked("bitflags", "1.3.2", Some(CRATES_IO_SOURCE)),
+                locked("os", "0.1.0", None),
+                locked(
+                    "riscv",
+                    "0.6.0",
+                    Some("git+https://github.com/rcore-os/riscv#11d43cf7cccb3b62a3ef4f1b0a3b1b8e0e1d5a5a")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_lock_entries() {
+        let lock = r#"[[package]]
+name = "log"
+
+[[package]]
+name = buddy_system_allocator
+version = "0.6.0"
+
+[[package]]
+name = ""
+version = "1.0.0"
+
+[[package]]
+name = "spin" # trailing comment
+version = "0.7.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]
+name = "lazy_static"
+"#;
+        assert_eq!(
+            parse_lock_packages(lock),
+            vec![locked("spin", "0.7.1", Some(CRATES_IO_SOURCE))]
+        );
+        assert!(parse_lock_packages("not a lock file at all").is_empty());
+        assert!(parse_lock_packages("").is_empty());
+    }
+
+    #[test]
+    fn reports_dependencies_missing_from_the_mirror() {
+        let work_dir = std::env::temp_dir().join(format!("oj_vendor_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&work_dir);
+        std::fs::create_dir_all(work_dir.join("os")).unwrap();
+        std::fs::write(
+            work_dir.join("os/Cargo.lock"),
+            r#"[[package]]
+name = "bitflags"
+version = "1.3.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "log"
+version = "0.4.14"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "os"
+version = "0.1.0"
+
+[[package]]
+name = "riscv"
+version = "0.6.0"
+source = "git+https://github.com/rcore-os/riscv"
+"#,
+        )
+        .unwrap();
+        // 格式错误的 Cargo.lock 不应导致报错
+        std::fs::create_dir_all(work_dir.join("user")).unwrap();
+        std::fs::write(work_dir.join("user/Cargo.lock"), "[[package]]\nname = \"").unwrap();
+
+        let mirror = VendorMirror {
+            dir: work_dir.join("vendor"),
+            crates: [krate("bitflags", "1.3.2", CrateSource::CratesIo)].into_iter().collect(),
+            git_sources: Vec::new(),
+        };
+        assert_eq!(
+            mirror.missing_dependencies(&work_dir),
+            vec![
+                "log 0.4.14".to_string(),
+                "riscv 0.6.0 (git+https://github.com/rcore-os/riscv)".to_string(),
+            ]
+        );
+
+        std::fs::remove_dir_all(&work_dir).unwrap();
+    }
+
+    #[test]
+    fn parses_vendored_manifest() {
+        let manifest = r#"[package]
+edition = "2018"
+name = "spin"
+version = "0.7.1"
+
+[dependencies.lock_api]
+version = "0.4"
+optional = true
+"#;
+        assert_eq!(parse_package(manifest), Some(("spin".to_string(), "0.7.1".to_string())));
+        assert_eq!(parse_package("[dependencies]\nname = \"x\"\nversion = \"1\"\n"), None);
+    }
+
+    #[test]
+    fn vendors_git_dependencies_listed_in_sources() {
+        let work_dir = std::env::temp_dir().join(format!("oj_vendor_git_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&work_dir);
+        std::fs::create_dir_all(work_dir.join("os")).unwrap();
+        std::fs::write(
+            work_dir.join("os/Cargo.lock"),
+            r#"[[package]]
+name = "bitflags"
+version = "1.3.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "riscv"
+version = "0.6.0"
+source = "git+https://github.com/rcore-os/riscv#11d43cf7cccb3b62a3ef4f1b0a3b1b8e0e1d5a5a"
+
+[[package]]
+name = "virtio-drivers"
+version = "0.1.0"
+source = "git+https://github.com/rcore-os/virtio-drivers?rev=4ee80e5#4ee80e5a54ef1f1b5b8f8b2a5a8c1f6d2d1e3c4b"
+
+[[package]]
+name = "spin"
+version = "0.7.1"
+source = "git+https://github.com/mvdnes/spin-rs#aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+"#,
+        )
+        .unwrap();
+
+        // cargo vendor 的输出
+        let sources = r#"
+[source.crates-io]
+replace-with = "vendored-sources"
+
+[source."git+https://github.com/rcore-os/riscv"]
+git = "https://github.com/rcore-os/riscv"
+replace-with = "vendored-sources"
+
+[source."git+https://github.com/rcore-os/virtio-drivers?rev=4ee80e5"]
+git = "https://github.com/rcore-os/virtio-drivers"
+rev = "4ee80e5"
+replace-with = "vendored-sources"
+
+[source.vendored-sources]
+directory = "judge_vendor"
+"#;
+        let git_sources = parse_git_sources(sources);
+        assert_eq!(
+            git_sources,
+            vec![
+                GitSource {
+                    id: "git+https://github.com/rcore-os/riscv".to_string(),
+                    entries: vec![("git".to_string(), "https://github.com/rcore-os/riscv".to_string())],
+                },
+                GitSource {
+                    id: "git+https://github.com/rcore-os/virtio-drivers?rev=4ee80e5".to_string(),
+                    entries: vec![
+                        ("git".to_string(), "https://github.com/rcore-os/virtio-drivers".to_string()),
+                        ("rev".to_string(), "4ee80e5".to_string()),
+                    ],
+                },
+            ]
+        );
+
+        let mirror = VendorMirror {
+            dir: work_dir.join("vendor"),
+            crates: [
+                krate("bitflags", "1.3.2", CrateSource::CratesIo),
+                krate("riscv", "0.6.0", CrateSource::Git),
+                krate("virtio-drivers", "0.1.0", CrateSource::Git),
+                // 同名同版本但来自 crates.io 的 crate 不能替代 git 依赖
+                krate("spin", "0.7.1", CrateSource::CratesIo),
+            ]
+            .into_iter()
+            .collect(),
+            git_sources,
+        };
+        assert_eq!(
+            mirror.missing_dependencies(&work_dir),
+            vec!["spin 0.7.1 (git+https://github.com/mvdnes/spin-rs#aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa)".to_string()]
+        );
+
+        let config = mirror.cargo_config();
+        assert!(config.contains(
+            "[source.\"git+https://github.com/rcore-os/riscv\"]\ngit = \"https://github.com/rcore-os/riscv\"\nreplace-with = \"judge-vendor\"\n"
+        ));
+        assert!(config.contains(
+            "[source.\"git+https://github.com/rcore-os/virtio-drivers?rev=4ee80e5\"]\ngit = \"https://github.com/rcore-os/virtio-drivers\"\nrev = \"4ee80e5\"\nreplace-with = \"judge-vendor\"\n"
+        ));
+        assert!(config.contains("[source.crates-io]\nreplace-with = \"judge-vendor\"\n"));
+
+        // 镜像中没有 git 源的配置时，git 依赖都视为缺失
+        let mirror = VendorMirror {
+            git_sources: Vec::new(),
+            ..mirror
+        };
+        assert_eq!(mirror.missing_dependencies(&work_dir).len(), 3);
+
+        std::fs::remove_dir_all(&work_dir).unwrap();
+    }
+
+    #[test]
+    fn distinguishes_git_crates_by_checksum() {
+        assert_eq!(checksum_source(r#"{"files":{"src/lib.rs":"ab"},"package":null}"#), CrateSource::Git);
+        assert_eq!(checksum_source("{\"files\": {}, \"package\": null}"), CrateSource::Git);
+        assert_eq!(
+            checksum_source(r#"{"files":{},"package":"bef38d45163c2f1dde094a7dfd33ccf595c92905c8f8f4fdc18d06fb1037718a"}"#),
+            CrateSource::CratesIo
+        );
+    }
+}
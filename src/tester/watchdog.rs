@@ -0,0 +1,386 @@
+use crate::models::{CaseVerdict, TestCaseResult};
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const RUNNING_PREFIX: &str = "Usertests: Running ";
+const EXITED_PREFIX: &str = "Usertests: Test ";
+const EXITED_MARKER: &str = " exited with code ";
+
+// 看门狗的时间限制
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    pub case_timeout: Duration, // 单个用例的时间限制
+    pub idle_timeout: Duration, // 无输出的最长时间
+}
+
+impl WatchdogConfig {
+    // 从环境变量读取配置，未设置时使用默认值
+    pub fn from_env() -> Self {
+        let read_secs = |key: &str, default: u64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            case_timeout: Duration::from_secs(read_secs("JUDGE_CASE_TIMEOUT_SECS", 60)),
+            idle_timeout: Duration::from_secs(read_secs("JUDGE_IDLE_TIMEOUT_SECS", 30)),
+        }
+    }
+}
+
+// 测试包中定义的一个用例及其期望的退出码
+#[derive(Debug, Clone)]
+pub struct ExpectedCase {
+    pub name: String,
+    pub exit_code: i32,
+}
+
+// 从测试包的 usertests.rs 中解析用例列表（按运行顺序）
+pub fn load_expected_cases(user_dir: &Path) -> Vec<ExpectedCase> {
+    let source = match std::fs::read_to_string(user_dir.join("src/bin/usertests.rs")) {
+        Ok(source) => source,
+        Err(e) => {
+            tracing::warn!("无法读取usertests.rs，跳过用例解析: {}", e);
+            return Vec::new();
+        }
+    };
+
+    parse_expected_cases(&source)
+}
+
+// 用例形如 ("name\0", "\0", "\0", "\0", 0)：四个字符串和一个退出码。
+// 按词法单元匹配，注释掉的用例和字符串中的逗号、括号不影响解析
+fn parse_expected_cases(source: &str) -> Vec<ExpectedCase> {
+    let tokens = case_tokens(source);
+    (0..tokens.len()).filter_map(|i| parse_case(&tokens[i..])).collect()
+}
+
+fn parse_case(tokens: &[CaseToken]) -> Option<ExpectedCase> {
+    use CaseToken::{Number, Punct, Str};
+    let (name, rest) = match tokens {
+        [Punct('('), Str(name), Punct(','), Str(_), Punct(','), Str(_), Punct(','), Str(_), Punct(','), rest @ ..] => (name, rest),
+        _ => return None,
+    };
+    let (negative, code, rest) = match rest {
+        [Punct('-'), Number(code), rest @ ..] => (true, code, rest),
+        [Number(code), rest @ ..] => (false, code, rest),
+        _ => return None,
+    };
+    if !matches!(rest.first(), Some(Punct(')' | ','))) {
+        return None;
+    }
+    let code: i32 = code.parse().ok()?;
+    Some(ExpectedCase {
+        name: name.trim_end_matches("\\0").to_string(),
+        exit_code: if negative { -code } else { code },
+    })
+}
+
+// 解析用例列表所需的词法单元，空白和注释已被跳过
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CaseToken<'a> {
+    Str(&'a str), // 字符串字面量引号之间的原文
+    Number(&'a str),
+    Punct(char),
+    Other, // 标识符、字符字面量等
+}
+
+fn case_tokens(source: &str) -> Vec<CaseToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_whitespace() {
+            c.len_utf8()
+        } else if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            comment.find("*/").map_or(rest.len(), |end| end + 4)
+        } else if c == '"' {
+            // 找到没有被转义的右引号，字符串没有结束时忽略剩余部分
+            let mut escaped = false;
+            let close = rest.char_indices().skip(1).find(|&(_, ch)| {
+                let close = ch == '"' && !escaped;
+                escaped = ch == '\\' && !escaped;
+                close
+            });
+            match close {
+                Some((end, _)) => {
+                    tokens.push(CaseToken::Str(&rest[1..end]));
+                    end + 1
+                }
+                None => break,
+            }
+        } else if c.is_ascii_digit() || c.is_alphabetic() || c == '_' {
+            let len = rest.find(|ch: char| !ch.is_alphanumeric() && ch != '_').unwrap_or(rest.len());
+            tokens.push(if c.is_ascii_digit() { CaseToken::Number(&rest[..len]) } else { CaseToken::Other });
+            len
+        } else if let Some(len) = char_literal_len(rest) {
+            tokens.push(CaseToken::Other);
+            len
+        } else {
+            tokens.push(CaseToken::Punct(c));
+            c.len_utf8()
+        };
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+// rest 以字符字面量（如 '"'、'\''）开头时返回它的长度，生命周期标注返回 None
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices();
+    if chars.next()?.1 != '\'' {
+        return None;
+    }
+    let (_, first) = chars.next()?;
+    if first != '\\' {
+        let (end, close) = chars.next()?;
+        return Some(end + 1).filter(|_| close == '\'');
+    }
+    // 转义序列如 \u{4e2d} 可能有多个字符
+    chars.skip(1).take(8).find(|&(_, ch)| ch == '\'').map(|(end, _)| end + 1)
+}
+
+// 去掉用例名中的 \0 和颜色控制字符
+fn clean_name(name: &str) -> String {
+    name.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string()
+}
+
+struct RunningCase {
+    name: String,
+    started_at: Instant,
+}
+
+// 跟踪 usertests 的进度，判断是否卡死
+pub struct Watchdog {
+    config: WatchdogConfig,
+    expected: Vec<ExpectedCase>,
+    finished: Vec<TestCaseResult>,
+    current: Option<RunningCase>,
+    last_output_at: Instant,
+    partial_line: String,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig, expected: Vec<ExpectedCase>) -> Self {
+        Self {
+            config,
+            expected,
+            finished: Vec::new(),
+            current: None,
+            last_output_at: Instant::now(),
+            partial_line: String::new(),
+        }
+    }
+
+    // 处理新的一段输出
+    pub fn feed(&mut self, chunk: &str) {
+        self.last_output_at = Instant::now();
+        self.partial_line.push_str(chunk);
+
+        while let Some(pos) = self.partial_line.find('\n') {
+            let line: String = self.partial_line.drain(..=pos).collect();
+            self.handle_line(&line);
+        }
+    }
+
+    fn handle_line(&mut self, line: &str) {
+        if let Some(idx) = line.find(RUNNING_PREFIX) {
+            let name = clean_name(&line[idx + RUNNING_PREFIX.len()..]);
+            self.current = Some(RunningCase {
+                name,
+                started_at: Instant::now(),
+            });
+        } else if let Some(idx) = line.find(EXITED_PREFIX) {
+            let rest = &line[idx + EXITED_PREFIX.len()..];
+            let name = clean_name(rest.split(" in Process ").next().unwrap_or(rest));
+            let exit_code = rest.find(EXITED_MARKER).and_then(|pos| {
+                let code: String = rest[pos + EXITED_MARKER.len()..]
+                    .chars()
+                    .take_while(|c| *c == '-' || c.is_ascii_digit())
+                    .collect();
+                code.parse::<i32>().ok()
+            });
+
+            let duration_ms = self
+                .current
+                .take()
+                .filter(|case| case.name == name)
+                .map(|case| case.started_at.elapsed().as_millis() as i64);
+
+            let expected_code = self
+                .expected
+                .iter()
+                .find(|case| case.name == name)
+                .map(|case| case.exit_code)
+                .unwrap_or(0);
+            let verdict = if exit_code == Some(expected_code) {
+                CaseVerdict::Passed
+            } else {
+                CaseVerdict::Failed
+            };
+
+            self.finished.push(TestCaseResult {
+                name,
+                verdict,
+                exit_code,
+                duration_ms,
+            });
+        }
+    }
+
+    // 下一次需要检查的时间点
+    pub fn deadline(&self) -> Instant {
+        let idle_deadline = self.last_output_at + self.config.idle_timeout;
+        match &self.current {
+            Some(case) => idle_deadline.min(case.started_at + self.config.case_timeout),
+            None => idle_deadline,
+        }
+    }
+
+    // 检查是否触发了超时，返回原因
+    pub fn check(&self, now: Instant) -> Option<String> {
+        if let Some(case) = &self.current {
+            if now >= case.started_at + self.config.case_timeout {
+                return Some(format!(
+                    "用例 {} 超过单用例时间限制 {}s",
+                    case.name,
+                    self.config.case_timeout.as_secs()
+                ));
+            }
+        }
+        if now >= self.last_output_at + self.config.idle_timeout {
+            return Some(format!("超过 {}s 没有任何输出", self.config.idle_timeout.as_secs()));
+        }
+        None
+    }
+
+    // 生成最终的用例结果：已完成的用例保持原结果，
+    // 正在运行的用例根据 interrupted_verdict 标记，其余未运行
+    pub fn into_results(self, interrupted_verdict: CaseVerdict) -> Vec<TestCaseResult> {
+        let mut results = self.finished;
+
+        if let Some(case) = self.current {
+            results.push(TestCaseResult {
+                duration_ms: Some(case.started_at.elapsed().as_millis() as i64),
+                name: case.name,
+                verdict: interrupted_verdict,
+                exit_code: None,
+            });
+        }
+
+        for case in self.expected {
+            if !results.iter().any(|r| r.name == case.name) {
+                results.push(TestCaseResult {
+                    name: case.name,
+                    verdict: CaseVerdict::NotRun,
+                    exit_code: None,
+                    duration_ms: None,
+                });
+            }
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig {
+            case_timeout: Duration::from_secs(60),
+            idle_timeout: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn parses_case_tuples_from_usertests() {
+        let source = r#"
+// item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
+fn quote<'a>(s: &'a str) -> (char, char) { ('"', '\'') }
+static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
+    ("filetest_simple\0", "\0", "\0", "\0", 0),
+    ("cat_filea\0", "a,b)\0", "\0", "\0", 0),
+    // ("disabled\0", "\0", "\0", "\0", 0),
+    /* ("blocked\0", "\0", "\0", "\0", 0), */
+    ("escaped\"quote\0", "\0", "\0", "\0", 1),
+];
+static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[("stack_overflow\0", "\0", "\0", "\0", -2,)];
+fn main() { println!("Usertests: Running {}", test.0); }
+"#;
+        let cases: Vec<(String, i32)> = parse_expected_cases(source)
+            .into_iter()
+            .map(|case| (case.name, case.exit_code))
+            .collect();
+        assert_eq!(
+            cases,
+            [
+                ("filetest_simple".to_string(), 0),
+                ("cat_filea".to_string(), 0),
+                ("escaped\\\"quote".to_string(), 1),
+                ("stack_overflow".to_string(), -2),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_the_bundled_usertests() {
+        let cases = load_expected_cases(Path::new("user"));
+        assert!(cases.iter().any(|case| case.name == "filetest_simple" && case.exit_code == 0));
+        assert!(cases.iter().any(|case| case.name == "stack_overflow" && case.exit_code == -2));
+    }
+
+    #[tokio::test]
+    async fn tracks_cases_from_split_output() {
+        let expected = parse_expected_cases(r#"[("hello\0", "\0", "\0", "\0", 0), ("stack_overflow\0", "\0", "\0", "\0", -2), ("sleep\0", "\0", "\0", "\0", 0)]"#);
+        let mut watchdog = Watchdog::new(config(), expected);
+        watchdog.feed("Usertests: Running hello\0\n\x1b[32mUsertests: Test hello\0 in Pro");
+        watchdog.feed("cess 2 exited with code 0\x1b[0m\nUsertests: Running stack_overflow\0\n");
+        watchdog.feed("Usertests: Test stack_overflow\0 in Process 3 exited with code -2\n");
+        watchdog.feed("Usertests: Running sleep\0\n");
+        assert!(watchdog.check(Instant::now()).is_none());
+
+        let results: Vec<(String, CaseVerdict, Option<i32>)> = watchdog
+            .into_results(CaseVerdict::TimeLimitExceeded)
+            .into_iter()
+            .map(|case| (case.name, case.verdict, case.exit_code))
+            .collect();
+        assert_eq!(
+            results,
+            [
+                ("hello".to_string(), CaseVerdict::Passed, Some(0)),
+                ("stack_overflow".to_string(), CaseVerdict::Passed, Some(-2)),
+                ("sleep".to_string(), CaseVerdict::TimeLimitExceeded, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_case_and_idle_timeouts() {
+        let mut watchdog = Watchdog::new(
+            WatchdogConfig {
+                case_timeout: Duration::from_secs(10),
+                idle_timeout: Duration::from_secs(5),
+            },
+            Vec::new(),
+        );
+        let before = Instant::now();
+        watchdog.feed("Usertests: Running sleep\0\n");
+        let after = Instant::now();
+
+        // 两个时间限制中较早的一个
+        assert!(watchdog.deadline() >= before + Duration::from_secs(5));
+        assert!(watchdog.deadline() <= after + Duration::from_secs(5));
+        assert!(watchdog.check(before + Duration::from_secs(4)).is_none());
+        assert_eq!(watchdog.check(after + Duration::from_secs(5)).unwrap(), "超过 5s 没有任何输出");
+        assert_eq!(
+            watchdog.check(after + Duration::from_secs(10)).unwrap(),
+            "用例 sleep 超过单用例时间限制 10s"
+        );
+    }
+}